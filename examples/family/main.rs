@@ -7,19 +7,39 @@ fn main() {
     let bytes = include_bytes!("family.stp");
     match iso_10303::step::parser::exchange_file().parse(bytes) {
         Ok(file) => {
-            println!("entities: {}", file.data.len());
-            println!("{:?}", file.data);
+            println!("entities: {}", file.data[0].instances.len());
+            println!("{:?}", file.data[0].instances);
         }
         Err(err) => println!("{:?}", err),
     }
 
     let mut reader = ExampleReader::new();
     if reader.read("examples/family/family.stp").is_ok() {
-        for male in reader.get_entities::<Male>() {
+        for error in reader.validate() {
+            println!("{}", error);
+        }
+        for (id, male) in reader.get_entities::<Male>() {
             println!("{:?}", male);
+            if let Some(wife) = male.wife().as_ref().and_then(|wife| reader.resolve(wife)) {
+                println!("wife: {}", wife.first_name());
+            }
+            for parent in reader
+                .person_parents(id)
+                .iter()
+                .filter_map(|parent| reader.resolve(parent))
+            {
+                println!("parent: {}", parent.first_name());
+            }
         }
-        for female in reader.get_entities::<Female>() {
+        for (id, female) in reader.get_entities::<Female>() {
             println!("{:?}", female);
+            for husband in reader
+                .female_husband(id)
+                .iter()
+                .filter_map(|husband| reader.resolve(husband))
+            {
+                println!("husband: {}", husband.first_name());
+            }
         }
     }
 }
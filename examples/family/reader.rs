@@ -1,60 +1,85 @@
 #![doc = r" This file is generated. Do not edit."]
-#![allow(dead_code)]
+#![allow(dead_code, unused_imports, clippy::single_match)]
 use iso_10303::step::*;
 use std::collections::HashSet;
+use std::convert::{TryFrom, TryInto};
 #[derive(Default, Debug)]
 pub struct Unimplemented {}
-impl From<Parameter> for Unimplemented {
-    fn from(_parameter: Parameter) -> Self {
-        Unimplemented {}
+impl TryFrom<Parameter> for Unimplemented {
+    type Error = ConversionError;
+    fn try_from(_parameter: Parameter) -> Result<Self, Self::Error> {
+        Ok(Unimplemented {})
+    }
+}
+impl ToParameter for Unimplemented {
+    fn to_parameter(&self) -> Parameter {
+        Parameter::UnTypedParameter(UnTypedParameter::Null)
     }
 }
 type Date = Vec<i64>;
-#[derive(Eq, PartialEq, Hash, Debug)]
+#[derive(Default, Eq, PartialEq, Hash, Debug)]
 pub enum HairType {
+    #[default]
     Blonde,
     Brown,
     Black,
     Red,
     White,
 }
-impl Default for HairType {
-    fn default() -> Self {
-        HairType::Blonde
-    }
-}
-impl From<String> for HairType {
-    fn from(value: String) -> Self {
+impl TryFrom<String> for HairType {
+    type Error = ConversionError;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
         match value.as_str() {
-            "BLONDE" => HairType::Blonde,
-            "BROWN" => HairType::Brown,
-            "BLACK" => HairType::Black,
-            "RED" => HairType::Red,
-            "WHITE" => HairType::White,
-            _ => panic!("{} is not a valid value", value),
+            "BLONDE" => Ok(HairType::Blonde),
+            "BROWN" => Ok(HairType::Brown),
+            "BLACK" => Ok(HairType::Black),
+            "RED" => Ok(HairType::Red),
+            "WHITE" => Ok(HairType::White),
+            _ => Err(ConversionError::new("hairType", format!(".{}.", value))),
         }
     }
 }
-impl From<Parameter> for HairType {
-    fn from(parameter: Parameter) -> Self {
+impl TryFrom<Parameter> for HairType {
+    type Error = ConversionError;
+    fn try_from(parameter: Parameter) -> Result<Self, Self::Error> {
         match parameter {
-            Parameter::UnTypedParameter(untyped_parameter) => match untyped_parameter {
-                UnTypedParameter::EnumValue(value) => value.into(),
-                _ => panic!("parameter is not an enum value"),
-            },
-            Parameter::OmittedParameter => HairType::default(),
-            _ => panic!("parameter is not an enum value"),
+            Parameter::UnTypedParameter(UnTypedParameter::EnumValue(value)) => value.try_into(),
+            Parameter::OmittedParameter => Ok(HairType::default()),
+            _ => Err(ConversionError::unexpected("enum value", &parameter)),
         }
     }
 }
+impl ToParameter for HairType {
+    fn to_parameter(&self) -> Parameter {
+        let value = match self {
+            HairType::Blonde => "BLONDE",
+            HairType::Brown => "BROWN",
+            HairType::Black => "BLACK",
+            HairType::Red => "RED",
+            HairType::White => "WHITE",
+        };
+        Parameter::UnTypedParameter(UnTypedParameter::EnumValue(value.to_string()))
+    }
+}
 pub trait IPerson {
     fn first_name(&self) -> &String;
     fn last_name(&self) -> &String;
     fn nickname(&self) -> &Option<String>;
     fn birth_date(&self) -> &Date;
-    fn children(&self) -> &HashSet<EntityRef>;
+    fn children(&self) -> &HashSet<TypedRef<dyn IPerson>>;
     fn hair(&self) -> &HairType;
 }
+impl FromEntity for dyn IPerson {
+    fn from_entity(entity: &dyn Any) -> Option<&Self> {
+        if let Some(entity) = entity.downcast_ref::<Female>() {
+            return Some(entity);
+        }
+        if let Some(entity) = entity.downcast_ref::<Male>() {
+            return Some(entity);
+        }
+        None
+    }
+}
 pub trait IFemale: IPerson {}
 #[derive(Default, Debug)]
 pub struct Female {
@@ -62,7 +87,7 @@ pub struct Female {
     last_name: String,
     nickname: Option<String>,
     birth_date: Date,
-    children: HashSet<EntityRef>,
+    children: HashSet<TypedRef<dyn IPerson>>,
     hair: HairType,
 }
 impl IPerson for Female {
@@ -78,7 +103,7 @@ impl IPerson for Female {
     fn birth_date(&self) -> &Date {
         &self.birth_date
     }
-    fn children(&self) -> &HashSet<EntityRef> {
+    fn children(&self) -> &HashSet<TypedRef<dyn IPerson>> {
         &self.children
     }
     fn hair(&self) -> &HairType {
@@ -87,30 +112,65 @@ impl IPerson for Female {
 }
 impl IFemale for Female {}
 impl Female {
-    pub fn from_parameters(parameters: Vec<Parameter>) -> Self {
-        let mut entity = Female::default();
+    pub fn from_parameters(parameters: Vec<Parameter>) -> Result<Self, (usize, ConversionError)> {
+        if parameters.len() != 6usize {
+            let error = ConversionError::new("6 parameters", format!("{} parameters", parameters.len()));
+            return Err((parameters.len().min(6usize), error));
+        }
+        let mut entity = Self::default();
         for (index, parameter) in parameters.into_iter().enumerate() {
             match index {
-                0usize => entity.first_name = parameter.into(),
-                1usize => entity.last_name = parameter.into(),
+                0usize => entity.first_name = parameter.try_into().map_err(|error| (index, error))?,
+                1usize => entity.last_name = parameter.try_into().map_err(|error| (index, error))?,
                 2usize => {
                     entity.nickname = if parameter.is_null() {
                         None
                     } else {
-                        Some(parameter.into())
+                        Some(parameter.try_into().map_err(|error| (index, error))?)
                     }
                 }
-                3usize => entity.birth_date = parameter.into(),
-                4usize => entity.children = parameter.into(),
-                5usize => entity.hair = parameter.into(),
+                3usize => entity.birth_date = parameter.try_into().map_err(|error| (index, error))?,
+                4usize => entity.children = parameter.try_into().map_err(|error| (index, error))?,
+                5usize => entity.hair = parameter.try_into().map_err(|error| (index, error))?,
                 _ => {}
             }
         }
-        entity
+        Ok(entity)
+    }
+    pub fn from_own_parameters(parameters: Vec<Parameter>) -> Result<Self, (usize, ConversionError)> {
+        if !parameters.is_empty() {
+            return Err((
+                0,
+                ConversionError::new("0 parameters", format!("{} parameters", parameters.len())),
+            ));
+        }
+        Ok(Self::default())
+    }
+}
+impl ToParameters for Female {
+    const TYPE_NAME: &'static str = "FEMALE";
+    fn to_parameters(&self) -> Vec<Parameter> {
+        vec![
+            self.first_name.to_parameter(),
+            self.last_name.to_parameter(),
+            optional_parameter(&self.nickname),
+            self.birth_date.to_parameter(),
+            self.children.to_parameter(),
+            self.hair.to_parameter(),
+        ]
+    }
+    fn to_own_parameters(&self) -> Vec<Parameter> {
+        vec![]
+    }
+}
+impl AttributeReferences for Female {
+    fn attribute_references(&self, visit: &mut dyn FnMut(&'static str, &'static str, i64, EntityCheck)) {
+        self.children
+            .references(&mut |id, check| visit("children", "PERSON", id, check.unwrap_or(|_| true)));
     }
 }
 pub trait IMale: IPerson {
-    fn wife(&self) -> &Option<EntityRef>;
+    fn wife(&self) -> &Option<TypedRef<Female>>;
 }
 #[derive(Default, Debug)]
 pub struct Male {
@@ -118,9 +178,9 @@ pub struct Male {
     last_name: String,
     nickname: Option<String>,
     birth_date: Date,
-    children: HashSet<EntityRef>,
+    children: HashSet<TypedRef<dyn IPerson>>,
     hair: HairType,
-    wife: Option<EntityRef>,
+    wife: Option<TypedRef<Female>>,
 }
 impl IPerson for Male {
     fn first_name(&self) -> &String {
@@ -135,7 +195,7 @@ impl IPerson for Male {
     fn birth_date(&self) -> &Date {
         &self.birth_date
     }
-    fn children(&self) -> &HashSet<EntityRef> {
+    fn children(&self) -> &HashSet<TypedRef<dyn IPerson>> {
         &self.children
     }
     fn hair(&self) -> &HairType {
@@ -143,54 +203,87 @@ impl IPerson for Male {
     }
 }
 impl IMale for Male {
-    fn wife(&self) -> &Option<EntityRef> {
+    fn wife(&self) -> &Option<TypedRef<Female>> {
         &self.wife
     }
 }
 impl Male {
-    pub fn from_parameters(parameters: Vec<Parameter>) -> Self {
-        let mut entity = Male::default();
+    pub fn from_parameters(parameters: Vec<Parameter>) -> Result<Self, (usize, ConversionError)> {
+        if parameters.len() != 7usize {
+            let error = ConversionError::new("7 parameters", format!("{} parameters", parameters.len()));
+            return Err((parameters.len().min(7usize), error));
+        }
+        let mut entity = Self::default();
         for (index, parameter) in parameters.into_iter().enumerate() {
             match index {
-                0usize => entity.first_name = parameter.into(),
-                1usize => entity.last_name = parameter.into(),
+                0usize => entity.first_name = parameter.try_into().map_err(|error| (index, error))?,
+                1usize => entity.last_name = parameter.try_into().map_err(|error| (index, error))?,
                 2usize => {
                     entity.nickname = if parameter.is_null() {
                         None
                     } else {
-                        Some(parameter.into())
+                        Some(parameter.try_into().map_err(|error| (index, error))?)
                     }
                 }
-                3usize => entity.birth_date = parameter.into(),
-                4usize => entity.children = parameter.into(),
-                5usize => entity.hair = parameter.into(),
+                3usize => entity.birth_date = parameter.try_into().map_err(|error| (index, error))?,
+                4usize => entity.children = parameter.try_into().map_err(|error| (index, error))?,
+                5usize => entity.hair = parameter.try_into().map_err(|error| (index, error))?,
                 6usize => {
                     entity.wife = if parameter.is_null() {
                         None
                     } else {
-                        Some(parameter.into())
+                        Some(parameter.try_into().map_err(|error| (index, error))?)
                     }
                 }
                 _ => {}
             }
         }
-        entity
+        Ok(entity)
     }
-    pub fn from_own_parameters(parameters: Vec<Parameter>) -> Self {
-        let mut entity = Male::default();
+    pub fn from_own_parameters(parameters: Vec<Parameter>) -> Result<Self, (usize, ConversionError)> {
+        if parameters.len() != 1usize {
+            let error = ConversionError::new("1 parameters", format!("{} parameters", parameters.len()));
+            return Err((parameters.len().min(1usize), error));
+        }
+        let mut entity = Self::default();
         for (index, parameter) in parameters.into_iter().enumerate() {
             match index {
                 0usize => {
                     entity.wife = if parameter.is_null() {
                         None
                     } else {
-                        Some(parameter.into())
+                        Some(parameter.try_into().map_err(|error| (index, error))?)
                     }
                 }
                 _ => {}
             }
         }
-        entity
+        Ok(entity)
+    }
+}
+impl ToParameters for Male {
+    const TYPE_NAME: &'static str = "MALE";
+    fn to_parameters(&self) -> Vec<Parameter> {
+        vec![
+            self.first_name.to_parameter(),
+            self.last_name.to_parameter(),
+            optional_parameter(&self.nickname),
+            self.birth_date.to_parameter(),
+            self.children.to_parameter(),
+            self.hair.to_parameter(),
+            optional_parameter(&self.wife),
+        ]
+    }
+    fn to_own_parameters(&self) -> Vec<Parameter> {
+        vec![optional_parameter(&self.wife)]
+    }
+}
+impl AttributeReferences for Male {
+    fn attribute_references(&self, visit: &mut dyn FnMut(&'static str, &'static str, i64, EntityCheck)) {
+        self.children
+            .references(&mut |id, check| visit("children", "PERSON", id, check.unwrap_or(|_| true)));
+        self.wife
+            .references(&mut |id, check| visit("wife", "FEMALE", id, check.unwrap_or(|_| true)));
     }
 }
 use std::any::{Any, TypeId};
@@ -200,27 +293,48 @@ pub struct ExampleReader {
     pub type_ids: HashMap<TypeId, Vec<i64>>,
     pub type_names: HashMap<TypeId, &'static str>,
     empty: Vec<i64>,
+    duplicate_ids: Vec<i64>,
+    visitors: HashMap<TypeId, ReferenceVisitor>,
+    used_in: UsedInIndex,
 }
 impl ExampleReader {
     pub fn new() -> Self {
+        let mut visitors = HashMap::new();
+        visitors.insert(TypeId::of::<Female>(), reference_visitor::<Female> as ReferenceVisitor);
+        visitors.insert(TypeId::of::<Male>(), reference_visitor::<Male> as ReferenceVisitor);
         ExampleReader {
             entities: BTreeMap::new(),
             type_ids: HashMap::new(),
             type_names: HashMap::new(),
             empty: Vec::new(),
+            duplicate_ids: Vec::new(),
+            visitors,
+            used_in: UsedInIndex::default(),
         }
     }
+    #[doc = r" Add an entity instance, replacing the instance with the same id."]
     pub fn add_entity<T: Any>(&mut self, id: i64, entity: T) {
+        self.remove_entity(id);
         let type_id = entity.type_id();
-        self.entities.insert(id, Box::new(entity));
-        self.type_ids.entry(type_id).or_insert(vec![]).push(id);
-        self.type_names.entry(type_id).or_insert(std::any::type_name::<T>());
+        self.insert_entity(id, type_id, std::any::type_name::<T>(), Box::new(entity));
+    }
+    fn remove_entity(&mut self, id: i64) {
+        if let Some(entity) = self.entities.remove(&id) {
+            self.used_in.remove(id, &*entity, &self.visitors);
+            if let Some(ids) = self.type_ids.get_mut(&(*entity).type_id()) {
+                ids.retain(|other| *other != id);
+            }
+        }
     }
     pub fn get_entity<T: Any>(&self, entity_ref: &EntityRef) -> Option<&T> {
         self.entities
             .get(&entity_ref.0)
-            .map(|entity| entity.downcast_ref::<T>())
-            .flatten()
+            .and_then(|entity| entity.downcast_ref::<T>())
+    }
+    pub fn resolve<T: FromEntity + ?Sized>(&self, entity_ref: &TypedRef<T>) -> Option<&T> {
+        self.entities
+            .get(&entity_ref.id())
+            .and_then(|entity| T::resolve(&**entity))
     }
     pub fn get_entities<T: Any>(&self) -> impl Iterator<Item = (i64, &T)> {
         let type_id = TypeId::of::<T>();
@@ -234,35 +348,107 @@ impl ExampleReader {
         let type_id = (*self.entities[&id]).type_id();
         self.type_names[&type_id]
     }
+    pub fn validate(&self) -> Vec<StepError> {
+        let mut errors = self
+            .duplicate_ids
+            .iter()
+            .map(|&id| StepError::DuplicateId { id })
+            .collect::<Vec<_>>();
+        errors.extend(validate_references(&self.entities, &self.visitors));
+        errors
+    }
+    pub fn used_in(&self, id: i64) -> &[(i64, &'static str)] {
+        self.used_in.used_in(id)
+    }
+    pub fn used_in_typed<T: FromEntity + ?Sized>(&self, id: i64, attribute: &str) -> Vec<TypedRef<T>> {
+        self.used_in.used_in_typed(&self.entities, id, attribute)
+    }
+    pub fn person_parents(&self, id: i64) -> Vec<TypedRef<dyn IPerson>> {
+        let mut refs = self.used_in_typed(id, "children");
+        refs.dedup();
+        refs
+    }
+    pub fn female_husband(&self, id: i64) -> Vec<TypedRef<Male>> {
+        let mut refs = self.used_in_typed(id, "wife");
+        refs.dedup();
+        refs
+    }
+    pub fn person_age(&self, id: i64) -> Option<i64> {
+        let entity = self.resolve(&TypedRef::<dyn IPerson>::new(id))?;
+        functions::years(self, IPerson::birth_date(entity))
+    }
+}
+impl Default for ExampleReader {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 impl StepReader for ExampleReader {
     fn insert_entity(&mut self, id: i64, type_id: TypeId, type_name: &'static str, entity: Box<dyn Any>) {
+        if self.entities.contains_key(&id) {
+            self.duplicate_ids.push(id);
+            return;
+        }
+        self.used_in.insert(id, &*entity, &self.visitors);
         self.entities.insert(id, entity);
         self.type_ids.entry(type_id).or_insert(vec![]).push(id);
         self.type_names.entry(type_id).or_insert(type_name);
     }
-    fn create_simple_entity(
-        &self,
-        typed_parameter: TypedParameter,
-        own_parameters_only: bool,
-    ) -> Option<(TypeId, &'static str, Box<dyn Any>)> {
+    fn create_simple_entity(id: i64, typed_parameter: TypedParameter, own_parameters_only: bool) -> EntityResult {
         match typed_parameter.type_name.as_str() {
             "FEMALE" => {
-                let entity = Female::from_parameters(typed_parameter.parameters);
-                Some((entity.type_id(), std::any::type_name::<Female>(), Box::new(entity)))
+                let entity = if own_parameters_only {
+                    Female::from_own_parameters(typed_parameter.parameters)
+                } else {
+                    Female::from_parameters(typed_parameter.parameters)
+                }
+                .map_err(|(index, error)| StepError::Conversion { id, index, error })?;
+                Ok((entity.type_id(), std::any::type_name::<Female>(), Box::new(entity)))
             }
             "MALE" => {
                 let entity = if own_parameters_only {
                     Male::from_own_parameters(typed_parameter.parameters)
                 } else {
                     Male::from_parameters(typed_parameter.parameters)
-                };
-                Some((entity.type_id(), std::any::type_name::<Male>(), Box::new(entity)))
-            }
-            _ => {
-                println!("{} is not implemented", typed_parameter.type_name);
-                None
+                }
+                .map_err(|(index, error)| StepError::Conversion { id, index, error })?;
+                Ok((entity.type_id(), std::any::type_name::<Male>(), Box::new(entity)))
             }
+            _ if own_parameters_only => Ok((
+                TypeId::of::<TypedParameter>(),
+                std::any::type_name::<TypedParameter>(),
+                Box::new(typed_parameter),
+            )),
+            _ => Err(StepError::UnknownEntity {
+                id,
+                type_name: typed_parameter.type_name,
+            }),
         }
     }
 }
+impl StepWriter for ExampleReader {
+    fn schema_name(&self) -> &'static str {
+        "EXAMPLE"
+    }
+    fn entities(&self) -> &BTreeMap<i64, Box<dyn Any>> {
+        &self.entities
+    }
+    fn typed_parameter_writers(&self) -> HashMap<TypeId, TypedParameterWriter> {
+        let mut writers = HashMap::new();
+        writers.insert(
+            TypeId::of::<Female>(),
+            typed_parameter_writer::<Female> as TypedParameterWriter,
+        );
+        writers.insert(
+            TypeId::of::<Male>(),
+            typed_parameter_writer::<Male> as TypedParameterWriter,
+        );
+        writers
+    }
+}
+mod functions {
+    use super::*;
+    pub fn years(_reader: &ExampleReader, _past: &[i64]) -> Option<i64> {
+        None
+    }
+}
@@ -1,5 +1,4 @@
 use iso_10303::step::StepReader;
-use iso_10303_parts::ap203;
 use iso_10303_parts::ap214;
 
 fn main() {
@@ -9,11 +8,19 @@ fn main() {
     let mut parsing_time = 0.0;
     let mut reader = ap214::Ap214Reader::new();
     match reader.read(input_file) {
-        Ok(_) => {
+        Ok(report) => {
             parsing_time = instant.elapsed().as_secs_f64();
+            println!("{:?}", report.header);
+            for diagnostic in &report.diagnostics {
+                println!("{}", diagnostic);
+            }
             for context in reader.get_entities::<ap214::ApplicationContext>() {
                 println!("{:?}", context);
             }
+            for (id, product) in reader.get_entities::<ap214::Product>() {
+                let formations = reader.used_in_typed::<dyn ap214::IProductDefinitionFormation>(id, "of_product");
+                println!("{:?} - formations: {:?}", product, formations);
+            }
             let mut total = 0;
             for (type_id, entity_ids) in reader.type_ids {
                 println!("{:?} - {} ({})", type_id, reader.type_names[&type_id], entity_ids.len());
@@ -21,7 +28,7 @@ fn main() {
             }
             println!("simple entities: {}", total);
         }
-        Err(err) => println!("{:?}", err),
+        Err(err) => println!("{}", err),
     }
     println!("elapsed time: {} seconds", parsing_time);
 
@@ -1,3 +1,59 @@
+use iso_10303::step::{Header, InstanceStream, ReadReport, StepError, StepReader};
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
 pub mod ap203;
 pub mod ap214;
 pub mod ifc4;
+
+/// A typed model of one of the supported schemas.
+pub enum Model {
+    Ap203(ap203::Ap203Reader),
+    Ap214(ap214::Ap214Reader),
+    Ifc4(ifc4::Ifc4Reader),
+}
+
+impl Model {
+    /// Create an empty model for a `FILE_SCHEMA` name.
+    pub fn for_schema(name: &str) -> Option<Model> {
+        match name.to_uppercase().as_str() {
+            "CONFIG_CONTROL_DESIGN"
+            | "AP203_CONFIGURATION_CONTROLLED_3D_DESIGN_OF_MECHANICAL_PARTS_AND_ASSEMBLIES_MIM_LF" => {
+                Some(Model::Ap203(ap203::Ap203Reader::new()))
+            }
+            "AUTOMOTIVE_DESIGN" => Some(Model::Ap214(ap214::Ap214Reader::new())),
+            "IFC4" => Some(Model::Ifc4(ifc4::Ifc4Reader::new())),
+            _ => None,
+        }
+    }
+
+    /// Read a STEP file with the reader of the first supported schema in its header.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<(Model, ReadReport), StepError> {
+        let file = std::fs::File::open(path)?;
+        Model::read_from(BufReader::with_capacity(0x10000, file))
+    }
+
+    /// Read an exchange file like `read`, parsing the header only once.
+    pub fn read_from<R: BufRead>(reader: R) -> Result<(Model, ReadReport), StepError> {
+        let instances = InstanceStream::new(reader)?;
+        let (header, _) = Header::from_entities(instances.header().to_vec());
+        let mut model = header
+            .schema_identifiers
+            .iter()
+            .find_map(|schema| Model::for_schema(&schema.name))
+            .ok_or_else(|| StepError::UnsupportedSchema {
+                schemas: header
+                    .schema_identifiers
+                    .iter()
+                    .map(|schema| schema.name.clone())
+                    .collect(),
+            })?;
+        // the header diagnostics are reported by the reader of the model
+        let report = match &mut model {
+            Model::Ap203(reader) => reader.read_stream(instances)?,
+            Model::Ap214(reader) => reader.read_stream(instances)?,
+            Model::Ifc4(reader) => reader.read_stream(instances)?,
+        };
+        Ok((model, report))
+    }
+}
@@ -0,0 +1,49 @@
+use iso_10303::step::StepError;
+use iso_10303_parts::Model;
+
+fn file(schemas: &str) -> String {
+    format!(
+        "ISO-10303-21;
+HEADER;
+FILE_DESCRIPTION((''), '2;1');
+FILE_NAME('', '', (''), (''), '', '', '');
+FILE_SCHEMA(({}));
+SECTION_LANGUAGE(1, 'EN');
+ENDSEC;
+DATA;
+#1=IFCCARTESIANPOINT((0.,0.,0.));
+ENDSEC;
+END-ISO-10303-21;
+",
+        schemas
+    )
+}
+
+#[test]
+fn model_of_the_first_supported_schema() {
+    let (model, report) = Model::read_from(file("'UNKNOWN', 'IFC4'").as_bytes()).unwrap();
+    let reader = match model {
+        Model::Ifc4(reader) => reader,
+        _ => panic!("IFC4 model expected"),
+    };
+    assert_eq!(reader.entities.len(), 1);
+    // the header is read once, its diagnostics are kept
+    assert_eq!(report.header.schema_identifiers.len(), 2);
+    assert_eq!(report.diagnostics.len(), 1);
+    assert!(matches!(&report.diagnostics[0], StepError::Header { type_name, .. } if type_name == "SECTION_LANGUAGE"));
+}
+
+#[test]
+fn unsupported_schema_is_an_error() {
+    match Model::read_from(file("'UNKNOWN', 'OTHER { 1 2 3 }'").as_bytes()) {
+        Err(StepError::UnsupportedSchema { schemas }) => assert_eq!(schemas, ["UNKNOWN", "OTHER"]),
+        _ => panic!("UnsupportedSchema expected"),
+    }
+}
+
+#[test]
+fn model_of_a_file() {
+    let (model, report) = Model::read("examples/ap214_example.stp").unwrap();
+    assert!(matches!(model, Model::Ap214(_)));
+    assert!(report.diagnostics.is_empty());
+}
@@ -0,0 +1,40 @@
+use iso_10303::step::{StepReader, TypedRef};
+use iso_10303_parts::ifc4::*;
+use std::io::Cursor;
+
+const MODEL: &str = "ISO-10303-21;
+HEADER;
+FILE_DESCRIPTION(('ViewDefinition [CoordinationView]'),'2;1');
+FILE_NAME('dim.ifc','2020-01-01T00:00:00',(''),(''),'','','');
+FILE_SCHEMA(('IFC4'));
+ENDSEC;
+DATA;
+#1=IFCCARTESIANPOINT((0.,0.,0.));
+#2=IFCCARTESIANPOINT((1.,0.,0.));
+#3=IFCPOLYLINE((#1,#2));
+#4=IFCCARTESIANPOINT((0.,0.));
+#5=IFCAXIS2PLACEMENT2D(#4,$);
+#6=IFCCIRCLE(#5,1.);
+#7=IFCTRIMMEDCURVE(#6,(#4),(#4),.T.,.CARTESIAN.);
+#8=IFCGEOMETRICSET((#2));
+#9=IFCCARTESIANPOINTLIST2D(((0.,0.),(1.,1.)));
+ENDSEC;
+END-ISO-10303-21;
+";
+
+#[test]
+fn dim_is_derived_from_the_points() {
+    let mut reader = Ifc4Reader::new();
+    let report = reader.read_from(Cursor::new(MODEL)).unwrap();
+    assert!(report.diagnostics.is_empty());
+    let point = reader.resolve(&TypedRef::<IfcCartesianPoint>::new(4)).unwrap();
+    assert_eq!(point.dim(), 2);
+    assert_eq!(reader.ifc_placement_dim(5), Some(2));
+    assert_eq!(reader.ifc_curve_dim(3), Some(3));
+    assert_eq!(reader.ifc_curve_dim(6), Some(2));
+    assert_eq!(reader.ifc_curve_dim(7), Some(2));
+    assert_eq!(reader.ifc_geometric_set_dim(8), Some(3));
+    assert_eq!(reader.ifc_cartesian_point_list_dim(9), Some(2));
+    // not a curve
+    assert_eq!(reader.ifc_curve_dim(1), None);
+}
@@ -2,6 +2,7 @@ use heck::*;
 use iso_10303::express::*;
 use proc_macro2::TokenStream;
 use quote::{format_ident, quote};
+use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
 
 pub struct Generator {
@@ -13,12 +14,24 @@ pub struct Generator {
     entity_infos: HashMap<String, EntityInfo>,
     /// these types will derive Eq, PartialEq, Hash
     hashable_types: HashSet<String>,
+    /// entities which are a supertype of other entities
+    supertypes: HashSet<String>,
+    /// inherited explicit attributes, by declaring entity and name, whose accessors return an `Option` as some
+    /// entity has no value for them
+    partial_attributes: HashSet<(String, String)>,
+    /// derived attributes, by declaring entity and name, which are read by accessors of the reader
+    reader_derives: HashSet<(String, String)>,
+    /// schema functions translated to Rust, by lowercase name
+    functions: HashSet<String>,
 }
 
 struct EntityInfo {
     name: String,
     attributes: Vec<Attribute>,
     supertypes: Vec<String>,
+    /// the entity and all its supertypes, supertypes first
+    ancestors: Vec<String>,
+    is_abstract: bool,
 }
 
 struct TypeInfo {
@@ -26,7 +39,193 @@ struct TypeInfo {
     underlying_type: DataType,
 }
 
+/// The Rust values which translated EXPRESS expressions evaluate to.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ValueKind {
+    Integer,
+    /// an `f64`, wrapped in `Real` for the result only
+    Real,
+    Boolean,
+    Logical,
+    String,
+    /// a LIST, SET or BAG, indexed from 1
+    List,
+    Array,
+    /// the id of an entity instance, read through the reader
+    Entity,
+}
+
+/// An EXPRESS expression translated to Rust.
+#[derive(Clone)]
+struct Translation {
+    code: TokenStream,
+    kind: ValueKind,
+    /// the code evaluates to an `Option` of the value
+    optional: bool,
+    /// the code needs no parentheses as an operand
+    atomic: bool,
+    /// the EXPRESS type of entity instances and aggregates, whose attributes and elements are accessed
+    data_type: Option<DataType>,
+    /// the value is a size, which is never negative
+    size: bool,
+}
+
+impl Translation {
+    fn new(code: TokenStream, kind: ValueKind, atomic: bool) -> Self {
+        Translation {
+            code,
+            kind,
+            optional: false,
+            atomic,
+            data_type: None,
+            size: false,
+        }
+    }
+
+    fn is_number(&self) -> bool {
+        !self.optional && matches!(self.kind, ValueKind::Integer | ValueKind::Real)
+    }
+
+    /// The code as operand of an operator or receiver of a method call.
+    fn operand(&self) -> TokenStream {
+        let code = &self.code;
+        if self.atomic {
+            code.clone()
+        } else {
+            quote! {(#code)}
+        }
+    }
+
+    fn to_real(&self) -> Self {
+        if let (ValueKind::Integer, Ok(value)) = (self.kind, self.code.to_string().parse::<i64>()) {
+            let code = proc_macro2::Literal::f64_unsuffixed(value as f64);
+            Translation::new(quote! {#code}, ValueKind::Real, true)
+        } else if self.kind == ValueKind::Integer {
+            let operand = self.operand();
+            Translation::new(quote! {#operand as f64}, ValueKind::Real, false)
+        } else {
+            self.clone()
+        }
+    }
+
+    /// The code as an `Option` of the value, a trailing `?` is dropped rather than wrapped in `Some`.
+    fn some(&self) -> TokenStream {
+        let code = &self.code;
+        if self.optional {
+            return code.clone();
+        }
+        let mut tokens = code.clone().into_iter().collect::<Vec<_>>();
+        match tokens.last() {
+            Some(proc_macro2::TokenTree::Punct(punct)) if self.atomic && punct.as_char() == '?' => {
+                tokens.pop();
+                tokens.into_iter().collect()
+            }
+            _ => quote! {Some(#code)},
+        }
+    }
+}
+
+/// Where an expression is translated: a method of an entity trait, an accessor of the reader or a function.
+struct Scope<'a> {
+    /// the entity whose attributes are referred to by name
+    entity: Option<&'a str>,
+    /// the code of the instance, `self` in trait methods
+    receiver: TokenStream,
+    /// the code of the id of the instance, known in accessors of the reader
+    id: Option<TokenStream>,
+    /// the code of the reader, which is not available in trait methods
+    reader: Option<TokenStream>,
+    /// the parameters of a function by lowercase name
+    parameters: Vec<(String, Translation)>,
+    /// the parameters used by the translation, `self` for the receiver and `reader` for the reader
+    used: RefCell<HashSet<String>>,
+}
+
+impl<'a> Scope<'a> {
+    /// A method of the trait of the entity.
+    fn method(entity: &'a str) -> Self {
+        Scope {
+            entity: Some(entity),
+            receiver: quote! {self},
+            id: None,
+            reader: None,
+            parameters: Vec::new(),
+            used: RefCell::default(),
+        }
+    }
+
+    /// An accessor of the reader for instances of the entity.
+    fn accessor(entity: &'a str) -> Self {
+        Scope {
+            entity: Some(entity),
+            receiver: quote! {entity},
+            id: Some(quote! {id}),
+            reader: Some(quote! {self}),
+            parameters: Vec::new(),
+            used: RefCell::default(),
+        }
+    }
+
+    /// A function with the parameters.
+    fn function(parameters: Vec<(String, Translation)>) -> Self {
+        Scope {
+            entity: None,
+            receiver: quote! {},
+            id: None,
+            reader: Some(quote! {reader}),
+            parameters,
+            used: RefCell::default(),
+        }
+    }
+
+    fn receiver(&self) -> TokenStream {
+        self.used.borrow_mut().insert("self".to_string());
+        self.receiver.clone()
+    }
+
+    fn reader(&self) -> Option<TokenStream> {
+        self.used.borrow_mut().insert("reader".to_string());
+        self.reader.clone()
+    }
+
+    fn parameter(&self, name: &str) -> Option<Translation> {
+        let name = name.to_lowercase();
+        let (_, value) = self.parameters.iter().find(|(parameter, _)| *parameter == name)?;
+        self.used.borrow_mut().insert(name);
+        Some(value.clone())
+    }
+
+    fn is_used(&self, name: &str) -> bool {
+        self.used.borrow().contains(name)
+    }
+}
+
+/// An entity instance in a chain of accessors.
+enum Instance {
+    /// the instance in scope, `SELF`
+    Receiver,
+    /// an instance by the code of its id
+    Id(TokenStream),
+}
+
+/// The result of the accessors applied so far.
+enum Accessed {
+    /// an instance viewed as the entity
+    Instance(String, Instance),
+    Value(Box<Translation>),
+}
+
+impl Accessed {
+    fn value(translation: Translation) -> Self {
+        Accessed::Value(Box::new(translation))
+    }
+}
+
 impl EntityInfo {
+    /// Attributes declared by the entity, excluding redeclared inherited attributes.
+    pub fn own_attributes(&self) -> Vec<&Attribute> {
+        self.attributes.iter().filter(|attr| attr.supertype.is_none()).collect()
+    }
     pub fn type_name(&self) -> TokenStream {
         let type_name = format_ident!("{}", self.name);
         quote! {#type_name}
@@ -37,78 +236,64 @@ impl EntityInfo {
     }
 }
 
-fn collect_types(schema: &Schema) -> (HashMap<String, TypeInfo>, HashMap<String, EntityInfo>) {
+fn collect_types(resolved: &ResolvedSchema) -> (HashMap<String, TypeInfo>, HashMap<String, EntityInfo>) {
     let mut type_infos = HashMap::<String, TypeInfo>::new();
     let mut entity_infos = HashMap::<String, EntityInfo>::new();
 
-    for declaration in &schema.declarations {
-        match declaration {
-            Declaration::Entity(entity) => {
-                entity_infos.insert(
-                    entity.name.to_string(),
-                    EntityInfo {
-                        name: entity.name.to_camel_case(),
-                        attributes: entity
-                            .attributes
-                            .iter()
-                            .map(|attr| Attribute {
-                                name: attr.name.to_snake_case(),
-                                data_type: attr.data_type.clone(),
-                                optional: attr.optional,
-                                supertype: attr.supertype.clone(),
-                            })
-                            .collect(),
-                        supertypes: entity.supertypes.clone(),
-                    },
-                );
-            }
-            _ => {}
-        }
-    }
-
-    let mut type_defs = schema
-        .declarations
-        .iter()
-        .filter_map(|declaration| match declaration {
-            Declaration::TypeDef(type_def) => Some(type_def),
-            _ => None,
-        })
-        .collect::<Vec<_>>();
-    let mut index = 0;
-    while index < type_defs.len() {
-        let type_def = type_defs[index];
-        let is_ready = match &type_def.underlying_type {
-            DataType::Select { types } => types
-                .iter()
-                .all(|type_name| entity_infos.contains_key(type_name) || type_infos.contains_key(type_name)),
-            _ => true,
-        };
-        if is_ready {
-            let is_entity = match &type_def.underlying_type {
-                DataType::Select { types } => types.iter().all(|type_name| {
-                    entity_infos.contains_key(type_name)
-                        || type_infos.get(type_name).map(|info| info.is_entity) == Some(true)
-                }),
-                _ => false,
-            };
-            type_infos.insert(
-                type_def.name.to_string(),
-                TypeInfo {
-                    is_entity,
-                    underlying_type: type_def.underlying_type.clone(),
-                },
-            );
-            type_defs.swap_remove(index);
-        } else {
-            index += 1;
-        }
-        if index >= type_defs.len() && index > 0 {
-            index = 0;
-        }
+    for (_, resolved_entity) in resolved.entities() {
+        let entity = resolved_entity.entity;
+        entity_infos.insert(
+            entity.name.to_string(),
+            EntityInfo {
+                name: entity.name.to_camel_case(),
+                attributes: entity
+                    .attributes
+                    .iter()
+                    .map(|attr| Attribute {
+                        name: attr.name.to_snake_case(),
+                        data_type: attr.data_type.clone(),
+                        optional: attr.optional,
+                        supertype: attr.supertype.clone(),
+                    })
+                    .collect(),
+                supertypes: entity.supertypes.clone(),
+                ancestors: resolved_entity
+                    .ancestors
+                    .iter()
+                    .map(|id| resolved.entity(*id).entity.name.to_string())
+                    .collect(),
+                is_abstract: entity.is_abstract,
+            },
+        );
+    }
+
+    for (id, resolved_type) in resolved.types() {
+        let type_def = resolved_type.type_def;
+        type_infos.insert(
+            type_def.name.to_string(),
+            TypeInfo {
+                is_entity: is_entity_select(resolved, id, &mut Vec::new()),
+                underlying_type: type_def.underlying_type.clone(),
+            },
+        );
     }
     (type_infos, entity_infos)
 }
 
+/// Whether a select lists only entities, directly or through nested selects.
+fn is_entity_select(resolved: &ResolvedSchema, id: TypeDefId, visited: &mut Vec<TypeDefId>) -> bool {
+    if visited.contains(&id) {
+        return true;
+    }
+    visited.push(id);
+    let resolved_type = resolved.type_def(id);
+    matches!(resolved_type.type_def.underlying_type, DataType::Select { .. })
+        && resolved_type.select.iter().all(|member| match member {
+            NamedType::Entity(_) => true,
+            NamedType::Type(member) => is_entity_select(resolved, *member, visited),
+        })
+}
+
 fn collect_hashable_types(
     type_infos: &HashMap<String, TypeInfo>,
     entity_infos: &HashMap<String, EntityInfo>,
@@ -116,17 +301,13 @@ fn collect_hashable_types(
     let mut hashable_types = HashSet::new();
     for entity_info in entity_infos.values() {
         for attribute in &entity_info.attributes {
-            match &attribute.data_type {
-                DataType::Set { base_type, .. } => match &**base_type {
-                    DataType::TypeRef { name } => {
-                        if type_infos.contains_key(name) {
-                            hashable_types.insert(name.clone());
-                            collect_inner_hashable_types(&mut hashable_types, type_infos, name);
-                        }
+            if let DataType::Set { base_type, .. } = &attribute.data_type {
+                if let DataType::TypeRef { name } = &**base_type {
+                    if type_infos.contains_key(name) {
+                        hashable_types.insert(name.clone());
+                        collect_inner_hashable_types(&mut hashable_types, type_infos, name);
                     }
-                    _ => {}
-                },
-                _ => {}
+                }
             }
         }
     }
@@ -138,17 +319,12 @@ fn collect_inner_hashable_types(
     type_infos: &HashMap<String, TypeInfo>,
     name: &str,
 ) {
-    if let Some(type_info) = type_infos.get(name) {
-        match &type_info.underlying_type {
-            DataType::Select { types } => {
-                for type_name in types {
-                    if type_infos.contains_key(name) {
-                        hashable_types.insert(type_name.clone());
-                        collect_inner_hashable_types(hashable_types, type_infos, type_name);
-                    }
-                }
+    if let Some(DataType::Select { types }) = type_infos.get(name).map(|type_info| &type_info.underlying_type) {
+        for type_name in types {
+            if type_infos.contains_key(name) {
+                hashable_types.insert(type_name.clone());
+                collect_inner_hashable_types(hashable_types, type_infos, type_name);
             }
-            _ => {}
         }
     }
 }
@@ -171,63 +347,254 @@ where
     }
 }
 
-fn is_copy_type(data_type: &DataType) -> bool {
-    match data_type {
-        DataType::Number => true,
-        DataType::Integer => true,
-        DataType::Real { .. } => true,
-        DataType::Boolean => true,
-        DataType::Logical => true,
-        _ => false,
+/// Inherited attributes which are `derived` are written as `*` and have no field.
+fn gen_from_parameters(attributes: &[&Attribute], name: &str, derived: &[String]) -> TokenStream {
+    let function = format_ident!("{}", name);
+    let count = attributes.len();
+    let expected = format!("{} parameters", count);
+    let check_count = quote! {
+        if parameters.len() != #count {
+            let error = ConversionError::new(#expected, format!("{} parameters", parameters.len()));
+            return Err((parameters.len().min(#count), error));
+        }
+    };
+    if attributes.is_empty() {
+        return quote! {
+            pub fn #function(parameters: Vec<Parameter>) -> Result<Self, (usize, ConversionError)> {
+                if !parameters.is_empty() {
+                    return Err((0, ConversionError::new("0 parameters", format!("{} parameters", parameters.len()))));
+                }
+                Ok(Self::default())
+            }
+        };
+    }
+    let set_fields = attributes.iter().enumerate().map(|(index, attr)| {
+        let field = format_ident!("{}", attr.name);
+        if derived.contains(&attr.name) {
+            quote! {}
+        } else if attr.optional {
+            quote! {
+                #index => entity.#field = if parameter.is_null() {None} else {Some(parameter.try_into().map_err(|error| (index, error))?)},
+            }
+        } else {
+            quote! {
+                #index => entity.#field = parameter.try_into().map_err(|error| (index, error))?,
+            }
+        }
+    });
+    quote! {
+        pub fn #function(parameters: Vec<Parameter>) -> Result<Self, (usize, ConversionError)> {
+            #check_count
+            let mut entity = Self::default();
+            for (index, parameter) in parameters.into_iter().enumerate() {
+                match index {
+                    #( #set_fields )*
+                    _ => {}
+                }
+            }
+            Ok(entity)
+        }
+    }
+}
+
+fn gen_to_parameters(attributes: &[&Attribute], name: &str, derived: &[String]) -> TokenStream {
+    let function = format_ident!("{}", name);
+    let values = attributes.iter().map(|attr| {
+        let field = format_ident!("{}", attr.name);
+        if derived.contains(&attr.name) {
+            quote! { Parameter::OmittedParameter }
+        } else if attr.optional {
+            quote! { optional_parameter(&self.#field) }
+        } else {
+            quote! { self.#field.to_parameter() }
+        }
+    });
+    quote! {
+        fn #function(&self) -> Vec<Parameter> {
+            vec![ #( #values ),* ]
+        }
     }
 }
 
+fn is_copy_type(data_type: &DataType) -> bool {
+    matches!(
+        data_type,
+        DataType::Number | DataType::Integer | DataType::Real { .. } | DataType::Boolean | DataType::Logical
+    )
+}
+
 impl Generator {
-    pub fn new(schema: Schema, name: String) -> Generator {
-        let (type_infos, entity_infos) = collect_types(&schema);
+    /// Fails with the diagnostics if the names in the schema do not resolve.
+    pub fn new(schema: Schema, name: String) -> Result<Generator, Vec<Diagnostic>> {
+        let resolved = ResolvedSchema::new(&schema);
+        if !resolved.diagnostics().is_empty() {
+            return Err(resolved.diagnostics().to_vec());
+        }
+        let (type_infos, entity_infos) = collect_types(&resolved);
         let hashable_types = collect_hashable_types(&type_infos, &entity_infos);
-        Generator {
+        let supertypes = resolved
+            .entities()
+            .filter(|(_, resolved_entity)| !resolved_entity.subtypes.is_empty())
+            .map(|(_, resolved_entity)| resolved_entity.entity.name.to_string())
+            .collect();
+        let mut generator = Generator {
             name,
             schema,
             type_infos,
             entity_infos,
             hashable_types,
+            supertypes,
+            partial_attributes: HashSet::new(),
+            reader_derives: HashSet::new(),
+            functions: HashSet::new(),
+        };
+        generator.keep_inherited_reference_types();
+        // fewer translated derivations leave more accessors partial, which translate fewer derivations
+        loop {
+            let partial_attributes = generator.collect_partial_attributes();
+            if partial_attributes == generator.partial_attributes {
+                break;
+            }
+            generator.partial_attributes = partial_attributes;
         }
+        generator.collect_reader_translations();
+        Ok(generator)
     }
 
-    fn get_entity_supertypes(&self, name: &str) -> Vec<&EntityInfo> {
+    /// A redeclared attribute referring to a subtype keeps the inherited type, so the field still serves the
+    /// accessor of the supertype trait.
+    fn keep_inherited_reference_types(&mut self) {
+        loop {
+            let mut changes = Vec::new();
+            for (name, entity_info) in &self.entity_infos {
+                for (index, attr) in entity_info.attributes.iter().enumerate() {
+                    if attr.supertype.is_none() {
+                        continue;
+                    }
+                    let inherited = entity_info
+                        .supertypes
+                        .iter()
+                        .flat_map(|parent| self.get_entity_attributes(parent))
+                        .find(|inherited| inherited.name == attr.name);
+                    if let Some(inherited) = inherited {
+                        let type_name =
+                            |data_type, typed_refs| self.data_type_name(data_type, false, typed_refs).to_string();
+                        if type_name(&inherited.data_type, true) != type_name(&attr.data_type, true)
+                            && type_name(&inherited.data_type, false) == type_name(&attr.data_type, false)
+                        {
+                            changes.push((name.clone(), index, inherited.data_type));
+                        }
+                    }
+                }
+            }
+            if changes.is_empty() {
+                break;
+            }
+            for (name, index, data_type) in changes {
+                self.entity_infos.get_mut(&name).unwrap().attributes[index].data_type = data_type;
+            }
+        }
+    }
+
+    /// Whether a reference to the entity may point to an instance of a subtype, and so to a trait object.
+    fn is_polymorphic(&self, name: &str) -> bool {
+        self.entity_infos[name].is_abstract || self.supertypes.contains(name)
+    }
+
+    /// The type a typed reference to the entity resolves to.
+    fn entity_ref_target(&self, name: &str) -> TokenStream {
         let entity_info = &self.entity_infos[name];
-        let mut supertypes = Vec::new();
-        for parent in &entity_info.supertypes {
-            supertypes.extend(self.get_entity_supertypes(parent));
+        if self.is_polymorphic(name) {
+            let trait_name = entity_info.trait_name();
+            quote! {dyn #trait_name}
+        } else {
+            entity_info.type_name()
+        }
+    }
+
+    /// Whether a select admits entities, directly or through nested selects.
+    fn select_has_entities(&self, name: &str) -> bool {
+        match self.type_infos.get(name).map(|info| &info.underlying_type) {
+            Some(DataType::Select { types }) => types
+                .iter()
+                .any(|name| self.entity_infos.contains_key(name) || self.select_has_entities(name)),
+            _ => false,
+        }
+    }
+
+    /// Whether values of the data type may hold entity references.
+    fn has_references(&self, data_type: &DataType) -> bool {
+        self.reference_target(data_type).is_some()
+    }
+
+    /// The entity or select of entities referenced by values of the data type.
+    fn reference_target<'a>(&'a self, data_type: &'a DataType) -> Option<&'a str> {
+        match data_type {
+            DataType::TypeRef { name } => {
+                if self.entity_infos.contains_key(name) || self.select_has_entities(name) {
+                    Some(name)
+                } else {
+                    match self.type_infos.get(name).map(|info| &info.underlying_type) {
+                        Some(DataType::Select { .. }) | None => None,
+                        Some(underlying_type) => self.reference_target(underlying_type),
+                    }
+                }
+            }
+            DataType::Set { base_type, .. }
+            | DataType::Bag { base_type, .. }
+            | DataType::Array { base_type, .. }
+            | DataType::List { base_type, .. } => self.reference_target(base_type),
+            _ => None,
+        }
+    }
+
+    fn get_entity_supertypes(&self, name: &str) -> Vec<&EntityInfo> {
+        self.entity_infos[name]
+            .ancestors
+            .iter()
+            .map(|ancestor| &self.entity_infos[ancestor])
+            .collect()
+    }
+
+    /// The names of the entity and its supertypes in the order their traits are implemented.
+    fn get_trait_names<'a>(&'a self, name: &'a str) -> Vec<&'a str> {
+        let mut names = Vec::new();
+        for parent in &self.entity_infos[name].supertypes {
+            names.extend(self.get_trait_names(parent));
         }
-        supertypes.push(entity_info);
-        dedup(&mut supertypes, |a, b| a.name == b.name);
-        supertypes
+        names.push(name);
+        dedup(&mut names, |a, b| a == b);
+        names
     }
 
+    /// All explicit attributes in instance order, a redeclared attribute keeps the position of the inherited one.
     fn get_entity_attributes(&self, name: &str) -> Vec<Attribute> {
         let mut attributes: Vec<Attribute> = Vec::new();
         if let Some(entity_info) = self.entity_infos.get(name) {
             for parent in &entity_info.supertypes {
-                let mut parent_attributes = self
-                    .get_entity_attributes(parent)
-                    .into_iter()
-                    .filter(|attribute| {
-                        !entity_info
-                            .attributes
-                            .iter()
-                            .chain(attributes.iter())
-                            .any(|attr| attribute.name == attr.name)
-                    })
-                    .collect::<Vec<_>>();
-                attributes.append(&mut parent_attributes);
+                for attribute in self.get_entity_attributes(parent) {
+                    if !attributes.iter().any(|attr| attr.name == attribute.name) {
+                        attributes.push(attribute);
+                    }
+                }
+            }
+            for attribute in &entity_info.attributes {
+                if let Some(inherited) = attributes.iter_mut().find(|attr| attr.name == attribute.name) {
+                    *inherited = attribute.clone();
+                } else {
+                    attributes.push(attribute.clone());
+                }
             }
-            attributes.extend(entity_info.attributes.clone());
         }
         attributes
     }
 
+    /// Whether the entity has inherited attributes, which are not written in its part of a complex instance.
+    fn has_inherited_attributes(&self, name: &str) -> bool {
+        let entity_info = &self.entity_infos[name];
+        self.get_entity_attributes(name).len() > entity_info.own_attributes().len()
+    }
+
     pub fn gencode(&self) -> String {
         let declarations = self.schema.declarations.iter().map(|declaration| match declaration {
             Declaration::TypeDef(type_def) => self.gen_type_def(type_def),
@@ -235,26 +602,40 @@ impl Generator {
             _ => quote! {},
         });
         let reader = self.gen_reader();
+        let functions = self.gen_functions();
 
         let code = quote! {
             //! This file is generated. Do not edit.
-            #![allow(dead_code)]
+            #![allow(dead_code, unused_imports, clippy::single_match)]
             use iso_10303::step::*;
             use std::collections::HashSet;
+            use std::convert::{TryFrom, TryInto};
             #[derive(Default, Debug)]
             pub struct Unimplemented {}
-            impl From<Parameter> for Unimplemented {
-                fn from(_parameter: Parameter) -> Self {
-                    Unimplemented {}
+            impl TryFrom<Parameter> for Unimplemented {
+                type Error = ConversionError;
+                fn try_from(_parameter: Parameter) -> Result<Self, Self::Error> {
+                    Ok(Unimplemented {})
+                }
+            }
+            impl ToParameter for Unimplemented {
+                fn to_parameter(&self) -> Parameter {
+                    Parameter::UnTypedParameter(UnTypedParameter::Null)
                 }
             }
             #( #declarations )*
             #reader
+            #functions
         };
         code.to_string()
     }
 
     fn type_name(&self, data_type: &DataType, optional: bool) -> TokenStream {
+        self.data_type_name(data_type, optional, true)
+    }
+
+    /// The Rust type of a data type, entity references are untyped `EntityRef`s unless `typed_refs` is set.
+    fn data_type_name(&self, data_type: &DataType, optional: bool, typed_refs: bool) -> TokenStream {
         let type_token = match data_type {
             DataType::Number => quote! {Real},
             DataType::Integer => quote! {i64},
@@ -262,12 +643,15 @@ impl Generator {
             DataType::Boolean => quote! {bool},
             DataType::Logical => quote! {Option<bool>},
             DataType::String { .. } => quote! {String},
+            DataType::Binary { .. } => quote! {Binary},
             DataType::TypeRef { name } => {
-                if self.entity_infos.contains_key(name)
+                if typed_refs && self.entity_infos.contains_key(name) {
+                    let target = self.entity_ref_target(name);
+                    quote! {TypedRef<#target>}
+                } else if self.entity_infos.contains_key(name)
                     || self.type_infos.get(name).map(|info| info.is_entity) == Some(true)
                 {
-                    // let trait_name = entity_info.trait_name();
-                    // quote! {&'a dyn #trait_name}
+                    // a select of entities admits many types
                     quote! {EntityRef}
                 } else {
                     let type_name = format_ident!("{}", name.to_camel_case());
@@ -275,19 +659,19 @@ impl Generator {
                 }
             }
             DataType::Set { base_type, .. } => {
-                let item_type = self.type_name(base_type, false);
+                let item_type = self.data_type_name(base_type, false, typed_refs);
                 quote!(HashSet<#item_type>)
             }
             DataType::Bag { base_type, .. } => {
-                let item_type = self.type_name(base_type, false);
+                let item_type = self.data_type_name(base_type, false, typed_refs);
                 quote!(Vec<#item_type>)
             }
             DataType::Array { base_type, .. } => {
-                let item_type = self.type_name(base_type, false);
+                let item_type = self.data_type_name(base_type, false, typed_refs);
                 quote!(Vec<#item_type>)
             }
             DataType::List { base_type, .. } => {
-                let item_type = self.type_name(base_type, false);
+                let item_type = self.data_type_name(base_type, false, typed_refs);
                 quote!(Vec<#item_type>)
             }
             _ => quote! {Unimplemented},
@@ -309,42 +693,49 @@ impl Generator {
                     .map(|value| format_ident!("{}", value.to_camel_case()))
                     .collect::<Vec<_>>();
                 let default_value = &names[0];
+                let others = &names[1..];
                 let convertions = values.iter().zip(names.iter()).map(|(value, name)| {
                     let upper = value.to_shouty_snake_case();
                     quote! {
-                        #upper => #ident::#name,
+                        #upper => Ok(#ident::#name),
                     }
                 });
+                let expected = &type_def.name;
+                let upper_values = values.iter().map(|value| value.to_shouty_snake_case());
                 quote! {
-                    #[derive(Eq, PartialEq, Hash, Debug)]
+                    #[derive(Default, Eq, PartialEq, Hash, Debug)]
                     pub enum #ident {
-                        #( #names, )*
-                    }
-                    impl Default for #ident {
-                        fn default() -> Self {
-                            #ident::#default_value
-                        }
+                        #[default]
+                        #default_value,
+                        #( #others, )*
                     }
-                    impl From<String> for #ident {
-                        fn from(value: String) -> Self {
+                    impl TryFrom<String> for #ident {
+                        type Error = ConversionError;
+                        fn try_from(value: String) -> Result<Self, Self::Error> {
                             match value.as_str() {
                                 #( #convertions )*
-                                _ => panic!("{} is not a valid value", value),
+                                _ => Err(ConversionError::new(#expected, format!(".{}.", value))),
                             }
                         }
                     }
-                    impl From<Parameter> for #ident {
-                        fn from(parameter: Parameter) -> Self {
+                    impl TryFrom<Parameter> for #ident {
+                        type Error = ConversionError;
+                        fn try_from(parameter: Parameter) -> Result<Self, Self::Error> {
                             match parameter {
-                                Parameter::UnTypedParameter(untyped_parameter) => match untyped_parameter {
-                                    UnTypedParameter::EnumValue(value) => value.into(),
-                                    _ => panic!("parameter is not an enum value"),
-                                },
-                                Parameter::OmittedParameter => #ident::default(),
-                                _ => panic!("parameter is not an enum value"),
+                                Parameter::UnTypedParameter(UnTypedParameter::EnumValue(value)) => value.try_into(),
+                                Parameter::OmittedParameter => Ok(#ident::default()),
+                                _ => Err(ConversionError::unexpected("enum value", &parameter)),
                             }
                         }
                     }
+                    impl ToParameter for #ident {
+                        fn to_parameter(&self) -> Parameter {
+                            let value = match self {
+                                #( #ident::#names => #upper_values, )*
+                            };
+                            Parameter::UnTypedParameter(UnTypedParameter::EnumValue(value.to_string()))
+                        }
+                    }
                 }
             }
             DataType::Bag { base_type, .. } => {
@@ -391,28 +782,61 @@ impl Generator {
         let fields = entity_info.attributes.iter().map(|attr| {
             let ident = format_ident!("{}", attr.name);
             let data_type = self.type_name(&attr.data_type, attr.optional);
-            if is_copy_type(&attr.data_type) {
+            let data_type = if is_copy_type(&attr.data_type) {
+                data_type
+            } else {
+                quote! {&#data_type}
+            };
+            if self.is_partial_attribute(&entity.name, &attr.name) {
                 quote! {
-                    fn #ident(&self) -> #data_type;
+                    fn #ident(&self) -> Option<#data_type>;
                 }
             } else {
                 quote! {
-                    fn #ident(&self) -> &#data_type;
+                    fn #ident(&self) -> #data_type;
                 }
             }
         });
+        // derivations reading other instances are accessors of the reader
+        let derives = entity
+            .derives
+            .iter()
+            .filter(|derive| derive.supertype.is_none())
+            .filter_map(|derive| {
+                let ident = format_ident!("{}", derive.name.to_snake_case());
+                let data_type = self.type_name(&derive.data_type, false);
+                let value = self.translate_derive(&entity.name, derive, &derive.data_type, false)?;
+                Some(quote! {
+                    fn #ident(&self) -> #data_type {
+                        #value
+                    }
+                })
+            });
+        let from_entity = if self.is_polymorphic(&entity.name) {
+            self.gen_from_entity(entity_info)
+        } else {
+            quote! {}
+        };
         let trait_code = quote! {
             pub trait #trait_name: #( #supertypes )+*  {
                 #( #fields )*
+                #( #derives )*
             }
+            #from_entity
         };
         if entity.is_abstract {
             trait_code
         } else {
             let type_name = entity_info.type_name();
             let attributes = self.get_entity_attributes(&entity.name);
+            let derived_attributes = self.get_derived_attributes(&entity.name);
+            let derived = derived_attributes
+                .iter()
+                .map(|(name, _, _)| name.clone())
+                .collect::<Vec<_>>();
             let fields = attributes
                 .iter()
+                .filter(|attr| !derived.contains(&attr.name))
                 .map(|attr| {
                     let ident = format_ident!("{}", attr.name);
                     let data_type = self.type_name(&attr.data_type, attr.optional);
@@ -427,48 +851,29 @@ impl Generator {
                     #( #fields )*
                 }
             };
-            let impls = self.get_entity_supertypes(&entity.name).into_iter().map(|supertype| {
-                let trait_name = supertype.trait_name();
-                let fields = supertype.attributes.iter().map(|super_attr| {
+            let impls = self.get_trait_names(&entity.name).into_iter().map(|supertype| {
+                let trait_name = self.entity_infos[supertype].trait_name();
+                let fields = self.entity_infos[supertype].attributes.iter().map(|super_attr| {
                     let field = format_ident!("{}", super_attr.name);
                     let data_type = self.type_name(&super_attr.data_type, super_attr.optional);
-                    if is_copy_type(&super_attr.data_type) {
-                        if super_attr.data_type.is_number()
-                            && attributes
-                                .iter()
-                                .find(|attr| attr.name == super_attr.name)
-                                .map(|attr| attr.data_type.is_integer())
-                                == Some(true)
-                        {
-                            quote! {
-                                fn #field(&self) -> #data_type {
-                                    Real(self.#field as f64)
-                                }
-                            }
-                        } else {
-                            quote! {
-                                fn #field(&self) -> #data_type {
-                                    self.#field
-                                }
+                    let data_type = if is_copy_type(&super_attr.data_type) {
+                        data_type
+                    } else {
+                        quote! {&#data_type}
+                    };
+                    let value = self.gen_accessor_value(super_attr, &attributes, &derived_attributes);
+                    if self.is_partial_attribute(supertype, &super_attr.name) {
+                        let value = value.map_or_else(|| quote! {None}, |value| quote! {Some(#value)});
+                        quote! {
+                            fn #field(&self) -> Option<#data_type> {
+                                #value
                             }
                         }
                     } else {
-                        if Some(data_type.to_string())
-                            != attributes
-                                .iter()
-                                .find(|attr| attr.name == super_attr.name)
-                                .map(|attr| self.type_name(&attr.data_type, attr.optional).to_string())
-                        {
-                            quote! {
-                                fn #field(&self) -> &#data_type {
-                                    unimplemented!()
-                                }
-                            }
-                        } else {
-                            quote! {
-                                fn #field(&self) -> &#data_type {
-                                    &self.#field
-                                }
+                        let value = value.expect("accessors without value are partial");
+                        quote! {
+                            fn #field(&self) -> #data_type {
+                                #value
                             }
                         }
                     }
@@ -479,73 +884,36 @@ impl Generator {
                     }
                 }
             });
-            let from_parameters = if attributes.len() > 0 {
-                let from_own_parameters =
-                    if attributes.len() > entity_info.attributes.len() && entity_info.attributes.len() > 0 {
-                        let set_fields = entity_info.attributes.iter().enumerate().map(|(index, attr)| {
-                        let field = format_ident!("{}", attr.name);
-                        if attr.optional {
-                            quote! {
-                                #index => entity.#field = if parameter.is_null() {None} else {Some(parameter.into())},
-                            }
-                        } else {
-                            quote! {
-                                #index => entity.#field = parameter.into(),
-                            }
-                        }
-                    });
-                        quote! {
-                            pub fn from_own_parameters(parameters: Vec<Parameter>) -> Self {
-                                let mut entity = #type_name::default();
-                                for (index, parameter) in parameters.into_iter().enumerate() {
-                                    match index {
-                                        #( #set_fields )*
-                                        _ => {}
-                                    }
-                                }
-                                entity
-                            }
-                        }
-                    } else {
-                        quote! {}
-                    };
-
-                let set_fields = attributes.iter().enumerate().map(|(index, attr)| {
-                    let field = format_ident!("{}", attr.name);
-                    if attr.optional {
-                        quote! {
-                            #index => entity.#field = if parameter.is_null() {None} else {Some(parameter.into())},
-                        }
-                    } else {
-                        quote! {
-                            #index => entity.#field = parameter.into(),
-                        }
-                    }
-                });
-                quote! {
-                    impl #type_name {
-                        pub fn from_parameters(parameters: Vec<Parameter>) -> Self {
-                            let mut entity = #type_name::default();
-                            for (index, parameter) in parameters.into_iter().enumerate() {
-                                match index {
-                                    #( #set_fields )*
-                                    _ => {}
-                                }
-                            }
-                            entity
-                        }
-
-                        #from_own_parameters
-                    }
-                }
+            let own_attributes = entity_info.own_attributes();
+            let from_parameters =
+                gen_from_parameters(&attributes.iter().collect::<Vec<_>>(), "from_parameters", &derived);
+            let to_parameters = gen_to_parameters(&attributes.iter().collect::<Vec<_>>(), "to_parameters", &derived);
+            let (from_own_parameters, to_own_parameters) = if self.has_inherited_attributes(&entity.name) {
+                (
+                    gen_from_parameters(&own_attributes, "from_own_parameters", &derived),
+                    gen_to_parameters(&own_attributes, "to_own_parameters", &derived),
+                )
             } else {
-                quote! {
-                    impl #type_name {
-                        pub fn from_parameters(_parameters: Vec<Parameter>) -> Self {
-                            #type_name::default()
-                        }
-                    }
+                (quote! {}, quote! {})
+            };
+            let constructor = entity.name.to_uppercase();
+            let stored_attributes = attributes
+                .iter()
+                .filter(|attr| !derived.contains(&attr.name))
+                .cloned()
+                .collect::<Vec<_>>();
+            let attribute_references = self.gen_attribute_references(&type_name, &stored_attributes);
+            let from_parameters = quote! {
+                impl #type_name {
+                    #from_parameters
+                    #from_own_parameters
                 }
+                impl ToParameters for #type_name {
+                    const TYPE_NAME: &'static str = #constructor;
+                    #to_parameters
+                    #to_own_parameters
+                }
+                #attribute_references
             };
             quote! {
                 #trait_code
@@ -556,21 +924,162 @@ impl Generator {
         }
     }
 
-    fn gen_select_def(&self, type_name: &str, types: &Vec<String>) -> TokenStream {
-        let ident = format_ident!("{}", type_name.to_camel_case());
-        let derive = if self.hashable_types.contains(type_name) {
-            quote! { #[derive(Eq, PartialEq, Hash, Debug)] }
+    /// The value an entity struct returns from the accessor of an inherited attribute, none if a redeclared
+    /// derived attribute is not translated or the attribute is stored as another type.
+    fn gen_accessor_value(
+        &self,
+        super_attr: &Attribute,
+        attributes: &[Attribute],
+        derived_attributes: &[(String, &DerivedAttribute, &str)],
+    ) -> Option<TokenStream> {
+        let field = format_ident!("{}", super_attr.name);
+        if let Some((_, derive, declaring)) = derived_attributes
+            .iter()
+            .rev()
+            .find(|(name, _, _)| *name == super_attr.name)
+        {
+            // only values can be returned for derived attributes
+            if !is_copy_type(&super_attr.data_type) {
+                return None;
+            }
+            self.translate_derive(declaring, derive, &super_attr.data_type, super_attr.optional)
+        } else if is_copy_type(&super_attr.data_type) {
+            if super_attr.data_type.is_number()
+                && attributes
+                    .iter()
+                    .find(|attr| attr.name == super_attr.name)
+                    .map(|attr| attr.data_type.is_integer())
+                    == Some(true)
+            {
+                Some(quote! {Real(self.#field as f64)})
+            } else {
+                Some(quote! {self.#field})
+            }
         } else {
-            quote! { #[derive(Debug)] }
-        };
-        let entity_count = types
+            let data_type = self.type_name(&super_attr.data_type, super_attr.optional).to_string();
+            attributes
+                .iter()
+                .find(|attr| attr.name == super_attr.name)
+                .filter(|attr| self.type_name(&attr.data_type, attr.optional).to_string() == data_type)
+                .map(|_| quote! {&self.#field})
+        }
+    }
+
+    fn is_partial_attribute(&self, entity: &str, name: &str) -> bool {
+        self.partial_attributes
+            .contains(&(entity.to_string(), name.to_string()))
+    }
+
+    /// The inherited attributes which some entity struct has no value for.
+    fn collect_partial_attributes(&self) -> HashSet<(String, String)> {
+        let mut partial_attributes = HashSet::new();
+        for (name, entity_info) in &self.entity_infos {
+            if entity_info.is_abstract {
+                continue;
+            }
+            let attributes = self.get_entity_attributes(name);
+            let derived_attributes = self.get_derived_attributes(name);
+            for supertype in &entity_info.ancestors {
+                for super_attr in &self.entity_infos[supertype].attributes {
+                    if self
+                        .gen_accessor_value(super_attr, &attributes, &derived_attributes)
+                        .is_none()
+                    {
+                        partial_attributes.insert((supertype.clone(), super_attr.name.clone()));
+                    }
+                }
+            }
+        }
+        partial_attributes
+    }
+
+    fn gen_attribute_references(&self, type_name: &TokenStream, attributes: &[Attribute]) -> TokenStream {
+        let visits = attributes
             .iter()
-            .filter(|name| self.entity_infos.contains_key(*name))
-            .count();
-        let default_variant = if entity_count > 1 {
-            quote! { EntityRef(EntityRef), }
+            .filter_map(|attr| {
+                let target = self.reference_target(&attr.data_type)?;
+                let field = format_ident!("{}", attr.name);
+                let name = &attr.name;
+                let expected = target.to_uppercase();
+                // untyped references are kept for selects of entities
+                let check = if self.entity_infos.contains_key(target) {
+                    quote! { |_| true }
+                } else {
+                    let select = format_ident!("{}", target.to_camel_case());
+                    quote! { #select::is_member }
+                };
+                Some(quote! {
+                    self.#field.references(&mut |id, check| visit(#name, #expected, id, check.unwrap_or(#check)));
+                })
+            })
+            .collect::<Vec<_>>();
+        let visit = if visits.is_empty() {
+            quote! {_visit}
         } else {
-            quote! {}
+            quote! {visit}
+        };
+        quote! {
+            impl AttributeReferences for #type_name {
+                fn attribute_references(&self, #visit: &mut dyn FnMut(&'static str, &'static str, i64, EntityCheck)) {
+                    #( #visits )*
+                }
+            }
+        }
+    }
+
+    /// Resolve an instance of any entity struct implementing the trait of the entity to a trait object.
+    fn gen_from_entity(&self, entity_info: &EntityInfo) -> TokenStream {
+        let trait_name = entity_info.trait_name();
+        let mut implementors = self
+            .entity_infos
+            .iter()
+            .filter(|(subtype, subtype_info)| {
+                !subtype_info.is_abstract
+                    && self
+                        .get_entity_supertypes(subtype)
+                        .iter()
+                        .any(|supertype| supertype.name == entity_info.name)
+            })
+            .map(|(subtype, subtype_info)| (subtype.as_str(), subtype_info.type_name()))
+            .collect::<Vec<_>>();
+        implementors.sort_by_key(|(subtype, _)| *subtype);
+        let casts = implementors.iter().map(|(_, type_name)| {
+            quote! {
+                if let Some(entity) = entity.downcast_ref::<#type_name>() {
+                    return Some(entity);
+                }
+            }
+        });
+        let entity = if implementors.is_empty() {
+            quote! {_entity}
+        } else {
+            quote! {entity}
+        };
+        quote! {
+            impl FromEntity for dyn #trait_name {
+                fn from_entity(#entity: &dyn Any) -> Option<&Self> {
+                    #( #casts )*
+                    None
+                }
+            }
+        }
+    }
+
+    fn gen_select_def(&self, type_name: &str, types: &[String]) -> TokenStream {
+        let ident = format_ident!("{}", type_name.to_camel_case());
+        let derive = if self.hashable_types.contains(type_name) {
+            quote! { #[derive(Eq, PartialEq, Hash, Debug)] }
+        } else {
+            quote! { #[derive(Debug)] }
+        };
+        let entity_count = types
+            .iter()
+            .filter(|name| self.entity_infos.contains_key(*name))
+            .count();
+        let default_variant = if entity_count > 1 {
+            quote! { EntityRef(EntityRef), }
+        } else {
+            quote! {}
         };
         let variants = types.iter().map(|name| {
             if let Some(entity_info) = self.entity_infos.get(name) {
@@ -609,16 +1118,42 @@ impl Generator {
                 let type_name = name.to_shouty_snake_case();
                 let variant = format_ident!("{}", name.to_camel_case());
                 quote! {
-                    #type_name => #ident::#variant(typed_parameter.parameters.into_iter().next().unwrap().into())
+                    #type_name => Ok(#ident::#variant(typed_parameter.into_value()?.try_into()?))
                 }
             })
             .collect::<Vec<_>>();
-        let typed_case = if create_variants.len() > 0 {
+        let write_variants = types.iter().map(|name| {
+            let variant = format_ident!("{}", name.to_camel_case());
+            let is_select = match self.type_infos.get(name) {
+                Some(type_info) => matches!(type_info.underlying_type, DataType::Select { .. }),
+                None => true,
+            };
+            if is_select {
+                // entity references and nested selects are written as they are
+                quote! {
+                    #ident::#variant(value) => value.to_parameter()
+                }
+            } else {
+                let type_name = name.to_shouty_snake_case();
+                quote! {
+                    #ident::#variant(value) => Parameter::TypedParameter(TypedParameter {
+                        type_name: #type_name.to_string(),
+                        parameters: vec![value.to_parameter()],
+                    })
+                }
+            }
+        });
+        let write_default_variant = if entity_count > 1 {
+            quote! { #ident::EntityRef(value) => value.to_parameter(), }
+        } else {
+            quote! {}
+        };
+        let typed_case = if !create_variants.is_empty() {
             quote! {
                 Parameter::TypedParameter(typed_parameter) => match typed_parameter.type_name.as_str() {
                     #( #create_variants, )*
-                    _ => panic!("parameter type is not recognized: {}", typed_parameter.type_name),
-                }
+                    _ => Err(ConversionError::new(#type_name, typed_parameter.type_name)),
+                },
             }
         } else {
             quote! {}
@@ -627,38 +1162,96 @@ impl Generator {
             let variant = types.iter().find(|name| self.entity_infos.contains_key(*name)).unwrap();
             let type_name = self.entity_infos[variant].type_name();
             quote! {
-                Parameter::UnTypedParameter(untyped_parameter) => match untyped_parameter {
-                    UnTypedParameter::EntityRef(id) => #ident::#type_name(EntityRef(id)),
-                    _ => panic!("parameter is not an instance"),
-                }
+                Parameter::UnTypedParameter(UnTypedParameter::EntityRef(id)) => Ok(#ident::#type_name(EntityRef(id))),
             }
         } else if entity_count > 1 {
             quote! {
-                Parameter::UnTypedParameter(untyped_parameter) => match untyped_parameter {
-                    UnTypedParameter::EntityRef(id) => #ident::EntityRef(EntityRef(id)),
-                    _ => panic!("parameter is not an instance"),
-                }
+                Parameter::UnTypedParameter(UnTypedParameter::EntityRef(id)) => Ok(#ident::EntityRef(EntityRef(id))),
             }
         } else {
             quote! {}
         };
+        let references = if self.select_has_entities(type_name) {
+            self.gen_select_references(&ident, types, entity_count > 1)
+        } else {
+            quote! {}
+        };
         quote! {
             #derive
             pub enum #ident {
                 #default_variant
                 #( #variants, )*
             }
+            #references
             impl Default for #ident {
                 fn default() -> Self {
                     #ident::#default_value
                 }
             }
-            impl From<Parameter> for #ident {
-                fn from(parameter: Parameter) -> Self {
+            impl TryFrom<Parameter> for #ident {
+                type Error = ConversionError;
+                fn try_from(parameter: Parameter) -> Result<Self, Self::Error> {
                     match parameter {
                         #typed_case
                         #untyped_case
-                        _ => panic!("parameter is not recognized"),
+                        _ => Err(ConversionError::unexpected(#type_name, &parameter)),
+                    }
+                }
+            }
+            impl ToParameter for #ident {
+                fn to_parameter(&self) -> Parameter {
+                    match self {
+                        #write_default_variant
+                        #( #write_variants, )*
+                    }
+                }
+            }
+        }
+    }
+
+    fn gen_select_references(
+        &self,
+        ident: &proc_macro2::Ident,
+        types: &[String],
+        has_default_variant: bool,
+    ) -> TokenStream {
+        let checks = types.iter().filter_map(|name| {
+            if self.entity_infos.contains_key(name) {
+                let target = self.entity_ref_target(name);
+                Some(quote! { entity_check::<#target>(entity) })
+            } else if self.select_has_entities(name) {
+                let select = format_ident!("{}", name.to_camel_case());
+                Some(quote! { #select::is_member(entity) })
+            } else {
+                None
+            }
+        });
+        let mut visits = Vec::new();
+        if has_default_variant {
+            visits.push(quote! { #ident::EntityRef(value) => visit(value.0, Some(#ident::is_member)) });
+        }
+        for name in types {
+            let variant = format_ident!("{}", name.to_camel_case());
+            if self.entity_infos.contains_key(name) {
+                let target = self.entity_ref_target(name);
+                visits.push(quote! { #ident::#variant(value) => visit(value.0, Some(entity_check::<#target>)) });
+            } else if self.has_references(&DataType::TypeRef { name: name.clone() }) {
+                visits.push(quote! { #ident::#variant(value) => value.references(visit) });
+            }
+        }
+        if visits.len() < types.len() + has_default_variant as usize {
+            visits.push(quote! { _ => {} });
+        }
+        quote! {
+            impl #ident {
+                pub fn is_member(entity: &dyn Any) -> bool {
+                    #( #checks )||*
+                }
+            }
+            impl References for #ident {
+                fn references(&self, visit: &mut dyn FnMut(i64, Option<EntityCheck>)) {
+                    match self {
+                        #( #visits, )*
                     }
                 }
             }
@@ -667,7 +1260,8 @@ impl Generator {
 
     fn gen_reader(&self) -> TokenStream {
         let reader_name = format_ident!("{}Reader", self.name.to_camel_case());
-        let read_entities = self
+        let schema_name = self.schema.name.to_uppercase();
+        let entity_names = self
             .schema
             .declarations
             .iter()
@@ -681,31 +1275,105 @@ impl Generator {
                 }
                 _ => None,
             })
-            .map(|name| {
-                let constructor = name.to_uppercase();
-                let entity_info = &self.entity_infos[name];
-                let type_name = entity_info.type_name();
-                let attributes = self.get_entity_attributes(name);
-                if attributes.len() > entity_info.attributes.len() && entity_info.attributes.len() > 0 {
-                    quote! {
-                        #constructor => {
-                            let entity = if own_parameters_only {
-                                #type_name::from_own_parameters(typed_parameter.parameters)
-                            } else {
-                                #type_name::from_parameters(typed_parameter.parameters)
-                            };
-                            Some((entity.type_id(), std::any::type_name::<#type_name>(), Box::new(entity)))
-                        }
+            .collect::<Vec<_>>();
+        let write_entities = entity_names.iter().map(|name| {
+            let type_name = self.entity_infos[*name].type_name();
+            quote! {
+                writers.insert(TypeId::of::<#type_name>(), typed_parameter_writer::<#type_name> as TypedParameterWriter);
+            }
+        });
+        let visit_entities = entity_names.iter().map(|name| {
+            let type_name = self.entity_infos[*name].type_name();
+            quote! {
+                visitors.insert(TypeId::of::<#type_name>(), reference_visitor::<#type_name> as ReferenceVisitor);
+            }
+        });
+        let inverse_accessors = self
+            .schema
+            .declarations
+            .iter()
+            .filter_map(|declaration| match declaration {
+                Declaration::Entity(entity) => Some(entity),
+                _ => None,
+            })
+            .flat_map(|entity| {
+                entity.inverses.iter().map(move |inverse| {
+                    let function = format_ident!("{}_{}", entity.name.to_snake_case(), inverse.name.to_snake_case());
+                    let target = self.entity_ref_target(inverse.entity());
+                    let attribute = inverse.attribute.to_snake_case();
+                    match &inverse.data_type {
+                        // the references of an instance are indexed together, so its duplicates are adjacent
+                        DataType::Set { .. } => quote! {
+                            pub fn #function(&self, id: i64) -> Vec<TypedRef<#target>> {
+                                let mut refs = self.used_in_typed(id, #attribute);
+                                refs.dedup();
+                                refs
+                            }
+                        },
+                        DataType::Bag { .. } => quote! {
+                            pub fn #function(&self, id: i64) -> Vec<TypedRef<#target>> {
+                                self.used_in_typed(id, #attribute)
+                            }
+                        },
+                        _ => quote! {
+                            pub fn #function(&self, id: i64) -> Option<TypedRef<#target>> {
+                                self.used_in_typed(id, #attribute).into_iter().next()
+                            }
+                        },
                     }
-                } else {
-                    quote! {
-                        #constructor => {
-                            let entity = #type_name::from_parameters(typed_parameter.parameters);
-                            Some((entity.type_id(), std::any::type_name::<#type_name>(), Box::new(entity)))
+                })
+            });
+        let derived_accessors = self
+            .schema
+            .declarations
+            .iter()
+            .filter_map(|declaration| match declaration {
+                Declaration::Entity(entity) => Some(entity),
+                _ => None,
+            })
+            .flat_map(|entity| {
+                entity.derives.iter().filter_map(move |derive| {
+                    let name = derive.name.to_snake_case();
+                    if derive.supertype.is_some() || !self.reader_derives.contains(&(entity.name.clone(), name.clone()))
+                    {
+                        return None;
+                    }
+                    let function = format_ident!("{}_{}", entity.name.to_snake_case(), name);
+                    let data_type = self.type_name(&derive.data_type, false);
+                    let body = self.translate_reader_derive(&entity.name, derive)?;
+                    Some(quote! {
+                        pub fn #function(&self, id: i64) -> Option<#data_type> {
+                            #body
+                        }
+                    })
+                })
+            });
+        let read_entities = entity_names.iter().map(|name| {
+            let constructor = name.to_uppercase();
+            let entity_info = &self.entity_infos[*name];
+            let type_name = entity_info.type_name();
+            if self.has_inherited_attributes(name) {
+                quote! {
+                    #constructor => {
+                        let entity = if own_parameters_only {
+                            #type_name::from_own_parameters(typed_parameter.parameters)
+                        } else {
+                            #type_name::from_parameters(typed_parameter.parameters)
                         }
+                        .map_err(|(index, error)| StepError::Conversion { id, index, error })?;
+                        Ok((entity.type_id(), std::any::type_name::<#type_name>(), Box::new(entity)))
                     }
                 }
-            });
+            } else {
+                quote! {
+                    #constructor => {
+                        let entity = #type_name::from_parameters(typed_parameter.parameters)
+                            .map_err(|(index, error)| StepError::Conversion { id, index, error })?;
+                        Ok((entity.type_id(), std::any::type_name::<#type_name>(), Box::new(entity)))
+                    }
+                }
+            }
+        });
 
         quote! {
             use std::any::{Any, TypeId};
@@ -716,27 +1384,47 @@ impl Generator {
                 pub type_ids: HashMap<TypeId, Vec<i64>>,
                 pub type_names: HashMap<TypeId, &'static str>,
                 empty: Vec<i64>,
+                duplicate_ids: Vec<i64>,
+                visitors: HashMap<TypeId, ReferenceVisitor>,
+                used_in: UsedInIndex,
             }
             impl #reader_name {
                 pub fn new() -> Self {
+                    let mut visitors = HashMap::new();
+                    #( #visit_entities )*
                     #reader_name {
                         entities: BTreeMap::new(),
                         type_ids: HashMap::new(),
                         type_names: HashMap::new(),
                         empty: Vec::new(),
+                        duplicate_ids: Vec::new(),
+                        visitors,
+                        used_in: UsedInIndex::default(),
                     }
                 }
+                /// Add an entity instance, replacing the instance with the same id.
                 pub fn add_entity<T: Any>(&mut self, id: i64, entity: T) {
+                    self.remove_entity(id);
                     let type_id = entity.type_id();
-                    self.entities.insert(id, Box::new(entity));
-                    self.type_ids.entry(type_id).or_insert(vec![]).push(id);
-                    self.type_names.entry(type_id).or_insert(std::any::type_name::<T>());
+                    self.insert_entity(id, type_id, std::any::type_name::<T>(), Box::new(entity));
+                }
+                fn remove_entity(&mut self, id: i64) {
+                    if let Some(entity) = self.entities.remove(&id) {
+                        self.used_in.remove(id, &*entity, &self.visitors);
+                        if let Some(ids) = self.type_ids.get_mut(&(*entity).type_id()) {
+                            ids.retain(|other| *other != id);
+                        }
+                    }
                 }
                 pub fn get_entity<T: Any>(&self, entity_ref: &EntityRef ) -> Option<&T> {
                     self.entities
                         .get(&entity_ref.0)
-                        .map(|entity| entity.downcast_ref::<T>())
-                        .flatten()
+                        .and_then(|entity| entity.downcast_ref::<T>())
+                }
+                pub fn resolve<T: FromEntity + ?Sized>(&self, entity_ref: &TypedRef<T>) -> Option<&T> {
+                    self.entities
+                        .get(&entity_ref.id())
+                        .and_then(|entity| T::resolve(&**entity))
                 }
                 pub fn get_entities<T: Any>(&self) -> impl Iterator<Item = (i64, &T)> {
                     let type_id = TypeId::of::<T>();
@@ -750,25 +1438,1270 @@ impl Generator {
                     let type_id = (*self.entities[&id]).type_id();
                     self.type_names[&type_id]
                 }
+                pub fn validate(&self) -> Vec<StepError> {
+                    let mut errors = self
+                        .duplicate_ids
+                        .iter()
+                        .map(|&id| StepError::DuplicateId { id })
+                        .collect::<Vec<_>>();
+                    errors.extend(validate_references(&self.entities, &self.visitors));
+                    errors
+                }
+                pub fn used_in(&self, id: i64) -> &[(i64, &'static str)] {
+                    self.used_in.used_in(id)
+                }
+                pub fn used_in_typed<T: FromEntity + ?Sized>(&self, id: i64, attribute: &str) -> Vec<TypedRef<T>> {
+                    self.used_in.used_in_typed(&self.entities, id, attribute)
+                }
+                #( #inverse_accessors )*
+                #( #derived_accessors )*
+            }
+
+            impl Default for #reader_name {
+                fn default() -> Self {
+                    Self::new()
+                }
             }
 
             impl StepReader for #reader_name {
                 fn insert_entity(&mut self, id: i64, type_id: TypeId, type_name: &'static str, entity: Box<dyn Any>) {
+                    // the first instance with a name is kept
+                    if self.entities.contains_key(&id) {
+                        self.duplicate_ids.push(id);
+                        return;
+                    }
+                    self.used_in.insert(id, &*entity, &self.visitors);
                     self.entities.insert(id, entity);
                     self.type_ids.entry(type_id).or_insert(vec![]).push(id);
                     self.type_names.entry(type_id).or_insert(type_name);
                 }
 
-                fn create_simple_entity(&self, typed_parameter: TypedParameter, own_parameters_only: bool) -> Option<(TypeId, &'static str, Box<dyn Any>)> {
+                fn create_simple_entity(id: i64, typed_parameter: TypedParameter, own_parameters_only: bool) -> EntityResult {
                     match typed_parameter.type_name.as_str() {
                         #( #read_entities )*
-                        _ => {
-                            println!("{} is not implemented", typed_parameter.type_name);
-                            None
-                        },
+                        // keep the parts of a complex instance without entity struct, e.g. abstract supertypes
+                        _ if own_parameters_only => Ok((
+                            TypeId::of::<TypedParameter>(),
+                            std::any::type_name::<TypedParameter>(),
+                            Box::new(typed_parameter),
+                        )),
+                        _ => Err(StepError::UnknownEntity {
+                            id,
+                            type_name: typed_parameter.type_name,
+                        }),
+                    }
+                }
+            }
+
+            impl StepWriter for #reader_name {
+                fn schema_name(&self) -> &'static str {
+                    #schema_name
+                }
+
+                fn entities(&self) -> &BTreeMap<i64, Box<dyn Any>> {
+                    &self.entities
+                }
+
+                fn typed_parameter_writers(&self) -> HashMap<TypeId, TypedParameterWriter> {
+                    let mut writers = HashMap::new();
+                    #( #write_entities )*
+                    writers
+                }
+            }
+        }
+    }
+}
+
+/// Translation of derived attributes and functions. Expressions over the attributes of the instance itself are
+/// translated to methods of the entity trait, expressions reading other instances or calling schema functions to
+/// accessors of the reader, which evaluate to `None` where EXPRESS evaluates to indeterminate.
+impl Generator {
+    fn get_entity(&self, name: &str) -> Option<&Entity> {
+        self.schema
+            .declarations
+            .iter()
+            .find_map(|declaration| match declaration {
+                Declaration::Entity(entity) if entity.name == name => Some(entity),
+                _ => None,
+            })
+    }
+
+    fn get_function(&self, name: &str) -> Option<&Function> {
+        self.schema
+            .declarations
+            .iter()
+            .find_map(|declaration| match declaration {
+                Declaration::Function(function) if function.name.eq_ignore_ascii_case(name) => Some(function),
+                _ => None,
+            })
+    }
+
+    /// The name of an entity as declared, `TYPEOF` names are upper case.
+    fn entity_key(&self, name: &str) -> Option<&str> {
+        match self.entity_infos.get_key_value(name) {
+            Some((key, _)) => Some(key),
+            None => self
+                .entity_infos
+                .keys()
+                .find(|key| key.eq_ignore_ascii_case(name))
+                .map(String::as_str),
+        }
+    }
+
+    /// The names of the entity and all its supertypes, supertypes first.
+    fn get_supertype_names(&self, name: &str) -> Vec<&str> {
+        self.entity_infos[name].ancestors.iter().map(String::as_str).collect()
+    }
+
+    /// Inherited attributes which the entity or its supertypes redeclare as derived, with the derived attribute
+    /// and the entity declaring it. A later redeclaration overrides an earlier one.
+    fn get_derived_attributes<'a>(&'a self, name: &'a str) -> Vec<(String, &'a DerivedAttribute, &'a str)> {
+        let mut derived = Vec::new();
+        for supertype in self.get_supertype_names(name) {
+            if let Some(entity) = self.get_entity(supertype) {
+                for derive in entity.derives.iter().filter(|derive| derive.supertype.is_some()) {
+                    derived.push((derive.name.to_snake_case(), derive, supertype));
+                }
+            }
+        }
+        derived
+    }
+
+    /// The entity declaring an attribute of the entity, and the attribute as declared there.
+    fn attribute_owner(&self, entity: &str, name: &str) -> Option<(&str, &Attribute)> {
+        self.get_supertype_names(entity).into_iter().find_map(|supertype| {
+            self.entity_infos[supertype]
+                .attributes
+                .iter()
+                .find(|attr| attr.name == name && attr.supertype.is_none())
+                .map(|attr| (supertype, attr))
+        })
+    }
+
+    /// The entities a select of entities admits, through nested selects.
+    fn select_members(&self, name: &str) -> Vec<&str> {
+        if let Some(entity) = self.entity_key(name) {
+            return vec![entity];
+        }
+        let mut members = Vec::new();
+        if let Some(DataType::Select { types }) = self.type_infos.get(name).map(|info| &info.underlying_type) {
+            for member in types.iter().flat_map(|name| self.select_members(name)) {
+                if !members.contains(&member) {
+                    members.push(member);
+                }
+            }
+        }
+        members
+    }
+
+    /// The aggregate type underlying a data type.
+    fn aggregate_type<'a>(&'a self, data_type: &'a DataType) -> Option<&'a DataType> {
+        match data_type {
+            DataType::TypeRef { name } => self.aggregate_type(&self.type_infos.get(name)?.underlying_type),
+            DataType::List { .. } | DataType::Bag { .. } | DataType::Set { .. } | DataType::Array { .. } => {
+                Some(data_type)
+            }
+            _ => None,
+        }
+    }
+
+    fn value_kind(&self, data_type: &DataType) -> Option<ValueKind> {
+        match data_type {
+            DataType::Integer => Some(ValueKind::Integer),
+            DataType::Number | DataType::Real { .. } => Some(ValueKind::Real),
+            DataType::Boolean => Some(ValueKind::Boolean),
+            DataType::Logical => Some(ValueKind::Logical),
+            DataType::String { .. } => Some(ValueKind::String),
+            DataType::Set { .. } | DataType::Bag { .. } | DataType::List { .. } => Some(ValueKind::List),
+            DataType::Array { .. } => Some(ValueKind::Array),
+            DataType::TypeRef { name } => match self.type_infos.get(name).map(|info| &info.underlying_type) {
+                Some(DataType::Select { .. }) | Some(DataType::Enum { .. }) | None => None,
+                Some(underlying_type) => self.value_kind(underlying_type),
+            },
+            _ => None,
+        }
+    }
+
+    /// The kind of a parameter, instances of entities and selects of entities are passed by id.
+    fn parameter_kind(&self, data_type: &DataType) -> Option<ValueKind> {
+        match data_type {
+            DataType::TypeRef { name }
+                if self.entity_infos.contains_key(name)
+                    || self.type_infos.get(name).map(|info| info.is_entity) == Some(true) =>
+            {
+                Some(ValueKind::Entity)
+            }
+            _ => self.value_kind(data_type),
+        }
+    }
+
+    /// Translate the expression of a derived attribute to a value of the data type, if possible.
+    fn translate_derive(
+        &self,
+        entity: &str,
+        derive: &DerivedAttribute,
+        data_type: &DataType,
+        optional: bool,
+    ) -> Option<TokenStream> {
+        let value = self.translate_value(&Scope::method(entity), &derive.expr, data_type)?;
+        let code = value.code;
+        Some(if optional {
+            quote! {Some(#code)}
+        } else {
+            code
+        })
+    }
+
+    /// Translate the body of the accessor of a derived attribute on the reader.
+    fn translate_reader_derive(&self, entity: &str, derive: &DerivedAttribute) -> Option<TokenStream> {
+        let scope = Scope::accessor(entity);
+        let value = self.translate_value(&scope, &derive.expr, &derive.data_type)?.some();
+        let target = self.entity_ref_target(entity);
+        Some(if scope.is_used("self") {
+            quote! {
+                let entity = self.resolve(&TypedRef::<#target>::new(id))?;
+                #value
+            }
+        } else {
+            quote! {
+                self.resolve(&TypedRef::<#target>::new(id))?;
+                #value
+            }
+        })
+    }
+
+    /// Derived attributes and functions which are translated for the reader. As they may use each other, all are
+    /// taken as translated at first, and those which do not translate are dropped until the rest translates.
+    fn collect_reader_translations(&mut self) {
+        let mut functions = HashSet::new();
+        let mut reader_derives = HashSet::new();
+        for declaration in &self.schema.declarations {
+            match declaration {
+                Declaration::Function(function) => {
+                    functions.insert(function.name.to_lowercase());
+                }
+                Declaration::Entity(entity) => {
+                    for derive in entity.derives.iter().filter(|derive| derive.supertype.is_none()) {
+                        if self
+                            .translate_derive(&entity.name, derive, &derive.data_type, false)
+                            .is_none()
+                        {
+                            reader_derives.insert((entity.name.clone(), derive.name.to_snake_case()));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.functions = functions;
+        self.reader_derives = reader_derives;
+        loop {
+            let functions = self
+                .functions
+                .iter()
+                .filter(|name| {
+                    self.get_function(name)
+                        .and_then(|function| self.translate_function(function))
+                        .is_some()
+                })
+                .cloned()
+                .collect::<HashSet<_>>();
+            let reader_derives = self
+                .reader_derives
+                .iter()
+                .filter(|(entity, name)| {
+                    self.get_entity(entity)
+                        .and_then(|entity| {
+                            entity
+                                .derives
+                                .iter()
+                                .find(|derive| derive.supertype.is_none() && derive.name.to_snake_case() == *name)
+                        })
+                        .and_then(|derive| self.translate_reader_derive(entity, derive))
+                        .is_some()
+                })
+                .cloned()
+                .collect::<HashSet<_>>();
+            if functions.len() == self.functions.len() && reader_derives.len() == self.reader_derives.len() {
+                break;
+            }
+            self.functions = functions;
+            self.reader_derives = reader_derives;
+        }
+    }
+
+    /// The translated schema functions, taking the reader and the arguments.
+    fn gen_functions(&self) -> TokenStream {
+        let functions = self
+            .schema
+            .declarations
+            .iter()
+            .filter_map(|declaration| match declaration {
+                Declaration::Function(function) if self.functions.contains(&function.name.to_lowercase()) => {
+                    self.translate_function(function)
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        if functions.is_empty() {
+            return quote! {};
+        }
+        quote! {
+            mod functions {
+                use super::*;
+                #( #functions )*
+            }
+        }
+    }
+
+    /// Translate a function whose body returns values under conditions, `RETURN (?)` returns `None`.
+    fn translate_function(&self, function: &Function) -> Option<TokenStream> {
+        if !function.locals.is_empty() || !function.constants.is_empty() || !function.declarations.is_empty() {
+            return None;
+        }
+        let kind = self.value_kind(&function.return_type)?;
+        let return_type = value_type(kind)?;
+        let mut parameters = Vec::new();
+        for parameter in &function.parameters {
+            let ident = format_ident!("{}", parameter.name.to_snake_case());
+            let kind = self.parameter_kind(&parameter.data_type)?;
+            let (value, parameter_type) = match kind {
+                ValueKind::List | ValueKind::Array => {
+                    let parameter_type = match self.aggregate_type(&parameter.data_type)? {
+                        DataType::Set { .. } => self.type_name(&parameter.data_type, false),
+                        DataType::List { base_type, .. }
+                        | DataType::Bag { base_type, .. }
+                        | DataType::Array { base_type, .. } => {
+                            let element_type = self.type_name(base_type, false);
+                            quote! {[#element_type]}
+                        }
+                        _ => return None,
+                    };
+                    let value = Translation {
+                        data_type: Some(parameter.data_type.clone()),
+                        ..Translation::new(quote! {#ident}, kind, true)
+                    };
+                    (value, quote! {&#parameter_type})
+                }
+                ValueKind::Entity => {
+                    let value = Translation {
+                        data_type: Some(parameter.data_type.clone()),
+                        ..Translation::new(quote! {#ident}, kind, true)
+                    };
+                    (value, quote! {i64})
+                }
+                ValueKind::String => (Translation::new(quote! {#ident.to_string()}, kind, true), quote! {&str}),
+                _ => (Translation::new(quote! {#ident}, kind, true), value_type(kind)?),
+            };
+            parameters.push((parameter.name.to_lowercase(), ident, value, parameter_type));
+        }
+        let scope = Scope::function(
+            parameters
+                .iter()
+                .map(|(name, _, value, _)| (name.clone(), value.clone()))
+                .collect(),
+        );
+        let body = self.translate_statements(&scope, &function.statements, kind, true)?;
+        let reader_name = format_ident!("{}Reader", self.name.to_camel_case());
+        let reader = if scope.is_used("reader") {
+            quote! {reader}
+        } else {
+            quote! {_reader}
+        };
+        let allow = if parameters.len() >= 7 {
+            quote! {#[allow(clippy::too_many_arguments)]}
+        } else {
+            quote! {}
+        };
+        let parameters = parameters.iter().map(|(name, ident, _, parameter_type)| {
+            if scope.is_used(name) {
+                quote! {#ident: #parameter_type}
+            } else {
+                let ident = format_ident!("_{}", ident);
+                quote! {#ident: #parameter_type}
+            }
+        });
+        let ident = format_ident!("{}", function.name.to_snake_case());
+        Some(quote! {
+            #allow
+            pub fn #ident(#reader: &#reader_name, #( #parameters ),*) -> Option<#return_type> {
+                #body
+            }
+        })
+    }
+
+    /// Translate statements returning values of the kind, the last statement of the function body evaluates to
+    /// the result if `tail` is set.
+    fn translate_statements(
+        &self,
+        scope: &Scope,
+        statements: &[Statement],
+        kind: ValueKind,
+        tail: bool,
+    ) -> Option<TokenStream> {
+        let mut code = Vec::new();
+        let mut has_result = false;
+        for (index, statement) in statements.iter().enumerate() {
+            let last = tail && index + 1 == statements.len();
+            has_result = last;
+            let statement = match statement {
+                Statement::Null => {
+                    has_result = false;
+                    continue;
+                }
+                Statement::Return(value) => {
+                    let value = match value {
+                        Some(value) if !is_indeterminate(value) => self.translate_result(scope, value, kind)?,
+                        _ => quote! {None},
+                    };
+                    if last {
+                        value
+                    } else {
+                        quote! {return #value;}
+                    }
+                }
+                Statement::If {
+                    condition,
+                    statements,
+                    else_statements,
+                } => {
+                    let condition = self.translate_condition(scope, condition)?;
+                    let statements = self.translate_statements(scope, statements, kind, last)?;
+                    let else_code = self.translate_statements(scope, else_statements, kind, last)?;
+                    match else_statements.as_slice() {
+                        [] if !last => quote! {if #condition { #statements }},
+                        [Statement::If { .. }] => quote! {if #condition { #statements } else #else_code},
+                        _ => quote! {if #condition { #statements } else { #else_code }},
                     }
                 }
+                Statement::Compound(statements) => {
+                    let statements = self.translate_statements(scope, statements, kind, last)?;
+                    quote! {{ #statements }}
+                }
+                _ => return None,
+            };
+            code.push(statement);
+        }
+        if tail && !has_result {
+            code.push(quote! {None});
+        }
+        Some(quote! {#( #code )*})
+    }
+
+    /// Translate a returned value to an `Option` of the kind.
+    fn translate_result(&self, scope: &Scope, expr: &Expression, kind: ValueKind) -> Option<TokenStream> {
+        let value = self.translate_expression(scope, expr)?;
+        let value = match (kind, value.kind) {
+            (ValueKind::Real, ValueKind::Integer) if !value.optional => value.to_real(),
+            (ValueKind::Logical, ValueKind::Boolean) if !value.optional => {
+                let code = &value.code;
+                Translation::new(quote! {Some(#code)}, kind, true)
+            }
+            (kind, value_kind) if kind == value_kind => value,
+            _ => return None,
+        };
+        Some(value.some())
+    }
+
+    /// Translate a condition to a `bool`, which is false for unknown and indeterminate.
+    fn translate_condition(&self, scope: &Scope, expr: &Expression) -> Option<TokenStream> {
+        let value = self.translate_expression(scope, expr)?;
+        match (value.kind, value.optional) {
+            (ValueKind::Boolean, false) => Some(value.code),
+            (ValueKind::Boolean, true) | (ValueKind::Logical, false) => {
+                let operand = value.operand();
+                Some(quote! {#operand == Some(true)})
+            }
+            _ => None,
+        }
+    }
+
+    /// Translate an expression to a value of the data type, if possible.
+    fn translate_value(&self, scope: &Scope, expr: &Expression, data_type: &DataType) -> Option<Translation> {
+        let value = self.translate_expression(scope, expr)?;
+        if value.optional {
+            return None;
+        }
+        let code = &value.code;
+        let kind = self.value_kind(data_type)?;
+        Some(match (kind, value.kind) {
+            (ValueKind::Integer, ValueKind::Integer)
+            | (ValueKind::Boolean, ValueKind::Boolean)
+            | (ValueKind::Logical, ValueKind::Logical)
+            | (ValueKind::String, ValueKind::String) => value,
+            (ValueKind::Real, ValueKind::Integer) => {
+                let operand = value.operand();
+                Translation::new(quote! {Real(#operand as f64)}, kind, true)
+            }
+            (ValueKind::Real, ValueKind::Real) => Translation::new(quote! {Real(#code)}, kind, true),
+            (ValueKind::Logical, ValueKind::Boolean) => Translation::new(quote! {Some(#code)}, kind, true),
+            _ => return None,
+        })
+    }
+
+    fn translate_expression(&self, scope: &Scope, expr: &Expression) -> Option<Translation> {
+        if let [(Operator::In, types)] = expr.operations.as_slice() {
+            return self.translate_type_test(scope, &expr.operand, types);
+        }
+        let mut value = self.translate_simple_expression(scope, &expr.operand)?;
+        for (op, operand) in &expr.operations {
+            value = translate_operation(scope, value, op, self.translate_simple_expression(scope, operand)?)?;
+        }
+        Some(value)
+    }
+
+    fn translate_simple_expression(&self, scope: &Scope, expr: &SimpleExpression) -> Option<Translation> {
+        let mut value = self.translate_term(scope, &expr.operand)?;
+        for (op, operand) in &expr.operations {
+            value = translate_operation(scope, value, op, self.translate_term(scope, operand)?)?;
+        }
+        Some(value)
+    }
+
+    fn translate_term(&self, scope: &Scope, term: &Term) -> Option<Translation> {
+        let mut value = self.translate_factor(scope, &term.operand)?;
+        for (op, operand) in &term.operations {
+            value = translate_operation(scope, value, op, self.translate_factor(scope, operand)?)?;
+        }
+        Some(value)
+    }
+
+    fn translate_factor(&self, scope: &Scope, factor: &Factor) -> Option<Translation> {
+        let mut value = self.translate_simple_factor(scope, &factor.operand)?;
+        for (op, operand) in &factor.operations {
+            value = translate_operation(scope, value, op, self.translate_simple_factor(scope, operand)?)?;
+        }
+        Some(value)
+    }
+
+    fn translate_simple_factor(&self, scope: &Scope, factor: &SimpleFactor) -> Option<Translation> {
+        match factor {
+            SimpleFactor::QualifiedAccess(access) => self.translate_access(scope, access),
+            SimpleFactor::UnaryExpression { op, operand } => {
+                let value = self.translate_access(scope, operand)?;
+                let operand = value.operand();
+                match op {
+                    Operator::Add if value.is_number() => Some(value),
+                    Operator::Neg if value.is_number() && value.kind == ValueKind::Integer => translate_checked(
+                        scope,
+                        &Translation::new(quote! {0}, ValueKind::Integer, true),
+                        op,
+                        &value,
+                    ),
+                    Operator::Neg if value.is_number() => Some(Translation::new(quote! {-#operand}, value.kind, false)),
+                    Operator::Not if !value.optional && value.kind == ValueKind::Boolean => {
+                        Some(Translation::new(quote! {!#operand}, value.kind, false))
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn translate_access(&self, scope: &Scope, access: &QualifiedAccess) -> Option<Translation> {
+        let mut accessors = access.accessors.as_slice();
+        let mut accessed = match &access.base {
+            Primary::Literal(literal) => Accessed::value(translate_literal(literal)?),
+            Primary::Constant(name) => match name.to_lowercase().as_str() {
+                "pi" => Accessed::value(Translation::new(quote! {std::f64::consts::PI}, ValueKind::Real, true)),
+                "const_e" => Accessed::value(Translation::new(quote! {std::f64::consts::E}, ValueKind::Real, true)),
+                "self" => Accessed::Instance(scope.entity?.to_string(), Instance::Receiver),
+                _ => return None,
+            },
+            Primary::Reference(name) => match accessors {
+                [Accessor::FunctionCall { parameters }, rest @ ..] => {
+                    accessors = rest;
+                    Accessed::value(self.translate_call(scope, name, parameters)?)
+                }
+                _ => match scope.parameter(name) {
+                    Some(parameter) => Accessed::value(parameter),
+                    None => {
+                        Accessed::value(self.translate_attribute(scope, scope.entity?, &Instance::Receiver, name)?)
+                    }
+                },
+            },
+            Primary::Grouped(expr) => Accessed::value(self.translate_expression(scope, expr)?),
+        };
+        for accessor in accessors {
+            accessed = self.translate_accessor(scope, accessed, accessor)?;
+        }
+        match accessed {
+            Accessed::Value(value) => Some(*value),
+            Accessed::Instance(entity, instance) => Some(Translation {
+                data_type: Some(DataType::TypeRef { name: entity }),
+                ..Translation::new(instance_id(scope, &instance)?, ValueKind::Entity, true)
+            }),
+        }
+    }
+
+    fn translate_accessor(&self, scope: &Scope, accessed: Accessed, accessor: &Accessor) -> Option<Accessed> {
+        match (accessed, accessor) {
+            (Accessed::Instance(entity, instance), Accessor::Attribute { name }) => Some(Accessed::value(
+                self.translate_attribute(scope, &entity, &instance, name)?,
+            )),
+            (Accessed::Instance(entity, instance), Accessor::Group { entity: group }) => {
+                let group = self.entity_key(group)?;
+                let instance = match instance {
+                    Instance::Receiver if !self.get_supertype_names(&entity).contains(&group) => {
+                        Instance::Id(scope.id.clone()?)
+                    }
+                    instance => instance,
+                };
+                Some(Accessed::Instance(group.to_string(), instance))
+            }
+            (Accessed::Value(value), Accessor::Group { entity: group }) if value.kind == ValueKind::Entity => Some(
+                Accessed::Instance(self.entity_key(group)?.to_string(), Instance::Id(value.code)),
+            ),
+            (Accessed::Value(value), Accessor::Attribute { name }) if value.kind == ValueKind::Entity => {
+                let target = match &value.data_type {
+                    Some(DataType::TypeRef { name }) => name,
+                    _ => return None,
+                };
+                if self.entity_infos.contains_key(target) {
+                    Some(Accessed::value(self.translate_attribute(
+                        scope,
+                        target,
+                        &Instance::Id(value.code),
+                        name,
+                    )?))
+                } else {
+                    Some(Accessed::value(
+                        self.translate_select_attribute(scope, target, value.code, name)?,
+                    ))
+                }
+            }
+            (Accessed::Value(value), Accessor::Indexer { start, end: None }) => {
+                Some(Accessed::value(self.translate_element(scope, &value, start)?))
+            }
+            _ => None,
+        }
+    }
+
+    /// Read an explicit or derived attribute of an instance of the entity, through the accessor of the declaring
+    /// trait or of the reader.
+    fn translate_attribute(&self, scope: &Scope, entity: &str, instance: &Instance, name: &str) -> Option<Translation> {
+        let name = name.to_snake_case();
+        if let Some((_, derive, declaring)) = self
+            .get_derived_attributes(entity)
+            .into_iter()
+            .rev()
+            .find(|(derived, _, _)| *derived == name)
+        {
+            // the accessor of a redeclared attribute is implemented only for translated values
+            let (_, attr) = self.attribute_owner(entity, &name)?;
+            if !is_copy_type(&attr.data_type) {
+                return None;
+            }
+            self.translate_derive(declaring, derive, &attr.data_type, attr.optional)?;
+        }
+        if let Some((owner, attr)) = self.attribute_owner(entity, &name) {
+            let trait_name = self.entity_infos[owner].trait_name();
+            let field = format_ident!("{}", attr.name);
+            let object = self.translate_instance(scope, owner, instance)?;
+            let call = if self.is_partial_attribute(owner, &attr.name) {
+                // some instances have no value
+                scope.reader.as_ref()?;
+                quote! {#trait_name::#field(#object)?}
+            } else {
+                quote! {#trait_name::#field(#object)}
+            };
+            return self.translate_stored(
+                scope,
+                call,
+                &attr.data_type,
+                attr.optional,
+                !is_copy_type(&attr.data_type),
+            );
+        }
+        // derived attributes of the entity or its supertypes
+        self.get_supertype_names(entity)
+            .into_iter()
+            .rev()
+            .find_map(|supertype| {
+                let derive = self
+                    .get_entity(supertype)?
+                    .derives
+                    .iter()
+                    .find(|derive| derive.supertype.is_none() && derive.name.to_snake_case() == name)?;
+                let kind = self.value_kind(&derive.data_type)?;
+                let call = if self
+                    .translate_derive(supertype, derive, &derive.data_type, false)
+                    .is_some()
+                {
+                    let trait_name = self.entity_infos[supertype].trait_name();
+                    let method = format_ident!("{}", name);
+                    let object = self.translate_instance(scope, supertype, instance)?;
+                    quote! {#trait_name::#method(#object)}
+                } else if self.reader_derives.contains(&(supertype.to_string(), name.clone())) {
+                    let reader = scope.reader()?;
+                    let id = instance_id(scope, instance)?;
+                    let accessor = format_ident!("{}_{}", supertype.to_snake_case(), name);
+                    quote! {#reader.#accessor(#id)?}
+                } else {
+                    return None;
+                };
+                let code = if kind == ValueKind::Real {
+                    quote! {#call.0}
+                } else {
+                    call
+                };
+                Some(Translation::new(code, kind, true))
+            })
+    }
+
+    /// Whether the entity has an explicit or derived attribute of the name.
+    fn has_attribute(&self, entity: &str, name: &str) -> bool {
+        let name = name.to_snake_case();
+        self.attribute_owner(entity, &name).is_some()
+            || self.get_supertype_names(entity).into_iter().any(|supertype| {
+                self.get_entity(supertype).is_some_and(|entity| {
+                    entity
+                        .derives
+                        .iter()
+                        .any(|derive| derive.supertype.is_none() && derive.name.to_snake_case() == name)
+                })
+            })
+    }
+
+    /// The instance as the entity to call the accessors of its trait on.
+    fn translate_instance(&self, scope: &Scope, entity: &str, instance: &Instance) -> Option<TokenStream> {
+        match instance {
+            Instance::Receiver => Some(scope.receiver()),
+            Instance::Id(id) => {
+                let reader = scope.reader()?;
+                let target = self.entity_ref_target(entity);
+                Some(quote! {#reader.resolve(&TypedRef::<#target>::new(#id))?})
+            }
+        }
+    }
+
+    /// A value of the data type stored in an instance, the code evaluating to a reference to it if `by_ref` is
+    /// set. References to other instances are translated to their id for the reader only.
+    fn translate_stored(
+        &self,
+        scope: &Scope,
+        code: TokenStream,
+        data_type: &DataType,
+        optional: bool,
+        by_ref: bool,
+    ) -> Option<Translation> {
+        let kind = match self.parameter_kind(data_type)? {
+            ValueKind::Entity if scope.reader.is_none() => return None,
+            kind => kind,
+        };
+        let (code, atomic, optional) = match kind {
+            ValueKind::Integer | ValueKind::Boolean | ValueKind::Logical if by_ref => {
+                (quote! {*#code}, false, optional)
+            }
+            ValueKind::Real if optional => (quote! {#code.map(|value| value.0)}, true, optional),
+            ValueKind::Real => (quote! {#code.0}, true, optional),
+            ValueKind::String => (quote! {#code.clone()}, true, optional),
+            ValueKind::Entity => {
+                let code = if optional {
+                    quote! {#code.as_ref()?}
+                } else {
+                    code
+                };
+                if matches!(data_type, DataType::TypeRef { name } if self.entity_infos.contains_key(name)) {
+                    (quote! {#code.id()}, true, false)
+                } else {
+                    (quote! {#code.0}, true, false)
+                }
+            }
+            _ => (code, true, optional),
+        };
+        Some(Translation {
+            code,
+            kind,
+            optional,
+            atomic,
+            data_type: Some(data_type.clone()),
+            size: false,
+        })
+    }
+
+    /// An attribute of an instance of a select, read as an attribute of the member entity the instance is of.
+    fn translate_select_attribute(
+        &self,
+        scope: &Scope,
+        select: &str,
+        id: TokenStream,
+        name: &str,
+    ) -> Option<Translation> {
+        // a member without the attribute is read as those of its subtypes declaring it
+        let mut members = Vec::new();
+        for member in self.select_members(select) {
+            if self.has_attribute(member, name) {
+                members.push(member);
+            } else {
+                let subtypes = self
+                    .schema
+                    .declarations
+                    .iter()
+                    .filter_map(|declaration| match declaration {
+                        Declaration::Entity(entity)
+                            if entity.name != member
+                                && self.get_supertype_names(&entity.name).contains(&member)
+                                && self.has_attribute(&entity.name, name)
+                                && !self.get_supertype_names(&entity.name).iter().any(|supertype| {
+                                    *supertype != entity.name && self.has_attribute(supertype, name)
+                                }) =>
+                        {
+                            Some(entity.name.as_str())
+                        }
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                if subtypes.is_empty() {
+                    return None;
+                }
+                members.extend(subtypes);
+            }
+        }
+        let mut alternatives = Vec::new();
+        for member in &members {
+            let value = self.translate_attribute(scope, member, &Instance::Id(quote! {id}), name)?;
+            if alternatives
+                .first()
+                .is_some_and(|first: &Translation| first.kind != value.kind)
+            {
+                return None;
+            }
+            // members inheriting the attribute read it alike
+            if !alternatives
+                .iter()
+                .any(|other| other.code.to_string() == value.code.to_string())
+            {
+                alternatives.push(value);
+            }
+        }
+        let first = alternatives.first()?;
+        let kind = first.kind;
+        let code = if let [value] = alternatives.as_slice() {
+            let code = value.operand();
+            quote! {{
+                let id = #id;
+                #code
+            }}
+        } else {
+            if alternatives.iter().any(|value| value.optional) {
+                return None;
+            }
+            let value_type = value_type(kind)?;
+            let alternatives = alternatives.iter().map(Translation::some);
+            quote! {{
+                let id = #id;
+                None #( .or_else(|| -> Option<#value_type> { #alternatives }) )* ?
+            }}
+        };
+        Some(Translation {
+            code,
+            kind,
+            optional: first.optional,
+            atomic: true,
+            data_type: first.data_type.clone(),
+            size: false,
+        })
+    }
+
+    /// An element of an aggregate, `?` for an index out of bounds.
+    fn translate_element(&self, scope: &Scope, value: &Translation, index: &SimpleExpression) -> Option<Translation> {
+        scope.reader.as_ref()?;
+        if !matches!(value.kind, ValueKind::List | ValueKind::Array) {
+            return None;
+        }
+        let (base_type, first, unordered) = match self.aggregate_type(value.data_type.as_ref()?)? {
+            DataType::List { base_type, .. } | DataType::Bag { base_type, .. } => (base_type, 1, false),
+            DataType::Set { base_type, .. } => (base_type, 1, true),
+            DataType::Array { base_type, bound, .. } => (base_type, integer_literal(&bound.as_ref()?.start)?, false),
+            _ => return None,
+        };
+        let index = self.translate_simple_expression(scope, index)?;
+        if index.optional || index.kind != ValueKind::Integer {
+            return None;
+        }
+        let aggregate = if value.optional {
+            let operand = value.operand();
+            quote! {#operand.as_ref()?}
+        } else {
+            value.operand()
+        };
+        let element = match index.code.to_string().parse::<i64>() {
+            Ok(index) if index < first => return None,
+            Ok(index) => {
+                let offset = proc_macro2::Literal::i64_unsuffixed(index - first);
+                match (unordered, index == first) {
+                    (false, true) => quote! {#aggregate.first()?},
+                    (false, false) => quote! {#aggregate.get(#offset)?},
+                    (true, true) => quote! {#aggregate.iter().next()?},
+                    (true, false) => quote! {#aggregate.iter().nth(#offset)?},
+                }
+            }
+            Err(_) => {
+                let index = &index.code;
+                let first = proc_macro2::Literal::i64_unsuffixed(first);
+                if unordered {
+                    quote! {#aggregate.iter().nth(i64::checked_sub(#index, #first)? as usize)?}
+                } else {
+                    quote! {#aggregate.get(i64::checked_sub(#index, #first)? as usize)?}
+                }
+            }
+        };
+        self.translate_stored(scope, element, base_type, false, true)
+    }
+
+    /// `'SCHEMA.ENTITY' IN TYPEOF (instance)`, whether the instance is of the entity.
+    fn translate_type_test(
+        &self,
+        scope: &Scope,
+        name: &SimpleExpression,
+        types: &SimpleExpression,
+    ) -> Option<Translation> {
+        let name = match simple_access(name)? {
+            QualifiedAccess {
+                base: Primary::Literal(Literal::String(name)),
+                accessors,
+            } if accessors.is_empty() => name,
+            _ => return None,
+        };
+        let instance = match simple_access(types)? {
+            QualifiedAccess {
+                base: Primary::Reference(function),
+                accessors,
+            } if function.eq_ignore_ascii_case("typeof") => match accessors.as_slice() {
+                [Accessor::FunctionCall { parameters }] if parameters.len() == 1 => &parameters[0],
+                _ => return None,
+            },
+            _ => return None,
+        };
+        let (schema, entity) = name.rsplit_once('.')?;
+        if !schema.eq_ignore_ascii_case(&self.schema.name) {
+            return None;
+        }
+        let target = self.entity_ref_target(self.entity_key(entity)?);
+        let value = self.translate_expression(scope, instance)?;
+        if value.kind != ValueKind::Entity {
+            return None;
+        }
+        let reader = scope.reader()?;
+        let id = &value.code;
+        Some(Translation::new(
+            quote! {#reader.resolve(&TypedRef::<#target>::new(#id)).is_some()},
+            ValueKind::Boolean,
+            true,
+        ))
+    }
+
+    fn translate_call(&self, scope: &Scope, function: &str, parameters: &[Box<Expression>]) -> Option<Translation> {
+        let function = function.to_lowercase();
+        if let ("exists", [parameter]) = (function.as_str(), parameters) {
+            return self.translate_exists(scope, parameter);
+        }
+        let arguments = parameters
+            .iter()
+            .map(|parameter| self.translate_expression(scope, parameter))
+            .collect::<Option<Vec<_>>>()?;
+        match (function.as_str(), arguments.as_slice()) {
+            ("sizeof", [value]) if !value.optional && matches!(value.kind, ValueKind::List | ValueKind::Array) => {
+                let operand = value.operand();
+                Some(Translation {
+                    size: true,
+                    ..Translation::new(quote! {#operand.len() as i64}, ValueKind::Integer, false)
+                })
+            }
+            // arrays may have other bounds
+            ("hiindex", [value]) if !value.optional && value.kind == ValueKind::List => {
+                let operand = value.operand();
+                Some(Translation {
+                    size: true,
+                    ..Translation::new(quote! {#operand.len() as i64}, ValueKind::Integer, false)
+                })
+            }
+            ("loindex", [value]) if !value.optional && value.kind == ValueKind::List => {
+                Some(Translation::new(quote! {1}, ValueKind::Integer, true))
             }
+            ("nvl", [value, default]) if value.optional && !default.optional => {
+                let default = if value.kind == ValueKind::Real && default.kind == ValueKind::Integer {
+                    default.to_real()
+                } else if value.kind == default.kind {
+                    Translation::new(default.code.clone(), default.kind, default.atomic)
+                } else {
+                    return None;
+                };
+                let operand = value.operand();
+                let default = &default.code;
+                Some(Translation::new(
+                    quote! {#operand.unwrap_or(#default)},
+                    value.kind,
+                    true,
+                ))
+            }
+            ("abs", [value]) if value.is_number() => {
+                let operand = value.operand();
+                Some(Translation::new(quote! {#operand.abs()}, value.kind, true))
+            }
+            ("sqrt", [value]) if value.is_number() => {
+                let operand = value.to_real().operand();
+                Some(Translation::new(quote! {#operand.sqrt()}, ValueKind::Real, true))
+            }
+            _ => self.translate_function_call(scope, &function, &arguments),
         }
     }
+
+    /// A call of a translated schema function.
+    fn translate_function_call(&self, scope: &Scope, name: &str, arguments: &[Translation]) -> Option<Translation> {
+        if !self.functions.contains(name) {
+            return None;
+        }
+        let function = self.get_function(name)?;
+        if function.parameters.len() != arguments.len() {
+            return None;
+        }
+        let arguments = function
+            .parameters
+            .iter()
+            .zip(arguments)
+            .map(|(parameter, argument)| self.translate_argument(&parameter.data_type, argument))
+            .collect::<Option<Vec<_>>>()?;
+        let kind = self.value_kind(&function.return_type)?;
+        let reader = scope.reader()?;
+        let ident = format_ident!("{}", function.name.to_snake_case());
+        Some(Translation::new(
+            quote! {functions::#ident(#reader, #( #arguments ),*)?},
+            kind,
+            true,
+        ))
+    }
+
+    /// An argument passed for a parameter of the data type, indeterminate arguments are not passed.
+    fn translate_argument(&self, data_type: &DataType, argument: &Translation) -> Option<TokenStream> {
+        if argument.optional {
+            return None;
+        }
+        let code = &argument.code;
+        match (self.parameter_kind(data_type)?, argument.kind) {
+            (ValueKind::Real, ValueKind::Integer) => Some(argument.to_real().code),
+            (ValueKind::Logical, ValueKind::Boolean) => Some(quote! {Some(#code)}),
+            (ValueKind::String, ValueKind::String) => Some(quote! {&#code}),
+            (ValueKind::List, ValueKind::List) | (ValueKind::Array, ValueKind::Array) => {
+                let parameter_type = self.aggregate_type(data_type)?;
+                let argument_type = self.aggregate_type(argument.data_type.as_ref()?)?;
+                if self.type_name(parameter_type, false).to_string() == self.type_name(argument_type, false).to_string()
+                {
+                    Some(code.clone())
+                } else {
+                    None
+                }
+            }
+            (kind, argument_kind) if kind == argument_kind => Some(code.clone()),
+            _ => None,
+        }
+    }
+
+    /// `EXISTS` of an attribute, which is false only for an unset optional attribute.
+    fn translate_exists(&self, scope: &Scope, expr: &Expression) -> Option<Translation> {
+        if !expr.operations.is_empty() {
+            return None;
+        }
+        let access = simple_access(&expr.operand)?;
+        let (entity, name) = match (&access.base, access.accessors.as_slice()) {
+            (Primary::Reference(name), []) => (scope.entity?, name),
+            (Primary::Constant(base), [Accessor::Group { entity: supertype }, Accessor::Attribute { name }])
+                if base.eq_ignore_ascii_case("self") && self.entity_infos.contains_key(supertype) =>
+            {
+                (supertype.as_str(), name)
+            }
+            _ => return None,
+        };
+        let (owner, attr) = self.attribute_owner(entity, &name.to_snake_case())?;
+        if self.is_partial_attribute(owner, &attr.name) {
+            return None;
+        }
+        let code = if attr.optional {
+            let trait_name = self.entity_infos[owner].trait_name();
+            let field = format_ident!("{}", attr.name);
+            let receiver = scope.receiver();
+            quote! {#trait_name::#field(#receiver).is_some()}
+        } else {
+            quote! {true}
+        };
+        Some(Translation::new(code, ValueKind::Boolean, true))
+    }
+}
+
+/// The code of the id of an instance.
+fn instance_id(scope: &Scope, instance: &Instance) -> Option<TokenStream> {
+    match instance {
+        Instance::Receiver => scope.id.clone(),
+        Instance::Id(id) => Some(id.clone()),
+    }
+}
+
+/// The Rust type of values of the kind.
+fn value_type(kind: ValueKind) -> Option<TokenStream> {
+    match kind {
+        ValueKind::Integer | ValueKind::Entity => Some(quote! {i64}),
+        ValueKind::Real => Some(quote! {f64}),
+        ValueKind::Boolean => Some(quote! {bool}),
+        ValueKind::Logical => Some(quote! {Option<bool>}),
+        ValueKind::String => Some(quote! {String}),
+        ValueKind::List | ValueKind::Array => None,
+    }
+}
+
+/// The qualified access an expression consists of.
+fn simple_access(expr: &SimpleExpression) -> Option<&QualifiedAccess> {
+    let term = &expr.operand;
+    if !expr.operations.is_empty() || !term.operations.is_empty() || !term.operand.operations.is_empty() {
+        return None;
+    }
+    match &term.operand.operand {
+        SimpleFactor::QualifiedAccess(access) => Some(access),
+        _ => None,
+    }
+}
+
+/// Whether an expression is the indeterminate value `?`.
+fn is_indeterminate(expr: &Expression) -> bool {
+    if !expr.operations.is_empty() {
+        return false;
+    }
+    match simple_access(&expr.operand) {
+        Some(QualifiedAccess {
+            base: Primary::Constant(name),
+            accessors,
+        }) if accessors.is_empty() => name == "?",
+        Some(QualifiedAccess {
+            base: Primary::Grouped(expr),
+            accessors,
+        }) if accessors.is_empty() => is_indeterminate(expr),
+        _ => false,
+    }
+}
+
+/// The value of an integer literal.
+fn integer_literal(expr: &SimpleExpression) -> Option<i64> {
+    match simple_access(expr)? {
+        QualifiedAccess {
+            base: Primary::Literal(Literal::Integer(value)),
+            accessors,
+        } if accessors.is_empty() => Some(*value),
+        _ => None,
+    }
+}
+
+fn translate_literal(literal: &Literal) -> Option<Translation> {
+    match literal {
+        Literal::Integer(value) => {
+            let code = proc_macro2::Literal::i64_unsuffixed(*value);
+            Some(Translation::new(quote! {#code}, ValueKind::Integer, *value >= 0))
+        }
+        Literal::Real(value) if value.is_finite() => {
+            let code = proc_macro2::Literal::f64_unsuffixed(*value);
+            Some(Translation::new(quote! {#code}, ValueKind::Real, *value >= 0.0))
+        }
+        Literal::Logical(Some(value)) => Some(Translation::new(quote! {#value}, ValueKind::Boolean, true)),
+        Literal::Logical(None) => Some(Translation::new(quote! {None}, ValueKind::Logical, true)),
+        Literal::String(value) => Some(Translation::new(quote! {#value.to_string()}, ValueKind::String, true)),
+        _ => None,
+    }
+}
+
+/// Translate a binary operation on values, numbers are promoted to `f64` as needed.
+/// Integer addition, subtraction, multiplication or negation as `0 - x`, checked for overflow as in the evaluator.
+///
+/// An overflow is `?` and so needs an accessor returning `Option`, unless both operands are literals and the result
+/// is computed here.
+fn translate_checked(scope: &Scope, left: &Translation, op: &Operator, right: &Translation) -> Option<Translation> {
+    // a negative literal is written as a minus and a literal
+    let literal = |value: &Translation| value.code.to_string().replace(' ', "").parse::<i64>();
+    if let (Ok(a), Ok(b)) = (literal(left), literal(right)) {
+        let value = match op {
+            Operator::Add => a.checked_add(b),
+            Operator::Sub | Operator::Neg => a.checked_sub(b),
+            Operator::Mul => a.checked_mul(b),
+            _ => None,
+        }?;
+        let code = proc_macro2::Literal::i64_unsuffixed(value);
+        return Some(Translation::new(quote! {#code}, ValueKind::Integer, value >= 0));
+    }
+    let (a, b) = (&left.code, &right.code);
+    // a size less a number which is not negative stays in range
+    if let (Operator::Sub, true, Ok(0..=i64::MAX)) = (op, left.size, literal(right)) {
+        let a = left.operand();
+        return Some(Translation::new(quote! {#a - #b}, ValueKind::Integer, false));
+    }
+    scope.reader.as_ref()?;
+    let code = match op {
+        Operator::Add => quote! {i64::checked_add(#a, #b)?},
+        Operator::Sub => quote! {i64::checked_sub(#a, #b)?},
+        Operator::Neg => quote! {i64::checked_neg(#b)?},
+        Operator::Mul => quote! {i64::checked_mul(#a, #b)?},
+        _ => return None,
+    };
+    Some(Translation::new(code, ValueKind::Integer, true))
+}
+
+fn translate_operation(scope: &Scope, left: Translation, op: &Operator, right: Translation) -> Option<Translation> {
+    if left.optional || right.optional {
+        return None;
+    }
+    let numbers = left.is_number() && right.is_number();
+    let (left, right, kind) = if numbers && (left.kind == ValueKind::Real || right.kind == ValueKind::Real) {
+        (left.to_real(), right.to_real(), ValueKind::Real)
+    } else {
+        let kind = left.kind;
+        (left, right, kind)
+    };
+    let (a, b) = (left.operand(), right.operand());
+    let (code, kind) = match op {
+        Operator::Add | Operator::Sub | Operator::Mul if numbers && kind == ValueKind::Integer => {
+            return translate_checked(scope, &left, op, &right);
+        }
+        Operator::Add if numbers => (quote! {#a + #b}, kind),
+        Operator::Sub if numbers => (quote! {#a - #b}, kind),
+        Operator::Mul if numbers => (quote! {#a * #b}, kind),
+        Operator::Div if numbers => {
+            let (a, b) = (left.to_real().operand(), right.to_real().operand());
+            (quote! {#a / #b}, ValueKind::Real)
+        }
+        Operator::IntegerDiv if left.kind == ValueKind::Integer && right.kind == ValueKind::Integer => {
+            let (a, b) = (left.to_real().operand(), right.to_real().operand());
+            (quote! {(#a / #b).floor() as i64}, ValueKind::Integer)
+        }
+        Operator::Power if numbers && right.kind == ValueKind::Integer => {
+            let a = left.to_real().operand();
+            (quote! {#a.powi(#b as i32)}, ValueKind::Real)
+        }
+        Operator::Power if numbers => {
+            let a = left.to_real().operand();
+            (quote! {#a.powf(#b)}, ValueKind::Real)
+        }
+        Operator::And if left.kind == ValueKind::Boolean && right.kind == ValueKind::Boolean => {
+            (quote! {#a && #b}, ValueKind::Boolean)
+        }
+        Operator::Or if left.kind == ValueKind::Boolean && right.kind == ValueKind::Boolean => {
+            (quote! {#a || #b}, ValueKind::Boolean)
+        }
+        Operator::Xor if left.kind == ValueKind::Boolean && right.kind == ValueKind::Boolean => {
+            (quote! {#a ^ #b}, ValueKind::Boolean)
+        }
+        _ if left.kind != right.kind
+            || matches!(
+                kind,
+                ValueKind::List | ValueKind::Array | ValueKind::Logical | ValueKind::Entity
+            ) =>
+        {
+            return None
+        }
+        Operator::Equal => (quote! {#a == #b}, ValueKind::Boolean),
+        Operator::NotEqual => (quote! {#a != #b}, ValueKind::Boolean),
+        Operator::Less if numbers => (quote! {#a < #b}, ValueKind::Boolean),
+        Operator::Greater if numbers => (quote! {#a > #b}, ValueKind::Boolean),
+        Operator::LessOrEqual if numbers => (quote! {#a <= #b}, ValueKind::Boolean),
+        Operator::GreaterOrEqual if numbers => (quote! {#a >= #b}, ValueKind::Boolean),
+        _ => return None,
+    };
+    Some(Translation::new(code, kind, false))
 }
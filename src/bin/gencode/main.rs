@@ -2,37 +2,58 @@ use structopt::StructOpt;
 
 mod generator;
 use generator::Generator;
-use iso_10303::express::parser;
+use iso_10303::express::load_schema;
 use std::process::Command;
 
 #[derive(StructOpt, Debug)]
 struct Args {
     schema: std::path::PathBuf,
+    /// EXPRESS files with further schemas interfaced by `USE FROM` or `REFERENCE FROM`
+    #[structopt(long)]
+    include: Vec<std::path::PathBuf>,
+    /// The schema to link, the first schema of the first file by default
+    #[structopt(long)]
+    root_schema: Option<String>,
     parser: std::path::PathBuf,
     name: String,
 }
 
-fn main() -> std::io::Result<()> {
-    let args = Args::from_args();
-    println!("work dir: {}", std::env::current_dir()?.display());
-    println!("read file: {}", args.schema.display());
-    let bytes = std::fs::read(args.schema)?;
-    match parser::schema().parse(&bytes) {
-        Ok(schema) => {
-            // generate parser code
-            println!("generate code");
-            let generator = Generator::new(schema, args.name);
-            let code = generator.gencode();
+fn main() {
+    if let Err(error) = run(Args::from_args()) {
+        eprintln!("error: {}", error);
+        std::process::exit(1);
+    }
+}
 
-            // write code file
-            println!("write file: {}", args.parser.display());
-            std::fs::write(&args.parser, code.as_bytes())?;
+fn run(args: Args) -> Result<(), Box<dyn std::error::Error>> {
+    println!("work dir: {}", std::env::current_dir()?.display());
+    let paths = std::iter::once(&args.schema).chain(&args.include).collect::<Vec<_>>();
+    for path in &paths {
+        println!("read file: {}", path.display());
+    }
+    let schema = load_schema(paths, args.root_schema.as_deref())?;
 
-            // format code file
-            Command::new("rustfmt").arg(args.parser).output()?;
+    // generate parser code
+    println!("generate code");
+    let generator = match Generator::new(schema, args.name) {
+        Ok(generator) => generator,
+        Err(diagnostics) => {
+            for diagnostic in &diagnostics {
+                eprintln!("error: {}", diagnostic);
+            }
+            return Err(format!("{} errors in schema", diagnostics.len()).into());
         }
-        Err(err) => println!("{:?}", err),
-    }
+    };
+    let code = generator.gencode();
 
+    // write code file
+    println!("write file: {}", args.parser.display());
+    std::fs::write(&args.parser, code.as_bytes())?;
+
+    // format code file
+    let status = Command::new("rustfmt").arg(&args.parser).status()?;
+    if !status.success() {
+        return Err(format!("rustfmt failed with {}", status).into());
+    }
     Ok(())
 }
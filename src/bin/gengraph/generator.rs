@@ -4,40 +4,34 @@ use petgraph::graph::{DiGraph, NodeIndex};
 use std::collections::{HashMap, HashSet};
 
 pub struct Generator {
-    schema: Schema,
     nodes: HashMap<String, NodeIndex>,
     graph: DiGraph<String, ()>,
 }
 
-fn create_graph(schema: &Schema) -> (HashMap<String, NodeIndex>, DiGraph<String, ()>) {
+fn create_graph(resolved: &ResolvedSchema) -> (HashMap<String, NodeIndex>, DiGraph<String, ()>) {
     let mut graph = DiGraph::new();
     let mut nodes = HashMap::new();
+    let mut node = |graph: &mut DiGraph<String, ()>, entity: &Entity| {
+        let name = entity.name.to_camel_case();
+        *nodes.entry(name.clone()).or_insert_with(|| graph.add_node(name))
+    };
 
-    for declaration in &schema.declarations {
-        match declaration {
-            Declaration::Entity(entity) => {
-                if entity.supertypes.len() > 0 {
-                    let child_id = *nodes
-                        .entry(entity.name.to_camel_case())
-                        .or_insert(graph.add_node(entity.name.to_camel_case()));
-                    for supertype in &entity.supertypes {
-                        let parent_id = *nodes
-                            .entry(supertype.to_camel_case())
-                            .or_insert(graph.add_node(supertype.to_camel_case()));
-                        graph.add_edge(parent_id, child_id, ());
-                    }
-                }
+    for (_, resolved_entity) in resolved.entities() {
+        if !resolved_entity.supertypes.is_empty() {
+            let child_id = node(&mut graph, resolved_entity.entity);
+            for supertype in &resolved_entity.supertypes {
+                let parent_id = node(&mut graph, resolved.entity(*supertype).entity);
+                graph.add_edge(parent_id, child_id, ());
             }
-            _ => {}
         }
     }
     (nodes, graph)
 }
 
 impl Generator {
-    pub fn new(schema: Schema) -> Generator {
-        let (nodes, graph) = create_graph(&schema);
-        Generator { schema, nodes, graph }
+    pub fn new(resolved: &ResolvedSchema) -> Generator {
+        let (nodes, graph) = create_graph(resolved);
+        Generator { nodes, graph }
     }
 
     fn write_edges(&self, parent_id: NodeIndex, visited: &mut HashSet<NodeIndex>, code: &mut String) {
@@ -47,13 +41,13 @@ impl Generator {
                 .neighbors(parent_id)
                 .map(|child_id| self.graph.node_weight(child_id).unwrap())
                 .fold(String::new(), |mut list, child| {
-                    if list.len() > 0 {
+                    if !list.is_empty() {
                         list.push_str(", ");
                     }
                     list.push_str(child);
                     list
                 });
-            if children.len() > 0 {
+            if !children.is_empty() {
                 let parent = self.graph.node_weight(parent_id).unwrap();
                 code.push_str(&format!("  {} -> {{{}}};\n", parent, children));
                 for child_id in self.graph.neighbors(parent_id) {
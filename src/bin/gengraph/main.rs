@@ -2,43 +2,60 @@ use structopt::StructOpt;
 
 mod generator;
 use generator::Generator;
-use iso_10303::express::parser;
+use iso_10303::express::{load_schema, ResolvedSchema};
 use std::process::Command;
 
 #[derive(StructOpt, Debug)]
 struct Args {
     schema: std::path::PathBuf,
+    /// EXPRESS files with further schemas interfaced by `USE FROM` or `REFERENCE FROM`
+    #[structopt(long)]
+    include: Vec<std::path::PathBuf>,
+    /// The schema to link, the first schema of the first file by default
+    #[structopt(long)]
+    root_schema: Option<String>,
     dotfile: std::path::PathBuf,
     root: Option<String>,
 }
 
-fn main() -> std::io::Result<()> {
-    let args = Args::from_args();
-    println!("work dir: {}", std::env::current_dir()?.display());
-    println!("read file: {}", args.schema.display());
-    let bytes = std::fs::read(args.schema)?;
-    match parser::schema().parse(&bytes) {
-        Ok(schema) => {
-            // generate parser dot file
-            println!("generate dot file");
-            let generator = Generator::new(schema);
-            let code = generator.gencode(args.root);
+fn main() {
+    if let Err(error) = run(Args::from_args()) {
+        eprintln!("error: {}", error);
+        std::process::exit(1);
+    }
+}
 
-            // write code file
-            println!("write file: {}", args.dotfile.display());
-            std::fs::write(&args.dotfile, code.as_bytes())?;
+fn run(args: Args) -> Result<(), Box<dyn std::error::Error>> {
+    println!("work dir: {}", std::env::current_dir()?.display());
+    let paths = std::iter::once(&args.schema).chain(&args.include).collect::<Vec<_>>();
+    for path in &paths {
+        println!("read file: {}", path.display());
+    }
+    let schema = load_schema(paths, args.root_schema.as_deref())?;
 
-            // generate svg image
-            let svgfile = args.dotfile.with_extension("svg");
-            Command::new("dot")
-                .arg("-Tsvg")
-                .arg(args.dotfile)
-                .arg("-o")
-                .arg(svgfile)
-                .output()?;
-        }
-        Err(err) => println!("{:?}", err),
+    // generate parser dot file
+    println!("generate dot file");
+    let resolved = ResolvedSchema::new(&schema);
+    for diagnostic in resolved.diagnostics() {
+        eprintln!("warning: {}", diagnostic);
     }
+    let generator = Generator::new(&resolved);
+    let code = generator.gencode(args.root);
+
+    // write code file
+    println!("write file: {}", args.dotfile.display());
+    std::fs::write(&args.dotfile, code.as_bytes())?;
 
+    // generate svg image
+    let svgfile = args.dotfile.with_extension("svg");
+    let status = Command::new("dot")
+        .arg("-Tsvg")
+        .arg(&args.dotfile)
+        .arg("-o")
+        .arg(svgfile)
+        .status()?;
+    if !status.success() {
+        return Err(format!("dot failed with {}", status).into());
+    }
     Ok(())
 }
@@ -0,0 +1,64 @@
+use structopt::StructOpt;
+
+use iso_10303::express::{load_schema, Model};
+use std::io::BufReader;
+
+#[derive(StructOpt, Debug)]
+struct Args {
+    schema: std::path::PathBuf,
+    /// EXPRESS files with further schemas interfaced by `USE FROM` or `REFERENCE FROM`
+    #[structopt(long)]
+    include: Vec<std::path::PathBuf>,
+    /// The schema to link, the first schema of the first file by default
+    #[structopt(long)]
+    root_schema: Option<String>,
+    step: std::path::PathBuf,
+}
+
+/// Exits with 1 when the model violates the schema and with 2 when it cannot be checked.
+fn main() {
+    match run(Args::from_args()) {
+        Ok(true) => {}
+        Ok(false) => std::process::exit(1),
+        Err(error) => {
+            eprintln!("error: {}", error);
+            std::process::exit(2);
+        }
+    }
+}
+
+/// Validate the model, `false` when it is invalid.
+fn run(args: Args) -> Result<bool, Box<dyn std::error::Error>> {
+    let paths = std::iter::once(&args.schema).chain(&args.include).collect::<Vec<_>>();
+    for path in &paths {
+        println!("read file: {}", path.display());
+    }
+    let schema = load_schema(paths, args.root_schema.as_deref())?;
+
+    println!("read file: {}", args.step.display());
+    let file = std::fs::File::open(args.step)?;
+    let model = Model::read_from(&schema, BufReader::new(file))?;
+    for diagnostic in model.resolved().diagnostics() {
+        eprintln!("warning: {}", diagnostic);
+    }
+
+    let report = model.validate();
+    for violation in report.failed.iter().chain(&report.indeterminate) {
+        println!("{}", violation);
+    }
+    for duplicate in &report.duplicates {
+        println!("{}", duplicate);
+    }
+    for violation in &report.global_rules {
+        println!("{}", violation);
+    }
+    println!(
+        "{} rules checked, {} failed, {} indeterminate, {} not unique, {} global rules not satisfied",
+        report.checked,
+        report.failed.len(),
+        report.indeterminate.len(),
+        report.duplicates.len(),
+        report.global_rules.len()
+    );
+    Ok(report.is_valid())
+}
@@ -67,22 +67,13 @@ pub struct BoundSpec {
 
 impl DataType {
     pub fn is_number(&self) -> bool {
-        match *self {
-            DataType::Number => true,
-            _ => false,
-        }
+        matches!(*self, DataType::Number)
     }
     pub fn is_real(&self) -> bool {
-        match *self {
-            DataType::Real { .. } => true,
-            _ => false,
-        }
+        matches!(*self, DataType::Real { .. })
     }
     pub fn is_integer(&self) -> bool {
-        match *self {
-            DataType::Integer => true,
-            _ => false,
-        }
+        matches!(*self, DataType::Integer)
     }
     pub fn type_ref(&self) -> Option<&String> {
         match self {
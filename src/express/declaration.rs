@@ -1,10 +1,11 @@
-use super::{DataType, Expression};
+use super::{DataType, Expression, Statement};
 
 #[derive(Debug)]
 pub enum Declaration {
     TypeDef(TypeDef),
     Entity(Entity),
     Function(Function),
+    Procedure(Procedure),
     Rule(Rule),
 }
 
@@ -22,6 +23,7 @@ pub struct Entity {
     pub supertypes: Vec<String>,
     pub attributes: Vec<Attribute>,
     pub derives: Vec<DerivedAttribute>,
+    pub inverses: Vec<InverseAttribute>,
     pub domain_rules: Vec<DomainRule>,
     pub unique_rules: Vec<UniqueRule>,
 }
@@ -33,6 +35,18 @@ pub struct Function {
     pub parameters: Vec<Parameter>,
     pub statements: Vec<Statement>,
     pub declarations: Vec<Declaration>,
+    pub constants: Vec<Constant>,
+    pub locals: Vec<Local>,
+}
+
+#[derive(Debug)]
+pub struct Procedure {
+    pub name: String,
+    pub parameters: Vec<Parameter>,
+    pub statements: Vec<Statement>,
+    pub declarations: Vec<Declaration>,
+    pub constants: Vec<Constant>,
+    pub locals: Vec<Local>,
 }
 
 #[derive(Debug)]
@@ -42,6 +56,8 @@ pub struct Rule {
     pub statements: Vec<Statement>,
     pub domain_rules: Vec<DomainRule>,
     pub declarations: Vec<Declaration>,
+    pub constants: Vec<Constant>,
+    pub locals: Vec<Local>,
 }
 
 #[derive(Debug, Clone)]
@@ -60,6 +76,16 @@ pub struct DerivedAttribute {
     pub expr: Expression,
 }
 
+/// An INVERSE attribute, the instances of `entity` which refer to this one by their `attribute`.
+#[derive(Debug)]
+pub struct InverseAttribute {
+    pub name: String,
+    pub supertype: Option<String>,
+    /// A `Set` or `Bag` of the entity with its bounds, or a `TypeRef` to a single entity.
+    pub data_type: DataType,
+    pub attribute: String,
+}
+
 #[derive(Debug)]
 pub struct AttributeReference {
     pub name: String,
@@ -82,6 +108,8 @@ pub struct UniqueRule {
 pub struct Parameter {
     pub name: String,
     pub data_type: DataType,
+    /// A `VAR` parameter of a procedure, whose assignments are seen by the caller.
+    pub var: bool,
 }
 
 #[derive(Debug)]
@@ -91,27 +119,46 @@ pub struct Constant {
     pub expr: Expression,
 }
 
+/// A local variable of a function, procedure or rule.
 #[derive(Debug)]
-pub struct Statement {
-    pub text: String,
+pub struct Local {
+    pub name: String,
+    pub data_type: DataType,
+    pub expr: Option<Expression>,
 }
 
 impl Declaration {
-    pub fn is_type_def(&self) -> bool {
+    pub fn name(&self) -> &str {
         match self {
-            Declaration::TypeDef(_) => true,
-            _ => false,
+            Declaration::TypeDef(type_def) => &type_def.name,
+            Declaration::Entity(entity) => &entity.name,
+            Declaration::Function(function) => &function.name,
+            Declaration::Procedure(procedure) => &procedure.name,
+            Declaration::Rule(rule) => &rule.name,
         }
     }
+
+    pub fn is_type_def(&self) -> bool {
+        matches!(self, Declaration::TypeDef(_))
+    }
 }
 
 impl Entity {
     pub fn get_attribute(&self, name: &str) -> Option<&Attribute> {
-        for attribute in &self.attributes {
-            if attribute.name == name {
-                return Some(attribute);
-            }
+        self.attributes.iter().find(|attribute| attribute.name == name)
+    }
+}
+
+impl InverseAttribute {
+    /// The name of the entity holding the forward attribute.
+    pub fn entity(&self) -> &str {
+        match &self.data_type {
+            DataType::Set { base_type, .. } | DataType::Bag { base_type, .. } => match &**base_type {
+                DataType::TypeRef { name } => name,
+                _ => unreachable!("inverse attribute of a non-entity type"),
+            },
+            DataType::TypeRef { name } => name,
+            _ => unreachable!("inverse attribute of a non-entity type"),
         }
-        return None;
     }
 }
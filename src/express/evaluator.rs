@@ -0,0 +1,967 @@
+use super::{
+    Accessor, BoundSpec, DataType, Entity, Expression, Factor, Literal, Model, Operator, Primary, QualifiedAccess,
+    SimpleExpression, SimpleFactor, Term, Value,
+};
+use crate::step::Binary;
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt;
+
+/// Errors evaluating an EXPRESS expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EvalError {
+    /// A construct the evaluator can not compute, such as assigning to an attribute of an instance.
+    Unsupported(String),
+    UnknownName(String),
+    UnknownInstance(i64),
+    /// An operand of the wrong kind, such as an attribute of a number.
+    TypeMismatch(String),
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EvalError::Unsupported(construct) => write!(f, "{} is not supported", construct),
+            EvalError::UnknownName(name) => write!(f, "{} is not defined", name),
+            EvalError::UnknownInstance(id) => write!(f, "#{} does not exist", id),
+            EvalError::TypeMismatch(message) => write!(f, "type mismatch: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for EvalError {}
+
+/// Evaluates EXPRESS expressions against the instances of a `Model`.
+///
+/// LOGICAL values are three-valued and operations on the indeterminate value `?` give `?` or UNKNOWN,
+/// so a rule over an unset optional attribute does not fail.
+pub struct Evaluator<'m, 'a> {
+    pub(super) model: &'m Model<'a>,
+    /// Variables in scope, the innermost last.
+    pub(super) variables: Vec<Variable<'a>>,
+    /// The number of schema functions and procedures being executed.
+    pub(super) depth: usize,
+}
+
+pub(super) struct Variable<'a> {
+    /// The name in lower case.
+    pub name: String,
+    pub value: Value,
+    /// The declared type of a parameter or local variable, which assigned values are conformed to.
+    pub data_type: Option<&'a DataType>,
+}
+
+/// The type an accessed value is declared with, and the instance whose attribute it is, for the bounds of an ARRAY.
+#[derive(Clone, Copy)]
+pub(super) struct Declared<'a> {
+    pub data_type: &'a DataType,
+    /// The instance the bound expressions of an attribute type refer to as SELF.
+    pub owner: Option<i64>,
+}
+
+impl<'m, 'a> Evaluator<'m, 'a> {
+    pub fn new(model: &'m Model<'a>) -> Self {
+        Evaluator {
+            model,
+            variables: Vec::new(),
+            depth: 0,
+        }
+    }
+
+    /// An evaluator for the rules and derived attributes of an instance, which `SELF` refers to.
+    pub fn with_self(model: &'m Model<'a>, id: i64) -> Self {
+        let mut evaluator = Evaluator::new(model);
+        evaluator.bind("self", Value::Entity(id));
+        evaluator
+    }
+
+    pub fn model(&self) -> &'m Model<'a> {
+        self.model
+    }
+
+    /// Bind a variable, hiding an earlier one of the same name.
+    pub fn bind(&mut self, name: &str, value: Value) {
+        self.variables.push(Variable {
+            name: name.to_lowercase(),
+            value,
+            data_type: None,
+        });
+    }
+
+    /// Remove the variable bound last.
+    pub fn unbind(&mut self) {
+        self.variables.pop();
+    }
+
+    pub fn variable(&self, name: &str) -> Option<&Value> {
+        self.variables
+            .iter()
+            .rev()
+            .find(|variable| variable.name.eq_ignore_ascii_case(name))
+            .map(|variable| &variable.value)
+    }
+
+    pub fn evaluate(&mut self, expression: &Expression) -> Result<Value, EvalError> {
+        let mut value = self.simple_expression(&expression.operand)?;
+        for (op, operand) in &expression.operations {
+            let operand = self.simple_expression(operand)?;
+            value = relation(op, &value, &operand)?;
+        }
+        Ok(value)
+    }
+
+    /// The value of an expression as LOGICAL, `None` for UNKNOWN.
+    pub fn evaluate_logical(&mut self, expression: &Expression) -> Result<Option<bool>, EvalError> {
+        let value = self.evaluate(expression)?;
+        value
+            .as_logical()
+            .ok_or_else(|| EvalError::TypeMismatch(format!("{:?} is not a LOGICAL", value)))
+    }
+
+    pub fn simple_expression(&mut self, expression: &SimpleExpression) -> Result<Value, EvalError> {
+        let mut value = self.term(&expression.operand)?;
+        for (op, operand) in &expression.operations {
+            // `OR` does not need its second operand once the first is TRUE
+            if let (Operator::Or, Some(Some(true))) = (op, value.as_logical()) {
+                continue;
+            }
+            let operand = self.term(operand)?;
+            value = arithmetic(op, value, operand)?;
+        }
+        Ok(value)
+    }
+
+    fn term(&mut self, term: &Term) -> Result<Value, EvalError> {
+        let mut value = self.factor(&term.operand)?;
+        for (op, operand) in &term.operations {
+            if let (Operator::And, Some(Some(false))) = (op, value.as_logical()) {
+                continue;
+            }
+            let operand = self.factor(operand)?;
+            value = arithmetic(op, value, operand)?;
+        }
+        Ok(value)
+    }
+
+    fn factor(&mut self, factor: &Factor) -> Result<Value, EvalError> {
+        // `**` is right associative
+        let mut operands = vec![self.simple_factor(&factor.operand)?];
+        for (_, operand) in &factor.operations {
+            operands.push(self.simple_factor(operand)?);
+        }
+        let mut value = operands.pop().unwrap();
+        while let Some(base) = operands.pop() {
+            value = arithmetic(&Operator::Power, base, value)?;
+        }
+        Ok(value)
+    }
+
+    fn simple_factor(&mut self, factor: &SimpleFactor) -> Result<Value, EvalError> {
+        match factor {
+            SimpleFactor::AggregateInitializer { elements } => {
+                let mut values = Vec::with_capacity(elements.len());
+                for (element, repetition) in elements {
+                    let value = self.evaluate(element)?;
+                    let count = match repetition {
+                        Some(repetition) => {
+                            let count = self.evaluate(repetition)?;
+                            count
+                                .as_integer()
+                                .ok_or_else(|| EvalError::TypeMismatch(format!("repetition {:?}", count)))?
+                        }
+                        None => 1,
+                    };
+                    for _ in 0..count {
+                        values.push(value.clone());
+                    }
+                }
+                Ok(Value::Aggregate(values))
+            }
+            SimpleFactor::EnityConstructor { entity, parameters } => {
+                let mut arguments = Vec::with_capacity(parameters.len());
+                for parameter in parameters {
+                    arguments.push(self.evaluate(parameter)?);
+                }
+                match self.model.entity(entity) {
+                    Some(entity) => self.construct(entity, arguments),
+                    None => Err(EvalError::UnknownName(entity.clone())),
+                }
+            }
+            SimpleFactor::EnumReference => Err(EvalError::Unsupported("enumeration reference".to_string())),
+            SimpleFactor::Interval {
+                low,
+                op1,
+                term,
+                op2,
+                high,
+            } => {
+                let low = self.simple_expression(low)?;
+                let term = self.simple_expression(term)?;
+                let high = self.simple_expression(high)?;
+                let first = relation(op1, &low, &term)?.as_logical().unwrap_or(None);
+                let second = relation(op2, &term, &high)?.as_logical().unwrap_or(None);
+                Ok(Value::Logical(and(first, second)))
+            }
+            SimpleFactor::QueryExpression {
+                variable,
+                source,
+                condition,
+            } => {
+                let source = self.simple_expression(source)?;
+                let elements = match source.plain() {
+                    Value::Indeterminate => return Ok(Value::Indeterminate),
+                    Value::Aggregate(elements) => elements,
+                    _ => return Err(EvalError::TypeMismatch(format!("QUERY over {:?}", source))),
+                };
+                let mut selected = Vec::new();
+                for element in elements {
+                    self.bind(variable, element.clone());
+                    let result = self.evaluate_logical(condition);
+                    self.unbind();
+                    if result? == Some(true) {
+                        selected.push(element.clone());
+                    }
+                }
+                Ok(Value::Aggregate(selected))
+            }
+            SimpleFactor::UnaryExpression { op, operand } => {
+                let value = self.qualified_access(operand)?;
+                match (op, value.plain()) {
+                    (_, Value::Indeterminate) => Ok(Value::Indeterminate),
+                    (Operator::Add, _) if value.as_real().is_some() => Ok(value),
+                    (Operator::Neg, Value::Integer(value)) => {
+                        Ok(value.checked_neg().map_or(Value::Indeterminate, Value::Integer))
+                    }
+                    (Operator::Neg, Value::Real(value)) => Ok(Value::Real(-value)),
+                    (Operator::Not, Value::Logical(value)) => Ok(Value::Logical(value.map(|value| !value))),
+                    _ => Err(EvalError::TypeMismatch(format!("{:?} of {:?}", op, value))),
+                }
+            }
+            SimpleFactor::QualifiedAccess(access) => self.qualified_access(access),
+        }
+    }
+
+    pub(super) fn qualified_access(&mut self, access: &QualifiedAccess) -> Result<Value, EvalError> {
+        self.declared_access(access).map(|(value, _)| value)
+    }
+
+    /// The value of a qualified access together with the type it is declared with, where that is known.
+    fn declared_access(&mut self, access: &QualifiedAccess) -> Result<(Value, Option<Declared<'a>>), EvalError> {
+        let mut accessors = access.accessors.iter().peekable();
+        let (mut value, mut declared) = match (&access.base, accessors.peek()) {
+            (Primary::Reference(name), Some(Accessor::FunctionCall { parameters })) => {
+                accessors.next();
+                let builtin = name.to_lowercase();
+                let value = match parameters.as_slice() {
+                    [parameter]
+                        if (builtin == "loindex" || builtin == "hiindex") && self.model.function(name).is_none() =>
+                    {
+                        self.index_function(&builtin, parameter)?
+                    }
+                    _ => {
+                        let mut arguments = Vec::with_capacity(parameters.len());
+                        for parameter in parameters {
+                            arguments.push(self.evaluate(parameter)?);
+                        }
+                        self.call(name, arguments)?
+                    }
+                };
+                (value, None)
+            }
+            (Primary::Reference(name), Some(Accessor::Attribute { name: item }))
+                if self.variable(name).is_none() && self.model.type_def(name).is_some() =>
+            {
+                // an enumeration item qualified by its type
+                accessors.next();
+                (Value::Enumeration(item.to_lowercase()), None)
+            }
+            (Primary::Reference(name), _) => self.reference(name)?,
+            (primary, _) => (self.primary(primary)?, None),
+        };
+        let mut group = None;
+        for accessor in accessors {
+            match accessor {
+                Accessor::Group { entity } => {
+                    group = Some(entity.as_str());
+                    continue;
+                }
+                Accessor::Attribute { name } => {
+                    declared = match value.plain() {
+                        Value::Entity(id) => self.model.attribute_type(*id, group, name).map(|data_type| Declared {
+                            data_type,
+                            owner: Some(*id),
+                        }),
+                        _ => None,
+                    };
+                    value = self.attribute(&value, group, name)?;
+                }
+                Accessor::Indexer { start, end } => {
+                    let start = self.simple_expression(start)?;
+                    let end = match end {
+                        Some(end) => Some(self.simple_expression(end)?),
+                        None => None,
+                    };
+                    let low = self.index_bounds(&value, declared)?.map_or(1, |(low, _)| low);
+                    declared = match end {
+                        None => declared.and_then(|declared| self.element_type(declared)),
+                        Some(_) => None,
+                    };
+                    value = index(value, start, end, low)?;
+                }
+                Accessor::FunctionCall { .. } => {
+                    return Err(EvalError::TypeMismatch(format!("call of {:?}", value)));
+                }
+            }
+            group = None;
+        }
+        Ok((value, declared))
+    }
+
+    /// `LOINDEX` or `HIINDEX` of an argument, which for an ARRAY are the bounds it is declared with.
+    fn index_function(&mut self, name: &str, parameter: &Expression) -> Result<Value, EvalError> {
+        let (value, declared) = match simple_access(parameter) {
+            Some(access) => self.declared_access(access)?,
+            None => (self.evaluate(parameter)?, None),
+        };
+        match self.index_bounds(&value, declared)? {
+            Some((low, _)) if name == "loindex" => Ok(Value::Integer(low)),
+            Some((_, high)) => Ok(Value::Integer(high)),
+            None => self.call(name, vec![value]),
+        }
+    }
+
+    /// The bounds of an ARRAY, from its declared type or else the defined type it is wrapped in.
+    ///
+    /// `None` for other aggregates, which are indexed from 1.
+    pub(super) fn index_bounds(
+        &mut self,
+        value: &Value,
+        declared: Option<Declared<'a>>,
+    ) -> Result<Option<(i64, i64)>, EvalError> {
+        let length = match value.as_aggregate() {
+            Some(values) => values.len() as i64,
+            None => return Ok(None),
+        };
+        let typed = match value {
+            Value::Typed(name, _) => self.model.type_def(name).map(|type_def| Declared {
+                data_type: &type_def.underlying_type,
+                owner: None,
+            }),
+            _ => None,
+        };
+        let bounds = match declared {
+            Some(declared) => self.declared_bounds(declared)?,
+            None => None,
+        };
+        let bounds = match (bounds, typed) {
+            (None, Some(typed)) => self.declared_bounds(typed)?,
+            (bounds, _) => bounds,
+        };
+        // an ARRAY holds all its elements, so an open bound follows from the size
+        Ok(bounds.and_then(|(low, high)| match high {
+            Some(high) => Some((low, high)),
+            None => low.checked_add(length - 1).map(|high| (low, high)),
+        }))
+    }
+
+    /// The lower and, unless it is open, the upper bound of a declared ARRAY type.
+    pub(super) fn declared_bounds(&mut self, declared: Declared<'a>) -> Result<Option<(i64, Option<i64>)>, EvalError> {
+        let bound = match self.model.underlying_type(declared.data_type) {
+            DataType::Array { bound: Some(bound), .. } => bound,
+            _ => return Ok(None),
+        };
+        let (start, end) = match declared.owner {
+            Some(id) => {
+                let mut evaluator = Evaluator::with_self(self.model, id);
+                evaluator.depth = self.depth;
+                evaluator.bound_values(bound)?
+            }
+            None => self.bound_values(bound)?,
+        };
+        Ok(start
+            .as_integer()
+            .map(|low| (low, end.as_ref().and_then(Value::as_integer))))
+    }
+
+    fn bound_values(&mut self, bound: &BoundSpec) -> Result<(Value, Option<Value>), EvalError> {
+        let start = self.simple_expression(&bound.start)?;
+        let end = match &bound.end {
+            Some(end) => Some(self.simple_expression(end)?),
+            None => None,
+        };
+        Ok((start, end))
+    }
+
+    /// The declared type of the elements of an aggregate type.
+    pub(super) fn element_type(&self, declared: Declared<'a>) -> Option<Declared<'a>> {
+        match self.model.underlying_type(declared.data_type) {
+            DataType::Array { base_type, .. }
+            | DataType::List { base_type, .. }
+            | DataType::Bag { base_type, .. }
+            | DataType::Set { base_type, .. } => Some(Declared {
+                data_type: base_type,
+                owner: declared.owner,
+            }),
+            _ => None,
+        }
+    }
+
+    fn primary(&mut self, primary: &Primary) -> Result<Value, EvalError> {
+        match primary {
+            Primary::Literal(literal) => Ok(literal_value(literal)),
+            Primary::Constant(name) => match name.to_lowercase().as_str() {
+                "?" => Ok(Value::Indeterminate),
+                "pi" => Ok(Value::Real(std::f64::consts::PI)),
+                "const_e" => Ok(Value::Real(std::f64::consts::E)),
+                name => self
+                    .variable(name)
+                    .cloned()
+                    .ok_or_else(|| EvalError::UnknownName(name.to_uppercase())),
+            },
+            Primary::Reference(name) => self.reference(name).map(|(value, _)| value),
+            Primary::Grouped(expression) => self.evaluate(expression),
+        }
+    }
+
+    /// The value of a variable, an attribute of SELF, a schema constant or an enumeration item.
+    fn reference(&mut self, name: &str) -> Result<(Value, Option<Declared<'a>>), EvalError> {
+        let variable = self
+            .variables
+            .iter()
+            .rev()
+            .find(|variable| variable.name.eq_ignore_ascii_case(name));
+        if let Some(variable) = variable {
+            let declared = variable.data_type.map(|data_type| Declared { data_type, owner: None });
+            return Ok((variable.value.clone(), declared));
+        }
+        if let Some(value) = self.variable("self").filter(|value| !value.is_indeterminate()).cloned() {
+            match self.attribute(&value, None, name) {
+                Err(EvalError::UnknownName(_)) | Err(EvalError::TypeMismatch(_)) => {}
+                Ok(attribute) => {
+                    let declared = value.as_entity().and_then(|id| {
+                        let data_type = self.model.attribute_type(id, None, name)?;
+                        Some(Declared {
+                            data_type,
+                            owner: Some(id),
+                        })
+                    });
+                    return Ok((attribute, declared));
+                }
+                Err(error) => return Err(error),
+            }
+        }
+        let constant = self
+            .model
+            .schema()
+            .constants
+            .iter()
+            .find(|constant| constant.name.eq_ignore_ascii_case(name));
+        if let Some(constant) = constant {
+            let value = Evaluator::new(self.model).evaluate(&constant.expr)?;
+            let declared = Declared {
+                data_type: &constant.data_type,
+                owner: None,
+            };
+            return Ok((self.model.typed_value(value, &constant.data_type), Some(declared)));
+        }
+        if self.model.enumeration_of(name).is_some() {
+            return Ok((Value::Enumeration(name.to_lowercase()), None));
+        }
+        Err(EvalError::UnknownName(name.to_string()))
+    }
+
+    /// The value of an explicit, derived or inverse attribute of an instance or of a constructed entity value.
+    pub fn attribute(&mut self, value: &Value, group: Option<&str>, name: &str) -> Result<Value, EvalError> {
+        let parts = match value.plain() {
+            Value::Indeterminate => return Ok(Value::Indeterminate),
+            Value::Entity(id) => return self.model.attribute(*id, group, name),
+            Value::Constructed(parts) => parts,
+            _ => return Err(EvalError::TypeMismatch(format!("attribute {} of {:?}", name, value))),
+        };
+        // with a group only the parts of that entity and its supertypes are visible
+        let visible = |entity: &str| match group {
+            Some(group) => self
+                .model
+                .supertypes(group)
+                .iter()
+                .any(|supertype| supertype.name.eq_ignore_ascii_case(entity)),
+            None => true,
+        };
+        for (entity, values) in parts.iter().filter(|(entity, _)| visible(entity)) {
+            let position = self.model.entity(entity).and_then(|entity| {
+                entity
+                    .attributes
+                    .iter()
+                    .filter(|attribute| attribute.supertype.is_none())
+                    .position(|attribute| attribute.name.eq_ignore_ascii_case(name))
+            });
+            if let Some(position) = position {
+                return Ok(values.get(position).cloned().unwrap_or(Value::Indeterminate));
+            }
+        }
+        for (entity, _) in parts {
+            for supertype in self.model.supertypes(entity).iter().rev() {
+                if let Some(derive) = supertype
+                    .derives
+                    .iter()
+                    .find(|derive| derive.name.eq_ignore_ascii_case(name))
+                {
+                    let mut evaluator = Evaluator::new(self.model);
+                    evaluator.depth = self.depth;
+                    evaluator.bind("self", value.clone());
+                    let derived = evaluator.evaluate(&derive.expr)?;
+                    return Ok(self.model.typed_value(derived, &derive.data_type));
+                }
+                // a constructed value is not referred to by any instance
+                if supertype
+                    .inverses
+                    .iter()
+                    .any(|inverse| inverse.name.eq_ignore_ascii_case(name))
+                {
+                    return Ok(Value::Indeterminate);
+                }
+            }
+        }
+        Err(EvalError::UnknownName(name.to_string()))
+    }
+
+    /// The partial entity value built by an entity constructor from the explicit attributes the entity declares.
+    fn construct(&self, entity: &Entity, arguments: Vec<Value>) -> Result<Value, EvalError> {
+        let attributes = entity
+            .attributes
+            .iter()
+            .filter(|attribute| attribute.supertype.is_none())
+            .collect::<Vec<_>>();
+        if attributes.len() != arguments.len() {
+            return Err(EvalError::TypeMismatch(format!(
+                "{} takes {} attributes",
+                entity.name.to_uppercase(),
+                attributes.len()
+            )));
+        }
+        let values = attributes
+            .iter()
+            .zip(arguments)
+            .map(|(attribute, value)| self.model.typed_value(value, &attribute.data_type))
+            .collect();
+        Ok(Value::Constructed(vec![(entity.name.to_lowercase(), values)]))
+    }
+
+    /// Call a schema function, an entity constructor or a built-in function.
+    pub fn call(&mut self, name: &str, arguments: Vec<Value>) -> Result<Value, EvalError> {
+        let name = name.to_lowercase();
+        if let Some(function) = self.model.function(&name) {
+            return self.call_function(function, arguments);
+        }
+        if let Some(entity) = self.model.entity(&name) {
+            return self.construct(entity, arguments);
+        }
+        let argument = |index: usize| {
+            arguments
+                .get(index)
+                .ok_or_else(|| EvalError::TypeMismatch(format!("{} takes more arguments", name.to_uppercase())))
+        };
+        let mismatch = |value: &Value| EvalError::TypeMismatch(format!("{} of {:?}", name.to_uppercase(), value));
+        let real_function: Option<fn(f64) -> f64> = match name.as_str() {
+            "sin" => Some(f64::sin),
+            "cos" => Some(f64::cos),
+            "tan" => Some(f64::tan),
+            "asin" => Some(f64::asin),
+            "acos" => Some(f64::acos),
+            "exp" => Some(f64::exp),
+            "log" => Some(f64::ln),
+            "log2" => Some(f64::log2),
+            "log10" => Some(f64::log10),
+            "sqrt" => Some(f64::sqrt),
+            _ => None,
+        };
+        if let Some(function) = real_function {
+            let value = argument(0)?;
+            return match value.plain() {
+                Value::Indeterminate => Ok(Value::Indeterminate),
+                _ => value
+                    .as_real()
+                    .map(|value| Value::Real(function(value)))
+                    .ok_or_else(|| mismatch(value)),
+            };
+        }
+        let value = argument(0)?;
+        if value.is_indeterminate() && !matches!(name.as_str(), "exists" | "nvl") {
+            return Ok(Value::Indeterminate);
+        }
+        match name.as_str() {
+            "abs" => match value.plain() {
+                Value::Integer(value) => Ok(value.checked_abs().map_or(Value::Indeterminate, Value::Integer)),
+                Value::Real(value) => Ok(Value::Real(value.abs())),
+                _ => Err(mismatch(value)),
+            },
+            "atan" => {
+                let y = value.as_real().ok_or_else(|| mismatch(value))?;
+                let x = argument(1)?;
+                match x.plain() {
+                    Value::Indeterminate => Ok(Value::Indeterminate),
+                    _ => Ok(Value::Real(y.atan2(x.as_real().ok_or_else(|| mismatch(x))?))),
+                }
+            }
+            "length" => match value.plain() {
+                Value::String(value) => Ok(Value::Integer(value.chars().count() as i64)),
+                _ => Err(mismatch(value)),
+            },
+            "blength" => match value.plain() {
+                Value::Binary(value) => Ok(Value::Integer(value.len() as i64)),
+                _ => Err(mismatch(value)),
+            },
+            "exists" => Ok(Value::from(!value.is_indeterminate())),
+            "nvl" => match value.is_indeterminate() {
+                true => Ok(argument(1)?.clone()),
+                false => Ok(value.clone()),
+            },
+            "sizeof" => value
+                .as_aggregate()
+                .map(|values| Value::Integer(values.len() as i64))
+                .ok_or_else(|| mismatch(value)),
+            "loindex" | "hiindex" => {
+                let length = value.as_aggregate().ok_or_else(|| mismatch(value))?.len() as i64;
+                let (low, high) = self.index_bounds(value, None)?.unwrap_or((1, length));
+                Ok(Value::Integer(if name == "loindex" { low } else { high }))
+            }
+            "typeof" => Ok(Value::Aggregate(
+                self.model.type_names(value).into_iter().map(Value::String).collect(),
+            )),
+            // a constructed entity value is not referred to by any instance
+            "usedin" | "rolesof" if matches!(value.plain(), Value::Constructed(_)) => Ok(Value::Aggregate(Vec::new())),
+            "usedin" => {
+                let id = value.as_entity().ok_or_else(|| mismatch(value))?;
+                let role = argument(1)?;
+                let role = role.as_str().ok_or_else(|| mismatch(role))?.to_lowercase();
+                let referrers = self
+                    .model
+                    .used_in(id)
+                    .iter()
+                    .filter(|used| role.is_empty() || role == self.role_name(used.entity, used.attribute))
+                    .map(|used| Value::Entity(used.id))
+                    .collect();
+                Ok(Value::Aggregate(referrers))
+            }
+            "rolesof" => {
+                let id = value.as_entity().ok_or_else(|| mismatch(value))?;
+                let mut roles = Vec::new();
+                for used in self.model.used_in(id) {
+                    let role = Value::String(self.role_name(used.entity, used.attribute).to_uppercase());
+                    if !roles.contains(&role) {
+                        roles.push(role);
+                    }
+                }
+                Ok(Value::Aggregate(roles))
+            }
+            "value" => {
+                let text = value.as_str().ok_or_else(|| mismatch(value))?.trim();
+                Ok(text
+                    .parse::<i64>()
+                    .map(Value::Integer)
+                    .or_else(|_| text.parse::<f64>().map(Value::Real))
+                    .unwrap_or(Value::Indeterminate))
+            }
+            "value_in" => {
+                let values = value.as_aggregate().ok_or_else(|| mismatch(value))?;
+                relation(&Operator::In, argument(1)?, &Value::Aggregate(values.to_vec()))
+            }
+            "value_unique" => {
+                let values = value.as_aggregate().ok_or_else(|| mismatch(value))?;
+                if values.iter().any(Value::is_indeterminate) {
+                    return Ok(Value::Logical(None));
+                }
+                let unique = values.iter().enumerate().all(|(index, value)| {
+                    values[index + 1..]
+                        .iter()
+                        .all(|other| value.compare(other) != Some(Ordering::Equal))
+                });
+                Ok(Value::from(unique))
+            }
+            "odd" => value
+                .as_integer()
+                .map(|value| Value::from(value % 2 != 0))
+                .ok_or_else(|| mismatch(value)),
+            _ => Err(EvalError::UnknownName(name.to_uppercase())),
+        }
+    }
+
+    /// The role `schema.entity.attribute` in lower case, as used by `USEDIN` and `ROLESOF`.
+    fn role_name(&self, entity: &Entity, attribute: &super::Attribute) -> String {
+        format!("{}.{}.{}", self.model.schema().name, entity.name, attribute.name).to_lowercase()
+    }
+}
+
+fn literal_value(literal: &Literal) -> Value {
+    match literal {
+        Literal::Binary(bits) => {
+            let mut binary = Binary::new();
+            for bit in bits.chars() {
+                binary.push(bit == '1');
+            }
+            Value::Binary(binary)
+        }
+        Literal::Integer(value) => Value::Integer(*value),
+        Literal::Real(value) => Value::Real(*value),
+        Literal::Logical(value) => Value::Logical(*value),
+        Literal::String(value) => Value::String(value.clone()),
+    }
+}
+
+/// The qualified access an expression consists of alone.
+fn simple_access(expression: &Expression) -> Option<&QualifiedAccess> {
+    let simple = &expression.operand;
+    let term = &simple.operand;
+    let factor = &term.operand;
+    let alone = expression.operations.is_empty()
+        && simple.operations.is_empty()
+        && term.operations.is_empty()
+        && factor.operations.is_empty();
+    match &factor.operand {
+        SimpleFactor::QualifiedAccess(access) if alone => Some(access),
+        _ => None,
+    }
+}
+
+fn and(a: Option<bool>, b: Option<bool>) -> Option<bool> {
+    match (a, b) {
+        (Some(false), _) | (_, Some(false)) => Some(false),
+        (Some(true), Some(true)) => Some(true),
+        _ => None,
+    }
+}
+
+fn or(a: Option<bool>, b: Option<bool>) -> Option<bool> {
+    match (a, b) {
+        (Some(true), _) | (_, Some(true)) => Some(true),
+        (Some(false), Some(false)) => Some(false),
+        _ => None,
+    }
+}
+
+/// The relational operators, UNKNOWN when an operand is indeterminate.
+pub(super) fn relation(op: &Operator, a: &Value, b: &Value) -> Result<Value, EvalError> {
+    if a.is_indeterminate() || b.is_indeterminate() {
+        return Ok(Value::Logical(None));
+    }
+    let ordering = a.compare(b);
+    let result = match op {
+        Operator::Equal | Operator::InstanceEqual => Some(ordering == Some(Ordering::Equal)),
+        Operator::NotEqual | Operator::InstanceNotEqual => Some(ordering != Some(Ordering::Equal)),
+        Operator::Greater => ordering.map(|ordering| ordering == Ordering::Greater),
+        Operator::Less => ordering.map(|ordering| ordering == Ordering::Less),
+        Operator::GreaterOrEqual => ordering.map(|ordering| ordering != Ordering::Less),
+        Operator::LessOrEqual => ordering.map(|ordering| ordering != Ordering::Greater),
+        Operator::In => {
+            let values = b
+                .as_aggregate()
+                .ok_or_else(|| EvalError::TypeMismatch(format!("IN {:?}", b)))?;
+            Some(values.iter().any(|value| a.compare(value) == Some(Ordering::Equal)))
+        }
+        Operator::Like => match (a.as_str(), b.as_str()) {
+            (Some(text), Some(pattern)) => {
+                let text = text.chars().collect::<Vec<_>>();
+                let pattern = pattern.chars().collect::<Vec<_>>();
+                Some(like(&text, &pattern))
+            }
+            _ => return Err(EvalError::TypeMismatch(format!("{:?} LIKE {:?}", a, b))),
+        },
+        _ => return Err(EvalError::Unsupported(format!("{:?} operator", op))),
+    };
+    // UNKNOWN compares with anything but itself to UNKNOWN
+    let unknown = matches!(a.plain(), Value::Logical(None)) || matches!(b.plain(), Value::Logical(None));
+    if unknown && ordering != Some(Ordering::Equal) && !matches!(op, Operator::In) {
+        return Ok(Value::Logical(None));
+    }
+    Ok(Value::Logical(result))
+}
+
+/// The arithmetic, string, aggregate and logical operators.
+fn arithmetic(op: &Operator, a: Value, b: Value) -> Result<Value, EvalError> {
+    match op {
+        Operator::And | Operator::Or | Operator::Xor => {
+            let (x, y) = match (a.as_logical(), b.as_logical()) {
+                (Some(x), Some(y)) => (x, y),
+                _ => return Err(EvalError::TypeMismatch(format!("{:?} {:?} {:?}", a, op, b))),
+            };
+            return Ok(Value::Logical(match op {
+                Operator::And => and(x, y),
+                Operator::Or => or(x, y),
+                _ => x.and_then(|x| y.map(|y| x != y)),
+            }));
+        }
+        Operator::Combine => {
+            return match (a.plain(), b.plain()) {
+                (Value::Constructed(x), Value::Constructed(y)) => {
+                    Ok(Value::Constructed(x.iter().chain(y).cloned().collect()))
+                }
+                _ => Err(EvalError::TypeMismatch(format!("{:?} || {:?}", a, b))),
+            };
+        }
+        _ => {}
+    }
+    if a.is_indeterminate() || b.is_indeterminate() {
+        return Ok(Value::Indeterminate);
+    }
+    let mismatch = || EvalError::TypeMismatch(format!("{:?} {:?} {:?}", a, op, b));
+    match (op, a.plain(), b.plain()) {
+        (Operator::Add, Value::Integer(x), Value::Integer(y)) => Ok(integer(x.checked_add(*y))),
+        (Operator::Sub, Value::Integer(x), Value::Integer(y)) => Ok(integer(x.checked_sub(*y))),
+        (Operator::Mul, Value::Integer(x), Value::Integer(y)) => Ok(integer(x.checked_mul(*y))),
+        (Operator::IntegerDiv, Value::Integer(x), Value::Integer(y)) => Ok(integer(floor_div(*x, *y))),
+        (Operator::Mod, Value::Integer(x), Value::Integer(y)) => Ok(integer(floor_mod(*x, *y))),
+        (Operator::Power, Value::Integer(x), Value::Integer(y)) if *y >= 0 => {
+            Ok(integer(u32::try_from(*y).ok().and_then(|y| x.checked_pow(y))))
+        }
+        (Operator::Add, Value::String(x), Value::String(y)) => Ok(Value::String(format!("{}{}", x, y))),
+        (Operator::Add, Value::Aggregate(x), Value::Aggregate(y)) => {
+            Ok(Value::Aggregate(x.iter().chain(y).cloned().collect()))
+        }
+        (Operator::Add, Value::Aggregate(x), _) => {
+            let mut values = x.clone();
+            values.push(b);
+            Ok(Value::Aggregate(values))
+        }
+        (Operator::Add, _, Value::Aggregate(y)) => {
+            let mut values = vec![a.clone()];
+            values.extend(y.iter().cloned());
+            Ok(Value::Aggregate(values))
+        }
+        (Operator::Sub, Value::Aggregate(x), Value::Aggregate(y)) => {
+            let mut values = x.clone();
+            for value in y {
+                remove_first(&mut values, value);
+            }
+            Ok(Value::Aggregate(values))
+        }
+        (Operator::Sub, Value::Aggregate(x), _) => {
+            let mut values = x.clone();
+            remove_first(&mut values, &b);
+            Ok(Value::Aggregate(values))
+        }
+        (Operator::Mul, Value::Aggregate(x), Value::Aggregate(y)) => Ok(Value::Aggregate(
+            x.iter()
+                .filter(|value| y.iter().any(|other| value.compare(other) == Some(Ordering::Equal)))
+                .cloned()
+                .collect(),
+        )),
+        (_, x, y) => {
+            let (x, y) = match (x.as_real(), y.as_real()) {
+                (Some(x), Some(y)) => (x, y),
+                _ => return Err(mismatch()),
+            };
+            match op {
+                Operator::Add => Ok(Value::Real(x + y)),
+                Operator::Sub => Ok(Value::Real(x - y)),
+                Operator::Mul => Ok(Value::Real(x * y)),
+                Operator::Div if y == 0.0 => Ok(Value::Indeterminate),
+                Operator::Div => Ok(Value::Real(x / y)),
+                Operator::Power => Ok(Value::Real(x.powf(y))),
+                _ => Err(mismatch()),
+            }
+        }
+    }
+}
+
+/// An integer result, indeterminate when it is out of range.
+fn integer(value: Option<i64>) -> Value {
+    value.map_or(Value::Indeterminate, Value::Integer)
+}
+
+/// The integer quotient rounded towards negative infinity, `None` for a zero divisor or an overflow.
+fn floor_div(x: i64, y: i64) -> Option<i64> {
+    let quotient = x.checked_div(y)?;
+    if x % y != 0 && (x < 0) != (y < 0) {
+        return quotient.checked_sub(1);
+    }
+    Some(quotient)
+}
+
+/// The remainder of `floor_div`, with the sign of the divisor.
+fn floor_mod(x: i64, y: i64) -> Option<i64> {
+    if y == 0 {
+        return None;
+    }
+    // only `i64::MIN MOD -1` overflows, and it leaves no remainder
+    let remainder = x.checked_rem(y).unwrap_or(0);
+    if remainder != 0 && (remainder < 0) != (y < 0) {
+        return Some(remainder + y);
+    }
+    Some(remainder)
+}
+
+fn remove_first(values: &mut Vec<Value>, value: &Value) {
+    if let Some(index) = values
+        .iter()
+        .position(|other| other.compare(value) == Some(Ordering::Equal))
+    {
+        values.remove(index);
+    }
+}
+
+/// An element or a range of elements of an aggregate indexed from `low` or of a string indexed from 1.
+fn index(value: Value, start: Value, end: Option<Value>, low: i64) -> Result<Value, EvalError> {
+    if value.is_indeterminate() || start.is_indeterminate() || end.as_ref().is_some_and(Value::is_indeterminate) {
+        return Ok(Value::Indeterminate);
+    }
+    let position = |index: &Value| {
+        index
+            .as_integer()
+            .ok_or_else(|| EvalError::TypeMismatch(format!("index {:?}", index)))
+    };
+    let start = position(&start)?;
+    match (value.plain(), end) {
+        (Value::Aggregate(values), None) => Ok(start
+            .checked_sub(low)
+            .and_then(|index| usize::try_from(index).ok())
+            .and_then(|index| values.get(index))
+            .cloned()
+            .unwrap_or(Value::Indeterminate)),
+        (Value::String(text), end) => {
+            let end = match end {
+                Some(end) => position(&end)?,
+                None => start,
+            };
+            let length = text.chars().count() as i64;
+            if start < 1 || end < start || end > length {
+                return Ok(Value::Indeterminate);
+            }
+            let text = text.chars().skip(start as usize - 1).take((end - start + 1) as usize);
+            Ok(Value::String(text.collect()))
+        }
+        _ => Err(EvalError::TypeMismatch(format!("index of {:?}", value))),
+    }
+}
+
+/// Match a string against a `LIKE` pattern.
+fn like(text: &[char], pattern: &[char]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some(('&', _)) => true,
+        Some(('*', rest)) => (0..=text.len()).any(|skip| like(&text[skip..], rest)),
+        Some(('$', rest)) => {
+            // a substring up to a space or the end of the string
+            let word = text.iter().position(|c| *c == ' ').unwrap_or(text.len());
+            (0..=word).any(|skip| like(&text[skip..], rest))
+        }
+        Some(('\\', rest)) => match (rest.split_first(), text.split_first()) {
+            (Some((escaped, rest)), Some((c, text))) => escaped == c && like(text, rest),
+            (None, Some(('\\', text))) => text.is_empty(),
+            _ => false,
+        },
+        Some((wildcard, rest)) => match text.split_first() {
+            Some((c, text)) => {
+                let matched = match wildcard {
+                    '@' => c.is_alphabetic(),
+                    '^' => c.is_uppercase(),
+                    '!' => c.is_lowercase(),
+                    '#' => c.is_ascii_digit(),
+                    '?' => true,
+                    _ => wildcard == c,
+                };
+                matched && like(text, rest)
+            }
+            None => false,
+        },
+    }
+}
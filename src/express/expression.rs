@@ -37,7 +37,10 @@ pub enum Operator {
     Add,
     Sub,
     Mul,
+    /// `/`, real division
     Div,
+    /// `DIV`, integer division
+    IntegerDiv,
     Mod,
     Or,
     Xor,
@@ -45,12 +48,15 @@ pub enum Operator {
     Not,
     Neg,
     Power,
+    /// `||`, combining partial entity values into a complex entity value
+    Combine,
 }
 
 #[derive(Debug, Clone)]
 pub enum SimpleFactor {
     AggregateInitializer {
-        elements: Vec<Box<Expression>>,
+        /// The elements and how many times they are repeated, `[element : repetition]`.
+        elements: Vec<(Box<Expression>, Option<Box<Expression>>)>,
     },
     EnityConstructor {
         entity: String,
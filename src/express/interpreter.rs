@@ -0,0 +1,485 @@
+use super::evaluator::{relation, Declared, Variable};
+use super::{
+    Accessor, Constant, DataType, EvalError, Evaluator, Expression, Function, Local, Operator, Parameter, Primary,
+    Procedure, QualifiedAccess, Rule, SimpleFactor, Statement, Value,
+};
+use std::convert::TryFrom;
+
+/// How deep schema functions and procedures may call each other, to stop runaway recursion.
+const MAX_DEPTH: usize = 64;
+
+/// How execution continues after a statement.
+enum Flow {
+    Next,
+    /// `SKIP`, continue with the next iteration of the enclosing REPEAT.
+    Skip,
+    /// `ESCAPE`, leave the enclosing REPEAT.
+    Escape,
+    Return(Value),
+}
+
+/// A step from a variable to the part of its value an assignment replaces.
+enum Target {
+    /// The offset of an element from the lower bound.
+    Index(usize),
+    Attribute(Option<String>, String),
+}
+
+impl<'m, 'a> Evaluator<'m, 'a> {
+    /// Execute a schema function, the result is `?` when it ends without RETURN.
+    pub fn call_function(&mut self, function: &'a Function, arguments: Vec<Value>) -> Result<Value, EvalError> {
+        let outer = self.enter(&function.name, &function.parameters, arguments)?;
+        let result = self.execute_body(&function.constants, &function.locals, &function.statements);
+        self.leave(outer);
+        match result? {
+            Flow::Return(value) => Ok(self.model.typed_value(value, &function.return_type)),
+            _ => Ok(Value::Indeterminate),
+        }
+    }
+
+    /// Execute a schema procedure, returning the final values of its parameters for the `VAR` ones.
+    pub fn call_procedure(&mut self, procedure: &'a Procedure, arguments: Vec<Value>) -> Result<Vec<Value>, EvalError> {
+        let outer = self.enter(&procedure.name, &procedure.parameters, arguments)?;
+        let result = self.execute_body(&procedure.constants, &procedure.locals, &procedure.statements);
+        let parameters = self
+            .variables
+            .drain(..procedure.parameters.len())
+            .map(|variable| variable.value)
+            .collect();
+        self.leave(outer);
+        result.map(|_| parameters)
+    }
+
+    /// Execute the body of a global rule, with each entity it is for bound to the set of its instances.
+    ///
+    /// The populations, constants and local variables stay bound for evaluating the WHERE rules of the rule.
+    pub fn execute_rule(&mut self, rule: &'a Rule) -> Result<(), EvalError> {
+        let model = self.model;
+        for entity in &rule.entities {
+            let population = model.instances_of(entity).map(Value::Entity).collect();
+            self.bind(entity, Value::Aggregate(population));
+        }
+        self.execute_body(&rule.constants, &rule.locals, &rule.statements)?;
+        Ok(())
+    }
+
+    /// Start executing a function or procedure, with only its parameters in scope.
+    fn enter(
+        &mut self,
+        name: &str,
+        parameters: &'a [Parameter],
+        arguments: Vec<Value>,
+    ) -> Result<Vec<Variable<'a>>, EvalError> {
+        if parameters.len() != arguments.len() {
+            return Err(EvalError::TypeMismatch(format!(
+                "{} takes {} arguments",
+                name.to_uppercase(),
+                parameters.len()
+            )));
+        }
+        if self.depth == MAX_DEPTH {
+            return Err(EvalError::Unsupported(format!(
+                "calls nested deeper than {} in {}",
+                MAX_DEPTH,
+                name.to_uppercase()
+            )));
+        }
+        self.depth += 1;
+        let outer = std::mem::take(&mut self.variables);
+        for (parameter, value) in parameters.iter().zip(arguments) {
+            self.declare(&parameter.name, value, &parameter.data_type);
+        }
+        Ok(outer)
+    }
+
+    fn leave(&mut self, outer: Vec<Variable<'a>>) {
+        self.variables = outer;
+        self.depth -= 1;
+    }
+
+    fn declare(&mut self, name: &str, value: Value, data_type: &'a DataType) {
+        let value = self.model.typed_value(value, data_type);
+        self.variables.push(Variable {
+            name: name.to_lowercase(),
+            value,
+            data_type: Some(data_type),
+        });
+    }
+
+    fn execute_body(
+        &mut self,
+        constants: &'a [Constant],
+        locals: &'a [Local],
+        statements: &'a [Statement],
+    ) -> Result<Flow, EvalError> {
+        for constant in constants {
+            let value = self.evaluate(&constant.expr)?;
+            self.declare(&constant.name, value, &constant.data_type);
+        }
+        for local in locals {
+            let value = match &local.expr {
+                Some(expr) => self.evaluate(expr)?,
+                None => Value::Indeterminate,
+            };
+            self.declare(&local.name, value, &local.data_type);
+        }
+        self.execute(statements)
+    }
+
+    fn execute(&mut self, statements: &'a [Statement]) -> Result<Flow, EvalError> {
+        for statement in statements {
+            match self.statement(statement)? {
+                Flow::Next => {}
+                flow => return Ok(flow),
+            }
+        }
+        Ok(Flow::Next)
+    }
+
+    fn statement(&mut self, statement: &'a Statement) -> Result<Flow, EvalError> {
+        match statement {
+            Statement::Alias {
+                variable,
+                target,
+                accessors,
+                statements,
+            } => {
+                let access = QualifiedAccess {
+                    base: Primary::Reference(target.clone()),
+                    accessors: accessors.clone(),
+                };
+                let value = self.qualified_access(&access)?;
+                self.bind(variable, value.clone());
+                let flow = self.execute(statements);
+                let aliased = self.variables.pop().map(|variable| variable.value);
+                // assignments to the alias change the variable it stands for
+                if let Some(aliased) = aliased {
+                    if aliased != value && self.variable(target).is_some() {
+                        self.assign(target, accessors, aliased)?;
+                    }
+                }
+                flow
+            }
+            Statement::Assignment {
+                target,
+                accessors,
+                value,
+            } => {
+                let value = self.evaluate(value)?;
+                self.assign(target, accessors, value)?;
+                Ok(Flow::Next)
+            }
+            Statement::Case {
+                selector,
+                actions,
+                otherwise,
+            } => {
+                let selector = self.evaluate(selector)?;
+                for action in actions {
+                    for label in &action.labels {
+                        let label = self.evaluate(label)?;
+                        if relation(&Operator::Equal, &selector, &label)?.as_logical() == Some(Some(true)) {
+                            return self.statement(&action.statement);
+                        }
+                    }
+                }
+                match otherwise {
+                    Some(statement) => self.statement(statement),
+                    None => Ok(Flow::Next),
+                }
+            }
+            Statement::Compound(statements) => self.execute(statements),
+            Statement::Escape => Ok(Flow::Escape),
+            Statement::If {
+                condition,
+                statements,
+                else_statements,
+            } => match self.evaluate_logical(condition)? {
+                Some(true) => self.execute(statements),
+                // UNKNOWN takes the ELSE branch
+                _ => self.execute(else_statements),
+            },
+            Statement::Null => Ok(Flow::Next),
+            Statement::ProcedureCall { name, parameters } => {
+                self.procedure_call(name, parameters)?;
+                Ok(Flow::Next)
+            }
+            Statement::Repeat {
+                increment,
+                while_condition,
+                until_condition,
+                statements,
+            } => {
+                let mut counter = None;
+                if let Some(increment) = increment {
+                    let start = self.evaluate(&increment.start)?;
+                    let end = self.evaluate(&increment.end)?;
+                    let step = match &increment.step {
+                        Some(step) => self.evaluate(step)?,
+                        None => Value::Integer(1),
+                    };
+                    // the loop is not executed when a bound is indeterminate
+                    if start.is_indeterminate() || end.is_indeterminate() || step.is_indeterminate() {
+                        return Ok(Flow::Next);
+                    }
+                    let bound = |value: &Value| {
+                        value
+                            .as_integer()
+                            .ok_or_else(|| EvalError::TypeMismatch(format!("REPEAT bound {:?}", value)))
+                    };
+                    let (start, end, step) = (bound(&start)?, bound(&end)?, bound(&step)?);
+                    if step == 0 {
+                        return Err(EvalError::TypeMismatch("REPEAT increment of 0".to_string()));
+                    }
+                    self.bind(&increment.variable, Value::Integer(start));
+                    counter = Some((start, end, step));
+                }
+                let flow = self.repeat(
+                    counter,
+                    while_condition.as_deref(),
+                    until_condition.as_deref(),
+                    statements,
+                );
+                if counter.is_some() {
+                    self.unbind();
+                }
+                flow
+            }
+            Statement::Return(value) => match value {
+                Some(value) => Ok(Flow::Return(self.evaluate(value)?)),
+                None => Ok(Flow::Return(Value::Indeterminate)),
+            },
+            Statement::Skip => Ok(Flow::Skip),
+        }
+    }
+
+    /// Run the body of a REPEAT, the loop variable is the variable bound last.
+    fn repeat(
+        &mut self,
+        counter: Option<(i64, i64, i64)>,
+        while_condition: Option<&'a Expression>,
+        until_condition: Option<&'a Expression>,
+        statements: &'a [Statement],
+    ) -> Result<Flow, EvalError> {
+        let mut current = counter.map(|(start, _, _)| start);
+        loop {
+            if let (Some(value), Some((_, end, step))) = (current, counter) {
+                if (step > 0 && value > end) || (step < 0 && value < end) {
+                    break;
+                }
+                if let Some(variable) = self.variables.last_mut() {
+                    variable.value = Value::Integer(value);
+                }
+            }
+            if let Some(condition) = while_condition {
+                if self.evaluate_logical(condition)? != Some(true) {
+                    break;
+                }
+            }
+            match self.execute(statements)? {
+                Flow::Next | Flow::Skip => {}
+                Flow::Escape => break,
+                flow @ Flow::Return(_) => return Ok(flow),
+            }
+            if let Some(condition) = until_condition {
+                if self.evaluate_logical(condition)? == Some(true) {
+                    break;
+                }
+            }
+            if let (Some(value), Some((_, _, step))) = (current, counter) {
+                // a counter stepping past the integer range has passed its end
+                match value.checked_add(step) {
+                    Some(next) => current = Some(next),
+                    None => break,
+                }
+            }
+        }
+        Ok(Flow::Next)
+    }
+
+    /// Call a schema procedure or one of the built-in procedures `INSERT` and `REMOVE`.
+    fn procedure_call(&mut self, name: &str, parameters: &'a [Expression]) -> Result<(), EvalError> {
+        let mut arguments = Vec::with_capacity(parameters.len());
+        for parameter in parameters {
+            arguments.push(self.evaluate(parameter)?);
+        }
+        let builtin = name.to_lowercase();
+        if builtin == "insert" || builtin == "remove" {
+            let target = parameters.first().and_then(assignable).ok_or_else(|| {
+                EvalError::TypeMismatch(format!("{} needs a variable to change", name.to_uppercase()))
+            })?;
+            let mut arguments = arguments.into_iter();
+            let list = arguments.next().unwrap_or(Value::Indeterminate);
+            let mut values = match list.plain() {
+                Value::Aggregate(values) => values.clone(),
+                _ => {
+                    return Err(EvalError::TypeMismatch(format!(
+                        "{} of {:?}",
+                        name.to_uppercase(),
+                        list
+                    )))
+                }
+            };
+            let rest = arguments.collect::<Vec<_>>();
+            let position = |value: Option<&Value>| {
+                value
+                    .and_then(Value::as_integer)
+                    .and_then(|position| usize::try_from(position).ok())
+                    .ok_or_else(|| EvalError::TypeMismatch(format!("{} position {:?}", name.to_uppercase(), value)))
+            };
+            if builtin == "insert" {
+                // the element is inserted after the position, 0 inserts at the head
+                let position = position(rest.get(1))?;
+                let element = rest.into_iter().next().unwrap_or(Value::Indeterminate);
+                if position > values.len() {
+                    return Err(EvalError::TypeMismatch(format!("INSERT at {} of {:?}", position, list)));
+                }
+                values.insert(position, element);
+            } else {
+                let position = position(rest.first())?;
+                if position < 1 || position > values.len() {
+                    return Err(EvalError::TypeMismatch(format!("REMOVE at {} of {:?}", position, list)));
+                }
+                values.remove(position - 1);
+            }
+            let value = match list {
+                Value::Typed(type_name, _) => Value::Typed(type_name, Box::new(Value::Aggregate(values))),
+                _ => Value::Aggregate(values),
+            };
+            return self.assign(target.0, target.1, value);
+        }
+        let procedure = self
+            .model
+            .procedure(name)
+            .ok_or_else(|| EvalError::UnknownName(name.to_string()))?;
+        let results = self.call_procedure(procedure, arguments)?;
+        // VAR parameters pass their final values back to the variables given for them
+        for ((parameter, argument), value) in procedure.parameters.iter().zip(parameters).zip(results) {
+            if let (true, Some((target, accessors))) = (parameter.var, assignable(argument)) {
+                self.assign(target, accessors, value)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Replace a variable, or an element or attribute of its value.
+    fn assign(&mut self, name: &str, accessors: &[Accessor], value: Value) -> Result<(), EvalError> {
+        let mut path = Vec::with_capacity(accessors.len());
+        let mut group = None;
+        // the declared type of the part assigned to, for the lower bound of an ARRAY
+        let mut declared = self
+            .variables
+            .iter()
+            .rev()
+            .find(|variable| variable.name.eq_ignore_ascii_case(name))
+            .and_then(|variable| variable.data_type)
+            .map(|data_type| Declared { data_type, owner: None });
+        for accessor in accessors {
+            match accessor {
+                Accessor::Indexer { start, end: None } => {
+                    let index = self.simple_expression(start)?;
+                    let index = index
+                        .as_integer()
+                        .ok_or_else(|| EvalError::TypeMismatch(format!("index {:?}", index)))?;
+                    let low = match declared {
+                        Some(declared) => self.declared_bounds(declared)?.map_or(1, |(low, _)| low),
+                        None => 1,
+                    };
+                    let offset = index
+                        .checked_sub(low)
+                        .and_then(|offset| usize::try_from(offset).ok())
+                        .ok_or_else(|| EvalError::TypeMismatch(format!("index {} of {}", index, name)))?;
+                    path.push(Target::Index(offset));
+                    declared = declared.and_then(|declared| self.element_type(declared));
+                }
+                Accessor::Indexer { .. } => return Err(EvalError::Unsupported("assignment to a range".to_string())),
+                Accessor::Attribute { name } => {
+                    path.push(Target::Attribute(group.take(), name.clone()));
+                    declared = None;
+                }
+                Accessor::Group { entity } => group = Some(entity.clone()),
+                Accessor::FunctionCall { .. } => {
+                    return Err(EvalError::TypeMismatch(format!("assignment to a call of {}", name)));
+                }
+            }
+        }
+        let model = self.model;
+        let variable = self
+            .variables
+            .iter_mut()
+            .rev()
+            .find(|variable| variable.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| EvalError::UnknownName(name.to_string()))?;
+        if path.is_empty() {
+            variable.value = match variable.data_type {
+                Some(data_type) => model.typed_value(value, data_type),
+                None => value,
+            };
+            return Ok(());
+        }
+        let mut target = &mut variable.value;
+        for step in path {
+            target = match (step, target.plain_mut()) {
+                (Target::Index(index), Value::Aggregate(values)) => {
+                    // assigning past the end extends the aggregate
+                    if index >= values.len() {
+                        values.resize(index + 1, Value::Indeterminate);
+                    }
+                    &mut values[index]
+                }
+                (Target::Attribute(group, attribute), Value::Constructed(parts)) => {
+                    let mut found = None;
+                    for (entity, values) in parts.iter_mut() {
+                        if group.as_ref().is_some_and(|group| !entity.eq_ignore_ascii_case(group)) {
+                            continue;
+                        }
+                        let position = model.entity(entity).and_then(|entity| {
+                            entity
+                                .attributes
+                                .iter()
+                                .filter(|attribute| attribute.supertype.is_none())
+                                .position(|own| own.name.eq_ignore_ascii_case(&attribute))
+                        });
+                        if let Some(position) = position.filter(|position| *position < values.len()) {
+                            found = Some(&mut values[position]);
+                            break;
+                        }
+                    }
+                    found.ok_or(EvalError::UnknownName(attribute))?
+                }
+                (Target::Attribute(_, attribute), Value::Entity(id)) => {
+                    return Err(EvalError::Unsupported(format!(
+                        "assignment to attribute {} of instance #{}",
+                        attribute, id
+                    )));
+                }
+                (_, target) => return Err(EvalError::TypeMismatch(format!("assignment into {:?}", target))),
+            };
+        }
+        *target = value;
+        Ok(())
+    }
+}
+
+/// The variable and accessors of an argument which names a variable or a part of its value.
+fn assignable(expression: &Expression) -> Option<(&str, &[Accessor])> {
+    if !expression.operations.is_empty()
+        || !expression.operand.operations.is_empty()
+        || !expression.operand.operand.operations.is_empty()
+        || !expression.operand.operand.operand.operations.is_empty()
+    {
+        return None;
+    }
+    match &expression.operand.operand.operand.operand {
+        SimpleFactor::QualifiedAccess(QualifiedAccess {
+            base: Primary::Reference(name),
+            accessors,
+        }) if !accessors
+            .iter()
+            .any(|accessor| matches!(accessor, Accessor::FunctionCall { .. })) =>
+        {
+            Some((name.as_str(), accessors.as_slice()))
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,624 @@
+use super::{
+    Accessor, Constant, DataType, Declaration, DomainRule, Expression, Factor, Interface, Local, Parameter, Primary,
+    QualifiedAccess, Schema, SimpleExpression, SimpleFactor, Statement, Term,
+};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Errors linking schemas into one.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LinkError {
+    UnknownSchema(String),
+    /// An interfaced item which the schema neither declares nor interfaces itself.
+    UnknownItem {
+        schema: String,
+        item: String,
+    },
+    /// Different declarations which would have the same name in the linked schema.
+    DuplicateName(String),
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LinkError::UnknownSchema(schema) => write!(f, "schema {} is not defined", schema),
+            LinkError::UnknownItem { schema, item } => write!(f, "{} is not defined in schema {}", item, schema),
+            LinkError::DuplicateName(name) => write!(f, "{} is declared more than once", name),
+        }
+    }
+}
+
+impl std::error::Error for LinkError {}
+
+/// A declaration or constant, by the index of its schema and its index in the schema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Item {
+    Declaration(usize, usize),
+    Constant(usize, usize),
+}
+
+/// The names visible in a schema, in the order they are declared or interfaced.
+#[derive(Default)]
+struct Scope {
+    items: Vec<(String, Item)>,
+    /// The position in `items` by lower case name.
+    index: HashMap<String, usize>,
+}
+
+impl Scope {
+    /// Add an item, `false` when it is visible already.
+    fn insert(&mut self, name: &str, item: Item) -> Result<bool, LinkError> {
+        let key = name.to_lowercase();
+        if let Some(position) = self.index.get(&key) {
+            return match self.items[*position].1 == item {
+                true => Ok(false),
+                false => Err(LinkError::DuplicateName(name.to_string())),
+            };
+        }
+        self.index.insert(key, self.items.len());
+        self.items.push((name.to_string(), item));
+        Ok(true)
+    }
+
+    fn get(&self, name: &str) -> Option<Item> {
+        self.index
+            .get(&name.to_lowercase())
+            .map(|position| self.items[*position].1)
+    }
+}
+
+/// Link the schema named `root` with the schemas it interfaces, directly or indirectly, into one schema.
+///
+/// The linked schema holds the declarations and constants of `root`, the items it interfaces by `USE FROM` and
+/// `REFERENCE FROM` under their aliases, and every declaration these refer to in turn. Names are resolved in the
+/// schema using them and replaced by the name of the item in the linked schema.
+///
+/// The items which are only referred to keep their names, unless another item of the linked schema has the name
+/// already. They are not visible in `root`, so they are renamed to `schema_name` then, e.g. `units_label`.
+pub fn link(schemas: Vec<Schema>, root: &str) -> Result<Schema, LinkError> {
+    let root = schemas
+        .iter()
+        .position(|schema| schema.name.eq_ignore_ascii_case(root))
+        .ok_or_else(|| LinkError::UnknownSchema(root.to_string()))?;
+    let scopes = scopes(&schemas)?;
+    let name = schemas[root].name.clone();
+    let mut linker = Linker {
+        names: schemas.iter().map(|schema| schema.name.to_lowercase()).collect(),
+        scopes,
+        declarations: Vec::with_capacity(schemas.len()),
+        constants: Vec::with_capacity(schemas.len()),
+        linked: HashMap::new(),
+        taken: HashMap::new(),
+        queue: Vec::new(),
+    };
+    for schema in schemas {
+        linker
+            .declarations
+            .push(schema.declarations.into_iter().map(Some).collect());
+        linker.constants.push(schema.constants.into_iter().map(Some).collect());
+    }
+    let visible = linker.scopes[root].items.clone();
+    for (name, item) in visible {
+        linker.include(item, &name);
+    }
+    let mut schema = Schema {
+        name,
+        interfaces: Vec::new(),
+        constants: Vec::new(),
+        declarations: Vec::new(),
+    };
+    let mut position = 0;
+    while position < linker.queue.len() {
+        let item = linker.queue[position];
+        position += 1;
+        let linked = linker.linked[&item].clone();
+        match item {
+            Item::Declaration(index, declaration) => {
+                let mut declaration = linker.declarations[index][declaration].take().unwrap();
+                *declaration_name(&mut declaration) = linked;
+                Renamer::new(&mut linker, index).declaration(&mut declaration);
+                schema.declarations.push(declaration);
+            }
+            Item::Constant(index, constant) => {
+                let mut constant = linker.constants[index][constant].take().unwrap();
+                constant.name = linked;
+                Renamer::new(&mut linker, index).constant(&mut constant);
+                schema.constants.push(constant);
+            }
+        }
+    }
+    Ok(schema)
+}
+
+/// The names visible in each schema, its own declarations and constants first.
+fn scopes(schemas: &[Schema]) -> Result<Vec<Scope>, LinkError> {
+    let mut scopes = Vec::with_capacity(schemas.len());
+    for (index, schema) in schemas.iter().enumerate() {
+        let mut scope = Scope::default();
+        for (position, constant) in schema.constants.iter().enumerate() {
+            scope.insert(&constant.name, Item::Constant(index, position))?;
+        }
+        for (position, declaration) in schema.declarations.iter().enumerate() {
+            scope.insert(declaration.name(), Item::Declaration(index, position))?;
+        }
+        scopes.push(scope);
+    }
+    let source = |interface: &Interface| {
+        schemas
+            .iter()
+            .position(|schema| schema.name.eq_ignore_ascii_case(&interface.schema))
+            .ok_or_else(|| LinkError::UnknownSchema(interface.schema.clone()))
+    };
+    // interfaced items can be interfaced again, so repeat until no schema sees more names
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (index, schema) in schemas.iter().enumerate() {
+            for interface in &schema.interfaces {
+                let source = source(interface)?;
+                let items = match interface.items.as_slice() {
+                    [] => scopes[source].items.clone(),
+                    items => items
+                        .iter()
+                        .filter_map(|item| {
+                            let alias = item.alias.as_ref().unwrap_or(&item.name);
+                            scopes[source].get(&item.name).map(|found| (alias.clone(), found))
+                        })
+                        .collect(),
+                };
+                for (name, item) in items {
+                    changed |= scopes[index].insert(&name, item)?;
+                }
+            }
+        }
+    }
+    for schema in schemas {
+        for interface in &schema.interfaces {
+            let source = &scopes[source(interface)?];
+            if let Some(item) = interface.items.iter().find(|item| source.get(&item.name).is_none()) {
+                return Err(LinkError::UnknownItem {
+                    schema: interface.schema.clone(),
+                    item: item.name.clone(),
+                });
+            }
+        }
+    }
+    Ok(scopes)
+}
+
+struct Linker {
+    /// The lower case names of the schemas.
+    names: Vec<String>,
+    scopes: Vec<Scope>,
+    /// The declarations and constants of each schema, taken when they are linked.
+    declarations: Vec<Vec<Option<Declaration>>>,
+    constants: Vec<Vec<Option<Constant>>>,
+    /// The names of the items in the linked schema.
+    linked: HashMap<Item, String>,
+    /// The items by lower case name in the linked schema.
+    taken: HashMap<String, Item>,
+    /// The items in the order they are added to the linked schema.
+    queue: Vec<Item>,
+}
+
+impl Linker {
+    /// Add an item to the linked schema under a name which is not taken, unless it is there already.
+    fn include(&mut self, item: Item, name: &str) -> String {
+        if let Some(linked) = self.linked.get(&item) {
+            return linked.clone();
+        }
+        self.taken.insert(name.to_lowercase(), item);
+        self.linked.insert(item, name.to_string());
+        self.queue.push(item);
+        name.to_string()
+    }
+
+    /// The name in the linked schema of the item a name refers to in a schema.
+    fn resolve(&mut self, schema: usize, name: &str) -> Option<String> {
+        let item = self.scopes[schema].get(name)?;
+        if let Some(linked) = self.linked.get(&item) {
+            return Some(linked.clone());
+        }
+        // an item needed by another one keeps the name it is declared with if that is free
+        let (schema, declared) = match item {
+            Item::Declaration(index, position) => (index, self.declarations[index][position].as_ref()?.name()),
+            Item::Constant(index, position) => (index, self.constants[index][position].as_ref()?.name.as_str()),
+        };
+        let mut name = declared.to_string();
+        let mut count = 1;
+        while self.taken.contains_key(&name.to_lowercase()) {
+            name = format!("{}_{}", self.names[schema], declared);
+            if count > 1 {
+                name = format!("{}_{}", name, count);
+            }
+            count += 1;
+        }
+        Some(self.include(item, &name))
+    }
+}
+
+fn declaration_name(declaration: &mut Declaration) -> &mut String {
+    match declaration {
+        Declaration::TypeDef(type_def) => &mut type_def.name,
+        Declaration::Entity(entity) => &mut entity.name,
+        Declaration::Function(function) => &mut function.name,
+        Declaration::Procedure(procedure) => &mut procedure.name,
+        Declaration::Rule(rule) => &mut rule.name,
+    }
+}
+
+/// Replaces the names in a declaration by the names of the items they refer to in the linked schema.
+struct Renamer<'l> {
+    linker: &'l mut Linker,
+    schema: usize,
+    /// Attributes, parameters and variables, which hide items of the same name.
+    hidden: Vec<String>,
+}
+
+impl<'l> Renamer<'l> {
+    fn new(linker: &'l mut Linker, schema: usize) -> Self {
+        Renamer {
+            linker,
+            schema,
+            hidden: Vec::new(),
+        }
+    }
+
+    fn name(&mut self, name: &mut String) {
+        if self.hidden.iter().any(|hidden| hidden.eq_ignore_ascii_case(name)) {
+            return;
+        }
+        if let Some(linked) = self.linker.resolve(self.schema, name) {
+            if !linked.eq_ignore_ascii_case(name) {
+                *name = linked;
+            }
+        }
+    }
+
+    fn hide(&mut self, name: &str) {
+        self.hidden.push(name.to_string());
+    }
+
+    fn declaration(&mut self, declaration: &mut Declaration) {
+        match declaration {
+            Declaration::TypeDef(type_def) => {
+                self.data_type(&mut type_def.underlying_type);
+                self.domain_rules(&mut type_def.domain_rules);
+            }
+            Declaration::Entity(entity) => {
+                // the types of the attributes are named in the schema, so they are resolved before the attribute
+                // names hide items of the same name
+                for supertype in &mut entity.supertypes {
+                    self.name(supertype);
+                }
+                for attribute in &mut entity.attributes {
+                    if let Some(supertype) = &mut attribute.supertype {
+                        self.name(supertype);
+                    }
+                    self.data_type(&mut attribute.data_type);
+                }
+                for derive in &mut entity.derives {
+                    if let Some(supertype) = &mut derive.supertype {
+                        self.name(supertype);
+                    }
+                    self.data_type(&mut derive.data_type);
+                }
+                for inverse in &mut entity.inverses {
+                    if let Some(supertype) = &mut inverse.supertype {
+                        self.name(supertype);
+                    }
+                    self.data_type(&mut inverse.data_type);
+                }
+                for rule in &mut entity.unique_rules {
+                    for attribute in &mut rule.attributes {
+                        if let Some(entity) = &mut attribute.entity {
+                            self.name(entity);
+                        }
+                    }
+                }
+                for attribute in &entity.attributes {
+                    self.hide(&attribute.name);
+                }
+                for derive in &entity.derives {
+                    self.hide(&derive.name);
+                }
+                for inverse in &entity.inverses {
+                    self.hide(&inverse.name);
+                }
+                for derive in &mut entity.derives {
+                    self.expression(&mut derive.expr);
+                }
+                self.domain_rules(&mut entity.domain_rules);
+            }
+            Declaration::Function(function) => {
+                self.data_type(&mut function.return_type);
+                self.algorithm(
+                    &mut function.parameters,
+                    &mut function.declarations,
+                    &mut function.constants,
+                    &mut function.locals,
+                    &mut function.statements,
+                );
+            }
+            Declaration::Procedure(procedure) => self.algorithm(
+                &mut procedure.parameters,
+                &mut procedure.declarations,
+                &mut procedure.constants,
+                &mut procedure.locals,
+                &mut procedure.statements,
+            ),
+            Declaration::Rule(rule) => {
+                // the sets of instances in the rule are named by their entities, which are renamed alike
+                for entity in &mut rule.entities {
+                    self.name(entity);
+                }
+                self.algorithm(
+                    &mut [],
+                    &mut rule.declarations,
+                    &mut rule.constants,
+                    &mut rule.locals,
+                    &mut rule.statements,
+                );
+                self.domain_rules(&mut rule.domain_rules);
+            }
+        }
+    }
+
+    fn algorithm(
+        &mut self,
+        parameters: &mut [Parameter],
+        declarations: &mut [Declaration],
+        constants: &mut [Constant],
+        locals: &mut [Local],
+        statements: &mut [Statement],
+    ) {
+        for parameter in parameters.iter_mut() {
+            self.data_type(&mut parameter.data_type);
+            self.hide(&parameter.name);
+        }
+        for declaration in declarations.iter() {
+            self.hide(declaration.name());
+        }
+        for constant in constants.iter_mut() {
+            self.constant(constant);
+            self.hide(&constant.name);
+        }
+        for local in locals.iter_mut() {
+            self.data_type(&mut local.data_type);
+            if let Some(expr) = &mut local.expr {
+                self.expression(expr);
+            }
+            self.hide(&local.name);
+        }
+        for declaration in declarations {
+            self.declaration(declaration);
+        }
+        self.statements(statements);
+    }
+
+    fn constant(&mut self, constant: &mut Constant) {
+        self.data_type(&mut constant.data_type);
+        self.expression(&mut constant.expr);
+    }
+
+    fn domain_rules(&mut self, rules: &mut [DomainRule]) {
+        for rule in rules {
+            self.expression(&mut rule.expr);
+        }
+    }
+
+    fn data_type(&mut self, data_type: &mut DataType) {
+        match data_type {
+            DataType::TypeRef { name } => self.name(name),
+            DataType::Select { types } => {
+                for name in types {
+                    self.name(name);
+                }
+            }
+            DataType::Array { bound, base_type, .. }
+            | DataType::List { bound, base_type, .. }
+            | DataType::Bag { bound, base_type }
+            | DataType::Set { bound, base_type } => {
+                if let Some(bound) = bound {
+                    self.simple_expression(&mut bound.start);
+                    if let Some(end) = &mut bound.end {
+                        self.simple_expression(end);
+                    }
+                }
+                self.data_type(base_type);
+            }
+            DataType::Aggregate { base_type, .. } => self.data_type(base_type),
+            DataType::Number
+            | DataType::Integer
+            | DataType::Real { .. }
+            | DataType::Boolean
+            | DataType::Logical
+            | DataType::String { .. }
+            | DataType::Binary { .. }
+            | DataType::Enum { .. }
+            | DataType::Generic { .. } => {}
+        }
+    }
+
+    fn statements(&mut self, statements: &mut [Statement]) {
+        for statement in statements {
+            self.statement(statement);
+        }
+    }
+
+    fn statement(&mut self, statement: &mut Statement) {
+        match statement {
+            Statement::Alias {
+                variable,
+                target,
+                accessors,
+                statements,
+            } => {
+                self.name(target);
+                self.accessors(accessors);
+                self.hide(variable);
+                self.statements(statements);
+                self.hidden.pop();
+            }
+            Statement::Assignment {
+                target,
+                accessors,
+                value,
+            } => {
+                self.name(target);
+                self.accessors(accessors);
+                self.expression(value);
+            }
+            Statement::Case {
+                selector,
+                actions,
+                otherwise,
+            } => {
+                self.expression(selector);
+                for action in actions {
+                    for label in &mut action.labels {
+                        self.expression(label);
+                    }
+                    self.statement(&mut action.statement);
+                }
+                if let Some(otherwise) = otherwise {
+                    self.statement(otherwise);
+                }
+            }
+            Statement::Compound(statements) => self.statements(statements),
+            Statement::If {
+                condition,
+                statements,
+                else_statements,
+            } => {
+                self.expression(condition);
+                self.statements(statements);
+                self.statements(else_statements);
+            }
+            Statement::ProcedureCall { name, parameters } => {
+                self.name(name);
+                for parameter in parameters {
+                    self.expression(parameter);
+                }
+            }
+            Statement::Repeat {
+                increment,
+                while_condition,
+                until_condition,
+                statements,
+            } => {
+                if let Some(increment) = increment {
+                    self.expression(&mut increment.start);
+                    self.expression(&mut increment.end);
+                    if let Some(step) = &mut increment.step {
+                        self.expression(step);
+                    }
+                    self.hide(&increment.variable);
+                }
+                for condition in while_condition.iter_mut().chain(until_condition) {
+                    self.expression(condition);
+                }
+                self.statements(statements);
+                if increment.is_some() {
+                    self.hidden.pop();
+                }
+            }
+            Statement::Return(Some(value)) => self.expression(value),
+            Statement::Return(None) | Statement::Escape | Statement::Null | Statement::Skip => {}
+        }
+    }
+
+    fn expression(&mut self, expression: &mut Expression) {
+        self.simple_expression(&mut expression.operand);
+        for (_, operand) in &mut expression.operations {
+            self.simple_expression(operand);
+        }
+    }
+
+    fn simple_expression(&mut self, expression: &mut SimpleExpression) {
+        self.term(&mut expression.operand);
+        for (_, operand) in &mut expression.operations {
+            self.term(operand);
+        }
+    }
+
+    fn term(&mut self, term: &mut Term) {
+        self.factor(&mut term.operand);
+        for (_, operand) in &mut term.operations {
+            self.factor(operand);
+        }
+    }
+
+    fn factor(&mut self, factor: &mut Factor) {
+        self.simple_factor(&mut factor.operand);
+        for (_, operand) in &mut factor.operations {
+            self.simple_factor(operand);
+        }
+    }
+
+    fn simple_factor(&mut self, factor: &mut SimpleFactor) {
+        match factor {
+            SimpleFactor::AggregateInitializer { elements } => {
+                for (element, repetition) in elements {
+                    self.expression(element);
+                    if let Some(repetition) = repetition {
+                        self.expression(repetition);
+                    }
+                }
+            }
+            SimpleFactor::EnityConstructor { entity, parameters } => {
+                self.name(entity);
+                for parameter in parameters {
+                    self.expression(parameter);
+                }
+            }
+            SimpleFactor::EnumReference => {}
+            SimpleFactor::Interval { low, term, high, .. } => {
+                self.simple_expression(low);
+                self.simple_expression(term);
+                self.simple_expression(high);
+            }
+            SimpleFactor::QueryExpression {
+                variable,
+                source,
+                condition,
+            } => {
+                self.simple_expression(source);
+                self.hide(variable);
+                self.expression(condition);
+                self.hidden.pop();
+            }
+            SimpleFactor::UnaryExpression { operand, .. } => self.qualified_access(operand),
+            SimpleFactor::QualifiedAccess(access) => self.qualified_access(access),
+        }
+    }
+
+    fn qualified_access(&mut self, access: &mut QualifiedAccess) {
+        match &mut access.base {
+            Primary::Reference(name) => self.name(name),
+            Primary::Grouped(expression) => self.expression(expression),
+            Primary::Literal(_) | Primary::Constant(_) => {}
+        }
+        self.accessors(&mut access.accessors);
+    }
+
+    fn accessors(&mut self, accessors: &mut [Accessor]) {
+        for accessor in accessors {
+            match accessor {
+                Accessor::FunctionCall { parameters } => {
+                    for parameter in parameters {
+                        self.expression(parameter);
+                    }
+                }
+                Accessor::Indexer { start, end } => {
+                    self.simple_expression(start);
+                    if let Some(end) = end {
+                        self.simple_expression(end);
+                    }
+                }
+                Accessor::Group { entity } => self.name(entity),
+                Accessor::Attribute { .. } => {}
+            }
+        }
+    }
+}
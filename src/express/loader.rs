@@ -0,0 +1,67 @@
+use super::{link, parser, LinkError, Schema};
+use crate::step::StepError;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Errors loading schemas from EXPRESS files.
+#[derive(Debug)]
+pub enum LoadError {
+    Io {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+    /// A file which is not valid EXPRESS, located by a `StepError::Syntax`.
+    Syntax {
+        path: PathBuf,
+        error: StepError,
+    },
+    /// No `root` schema was given and the first file declares none.
+    NoSchema,
+    Link(LinkError),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Io { path, error } => write!(f, "{}: {}", path.display(), error),
+            LoadError::Syntax { path, error } => write!(f, "{}: {}", path.display(), error),
+            LoadError::NoSchema => write!(f, "no schema in file"),
+            LoadError::Link(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl From<LinkError> for LoadError {
+    fn from(error: LinkError) -> Self {
+        LoadError::Link(error)
+    }
+}
+
+/// Read the schemas of EXPRESS files and link the schema named `root` with the ones it interfaces, the first
+/// schema of the first file by default.
+pub fn load_schema<I>(paths: I, root: Option<&str>) -> Result<Schema, LoadError>
+where
+    I: IntoIterator,
+    I::Item: AsRef<Path>,
+{
+    let mut schemas = Vec::new();
+    for path in paths {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(|error| LoadError::Io {
+            path: path.to_path_buf(),
+            error,
+        })?;
+        let parsed = parser::schemas().parse(&bytes).map_err(|error| LoadError::Syntax {
+            path: path.to_path_buf(),
+            error: StepError::syntax(&bytes, error),
+        })?;
+        schemas.extend(parsed);
+    }
+    let root = match root {
+        Some(root) => root.to_string(),
+        None => schemas.first().ok_or(LoadError::NoSchema)?.name.clone(),
+    };
+    Ok(link(schemas, &root)?)
+}
@@ -1,10 +1,27 @@
 mod datatype;
 mod declaration;
+mod evaluator;
 mod expression;
+mod interpreter;
+mod linker;
+mod loader;
+mod model;
 pub mod parser;
+mod resolve;
 mod schema;
+mod statement;
+mod validate;
+mod value;
 
 pub use datatype::*;
 pub use declaration::*;
+pub use evaluator::*;
 pub use expression::*;
+pub use linker::*;
+pub use loader::*;
+pub use model::*;
+pub use resolve::*;
 pub use schema::*;
+pub use statement::*;
+pub use validate::*;
+pub use value::*;
@@ -0,0 +1,534 @@
+use super::{
+    Attribute, AttributeSlot, DataType, Declaration, Entity, EntityId, EvalError, Evaluator, Function, Procedure,
+    ResolvedSchema, Schema, TypeDef, Value,
+};
+use crate::step::{EntityInstance, InstanceStream, Parameter, StepError, TypedParameter, UnTypedParameter};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::io::BufRead;
+
+/// An attribute of an entity instance referring to another instance.
+#[derive(Debug, Clone, Copy)]
+pub struct Role<'a> {
+    pub id: i64,
+    pub entity: &'a Entity,
+    pub attribute: &'a Attribute,
+}
+
+/// A population of entity instances together with their schema, to evaluate EXPRESS expressions against.
+///
+/// Names are looked up case insensitively, as EXPRESS is case insensitive.
+pub struct Model<'a> {
+    schema: &'a Schema,
+    resolved: ResolvedSchema<'a>,
+    procedures: HashMap<String, &'a Procedure>,
+    /// Enumeration items and the enumeration types declaring them.
+    enum_items: HashMap<String, &'a TypeDef>,
+    /// Named types and the select types listing them.
+    selects: HashMap<String, Vec<&'a TypeDef>>,
+    /// The entity and all its supertypes, supertypes first.
+    supertypes: HashMap<EntityId, Vec<&'a Entity>>,
+    instances: BTreeMap<i64, Vec<TypedParameter>>,
+    used_in: HashMap<i64, Vec<Role<'a>>>,
+}
+
+impl<'a> Model<'a> {
+    pub fn new(schema: &'a Schema) -> Self {
+        let resolved = ResolvedSchema::new(schema);
+        let supertypes = resolved
+            .entities()
+            .map(|(id, entity)| {
+                let ancestors = entity
+                    .ancestors
+                    .iter()
+                    .map(|ancestor| resolved.entity(*ancestor).entity);
+                (id, ancestors.collect())
+            })
+            .collect();
+        let mut model = Model {
+            schema,
+            resolved,
+            procedures: HashMap::new(),
+            enum_items: HashMap::new(),
+            selects: HashMap::new(),
+            supertypes,
+            instances: BTreeMap::new(),
+            used_in: HashMap::new(),
+        };
+        for declaration in &schema.declarations {
+            match declaration {
+                Declaration::TypeDef(type_def) => {
+                    if let DataType::Enum { values } = &type_def.underlying_type {
+                        for value in values {
+                            model.enum_items.insert(value.to_lowercase(), type_def);
+                        }
+                    }
+                    if let DataType::Select { types } = &type_def.underlying_type {
+                        for name in types {
+                            model.selects.entry(name.to_lowercase()).or_default().push(type_def);
+                        }
+                    }
+                }
+                Declaration::Procedure(procedure) => {
+                    model.procedures.insert(procedure.name.to_lowercase(), procedure);
+                }
+                Declaration::Entity(_) | Declaration::Function(_) | Declaration::Rule(_) => {}
+            }
+        }
+        model
+    }
+
+    /// Build the model of the instances of a STEP file.
+    pub fn read_from<R: BufRead>(schema: &'a Schema, reader: R) -> Result<Self, StepError> {
+        let mut model = Model::new(schema);
+        for instance in InstanceStream::new(reader)? {
+            model.insert(instance?);
+        }
+        Ok(model)
+    }
+
+    pub fn schema(&self) -> &'a Schema {
+        self.schema
+    }
+
+    /// The schema with its names resolved.
+    pub fn resolved(&self) -> &ResolvedSchema<'a> {
+        &self.resolved
+    }
+
+    pub fn entity(&self, name: &str) -> Option<&'a Entity> {
+        self.resolved.entity_id(name).map(|id| self.resolved.entity(id).entity)
+    }
+
+    pub fn type_def(&self, name: &str) -> Option<&'a TypeDef> {
+        self.resolved
+            .type_id(name)
+            .map(|id| self.resolved.type_def(id).type_def)
+    }
+
+    pub fn function(&self, name: &str) -> Option<&'a Function> {
+        self.resolved.function_id(name).map(|id| self.resolved.function(id))
+    }
+
+    pub fn procedure(&self, name: &str) -> Option<&'a Procedure> {
+        self.procedures.get(&name.to_lowercase()).copied()
+    }
+
+    /// The underlying type of a defined type, following defined types declared as other defined types.
+    pub fn underlying_type(&self, mut data_type: &'a DataType) -> &'a DataType {
+        while let DataType::TypeRef { name } = data_type {
+            match self.type_def(name) {
+                Some(type_def) => data_type = &type_def.underlying_type,
+                None => break,
+            }
+        }
+        data_type
+    }
+
+    /// The enumeration type declaring an enumeration item.
+    pub fn enumeration_of(&self, item: &str) -> Option<&'a TypeDef> {
+        self.enum_items.get(&item.to_lowercase()).copied()
+    }
+
+    /// The entity and all its supertypes, supertypes first.
+    pub fn supertypes(&self, name: &str) -> &[&'a Entity] {
+        self.resolved
+            .entity_id(name)
+            .and_then(|id| self.supertypes.get(&id))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// The explicit attributes of an entity in the order of its simple instances.
+    pub fn attributes(&self, name: &str) -> &[AttributeSlot<'a>] {
+        self.resolved
+            .entity_id(name)
+            .map_or(&[], |id| self.resolved.entity(id).attributes.as_slice())
+    }
+
+    /// Add an instance, a later instance with the same name replaces the earlier one.
+    pub fn insert(&mut self, instance: EntityInstance) {
+        let id = instance.id;
+        if let Some(old) = self.instances.remove(&id) {
+            // the replaced instance no longer refers to anything
+            let mut references = Vec::new();
+            self.visit_slots(&old, |_, parameter| {
+                collect_references(parameter, &mut |reference| references.push(reference));
+            });
+            for reference in references {
+                if let Some(roles) = self.used_in.get_mut(&reference) {
+                    roles.retain(|role| role.id != id);
+                }
+            }
+        }
+        let mut roles = Vec::new();
+        self.visit_slots(&instance.value, |slot, parameter| {
+            collect_references(parameter, &mut |reference| roles.push((reference, slot)));
+        });
+        for (reference, slot) in roles {
+            self.used_in.entry(reference).or_default().push(Role {
+                id,
+                entity: slot.entity,
+                attribute: slot.attribute,
+            });
+        }
+        self.instances.insert(id, instance.value);
+    }
+
+    /// Call `visit` with the explicit attributes of an instance and their parameters.
+    pub(super) fn visit_slots<'p, F: FnMut(AttributeSlot<'a>, &'p Parameter)>(
+        &self,
+        parts: &'p [TypedParameter],
+        mut visit: F,
+    ) {
+        if let [part] = parts {
+            for (slot, parameter) in self.attributes(&part.type_name).iter().zip(&part.parameters) {
+                visit(*slot, parameter);
+            }
+        } else {
+            // the parts of a complex instance hold the attributes their entities declare
+            for part in parts {
+                if let Some(entity) = self.entity(&part.type_name) {
+                    let own = entity
+                        .attributes
+                        .iter()
+                        .filter(|attribute| attribute.supertype.is_none());
+                    for (attribute, parameter) in own.zip(&part.parameters) {
+                        visit(AttributeSlot { entity, attribute }, parameter);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn instance(&self, id: i64) -> Option<&[TypedParameter]> {
+        self.instances.get(&id).map(Vec::as_slice)
+    }
+
+    pub fn instances(&self) -> impl Iterator<Item = (i64, &[TypedParameter])> {
+        self.instances.iter().map(|(id, parts)| (*id, parts.as_slice()))
+    }
+
+    /// The entities of the parts of an instance.
+    pub fn instance_types(&self, id: i64) -> Vec<&'a Entity> {
+        self.instance(id)
+            .into_iter()
+            .flatten()
+            .filter_map(|part| self.entity(&part.type_name))
+            .collect()
+    }
+
+    /// The entities of the parts of an instance and all their supertypes, supertypes first.
+    pub fn instance_supertypes(&self, id: i64) -> Vec<&'a Entity> {
+        let mut supertypes: Vec<&'a Entity> = Vec::new();
+        for entity in self.instance_types(id) {
+            for supertype in self.supertypes(&entity.name) {
+                if !supertypes.iter().any(|known| std::ptr::eq(*known, *supertype)) {
+                    supertypes.push(supertype);
+                }
+            }
+        }
+        supertypes
+    }
+
+    /// Whether the instance is of the entity type or one of its subtypes.
+    pub fn is_instance_of(&self, id: i64, entity: &str) -> bool {
+        self.instance(id).into_iter().flatten().any(|part| {
+            self.supertypes(&part.type_name)
+                .iter()
+                .any(|supertype| supertype.name.eq_ignore_ascii_case(entity))
+        })
+    }
+
+    /// The instances of the entity type and its subtypes.
+    pub fn instances_of<'m>(&'m self, entity: &'m str) -> impl Iterator<Item = i64> + 'm {
+        self.instances
+            .keys()
+            .copied()
+            .filter(move |id| self.is_instance_of(*id, entity))
+    }
+
+    /// The attributes referring to an instance, once per reference.
+    pub fn used_in(&self, id: i64) -> &[Role<'a>] {
+        self.used_in.get(&id).map_or(&[], Vec::as_slice)
+    }
+
+    /// The value of an explicit, derived or inverse attribute of an instance.
+    ///
+    /// With `group` the attribute is looked up in that supertype, as in `SELF\group.attribute`.
+    pub fn attribute(&self, id: i64, group: Option<&str>, name: &str) -> Result<Value, EvalError> {
+        let parts = self.instance(id).ok_or(EvalError::UnknownInstance(id))?;
+        let types = match group {
+            Some(group) if self.is_instance_of(id, group) => vec![self.entity(group).unwrap()],
+            Some(_) => return Ok(Value::Indeterminate),
+            None => self.instance_types(id),
+        };
+        let mut explicit = None;
+        self.visit_slots(parts, |slot, parameter| {
+            let visible = types.iter().any(|entity| {
+                self.supertypes(&entity.name)
+                    .iter()
+                    .any(|supertype| std::ptr::eq(*supertype, slot.entity))
+            });
+            if explicit.is_none() && visible && slot.attribute.name.eq_ignore_ascii_case(name) {
+                explicit = Some((slot, parameter));
+            }
+        });
+        if let Some((slot, parameter)) = explicit {
+            if *parameter != Parameter::OmittedParameter {
+                return Ok(self.value(parameter, Some(&slot.attribute.data_type)));
+            }
+        }
+        // derived attributes, an inherited one redeclared as derived is written as `*`
+        let all_types = self.instance_types(id);
+        for entity in all_types
+            .iter()
+            .rev()
+            .flat_map(|entity| self.supertypes(&entity.name).iter().rev())
+        {
+            if let Some(derive) = entity
+                .derives
+                .iter()
+                .find(|derive| derive.name.eq_ignore_ascii_case(name))
+            {
+                let value = Evaluator::with_self(self, id).evaluate(&derive.expr)?;
+                return Ok(self.typed_value(value, &derive.data_type));
+            }
+        }
+        if explicit.is_some() {
+            return Ok(Value::Indeterminate);
+        }
+        for entity in types.iter().flat_map(|entity| self.supertypes(&entity.name)) {
+            if let Some(inverse) = entity
+                .inverses
+                .iter()
+                .find(|inverse| inverse.name.eq_ignore_ascii_case(name))
+            {
+                let mut referrers = Vec::new();
+                for role in self.used_in(id) {
+                    if role.attribute.name.eq_ignore_ascii_case(&inverse.attribute)
+                        && self.is_instance_of(role.id, inverse.entity())
+                    {
+                        referrers.push(Value::Entity(role.id));
+                    }
+                }
+                return Ok(match &inverse.data_type {
+                    DataType::Set { .. } => {
+                        referrers.dedup();
+                        Value::Aggregate(referrers)
+                    }
+                    DataType::Bag { .. } => Value::Aggregate(referrers),
+                    _ => referrers.into_iter().next().unwrap_or(Value::Indeterminate),
+                });
+            }
+        }
+        Err(EvalError::UnknownName(name.to_string()))
+    }
+
+    /// The declared type of an explicit, derived or inverse attribute of an instance.
+    pub fn attribute_type(&self, id: i64, group: Option<&str>, name: &str) -> Option<&'a DataType> {
+        let types = match group {
+            Some(group) => vec![self.entity(group)?],
+            None => self.instance_types(id),
+        };
+        for entity in &types {
+            let slot = self
+                .attributes(&entity.name)
+                .iter()
+                .find(|slot| slot.attribute.name.eq_ignore_ascii_case(name));
+            if let Some(slot) = slot {
+                return Some(&slot.attribute.data_type);
+            }
+        }
+        let supertypes = || {
+            types
+                .iter()
+                .flat_map(|entity| self.supertypes(&entity.name).iter().rev())
+        };
+        if let Some(derive) = supertypes()
+            .flat_map(|entity| &entity.derives)
+            .find(|derive| derive.name.eq_ignore_ascii_case(name))
+        {
+            return Some(&derive.data_type);
+        }
+        supertypes()
+            .flat_map(|entity| &entity.inverses)
+            .find(|inverse| inverse.name.eq_ignore_ascii_case(name))
+            .map(|inverse| &inverse.data_type)
+    }
+
+    /// Convert a parameter to the value of an attribute of the data type.
+    pub fn value(&self, parameter: &Parameter, data_type: Option<&DataType>) -> Value {
+        match parameter {
+            Parameter::TypedParameter(typed) => {
+                let type_name = typed.type_name.to_lowercase();
+                let inner = match self.type_def(&type_name) {
+                    Some(type_def) => match typed.parameters.as_slice() {
+                        [parameter] => self.value(parameter, Some(&type_def.underlying_type)),
+                        _ => Value::Indeterminate,
+                    },
+                    None => Value::Indeterminate,
+                };
+                Value::Typed(type_name, Box::new(inner))
+            }
+            Parameter::OmittedParameter => Value::Indeterminate,
+            Parameter::UnTypedParameter(parameter) => self.untyped_value(parameter, data_type),
+        }
+    }
+
+    fn untyped_value(&self, parameter: &UnTypedParameter, data_type: Option<&DataType>) -> Value {
+        // a defined type wraps its underlying value
+        if let Some(DataType::TypeRef { name }) = data_type {
+            if let Some(type_def) = self.type_def(name) {
+                if !matches!(type_def.underlying_type, DataType::Select { .. }) && *parameter != UnTypedParameter::Null
+                {
+                    let value = self.untyped_value(parameter, Some(&type_def.underlying_type));
+                    return Value::Typed(type_def.name.to_lowercase(), Box::new(value));
+                }
+            }
+        }
+        match parameter {
+            UnTypedParameter::Integer(value) => match data_type {
+                Some(DataType::Real { .. }) => Value::Real(*value as f64),
+                _ => Value::Integer(*value),
+            },
+            UnTypedParameter::Real(value) => Value::Real(*value),
+            UnTypedParameter::String(value) => Value::String(value.clone()),
+            UnTypedParameter::Binary(value) => Value::Binary(value.clone()),
+            UnTypedParameter::EnumValue(value) => match (data_type, value.as_str()) {
+                (Some(DataType::Enum { .. }), _) | (Some(DataType::TypeRef { .. }), _) => {
+                    Value::Enumeration(value.to_lowercase())
+                }
+                (_, "T") => Value::Logical(Some(true)),
+                (_, "F") => Value::Logical(Some(false)),
+                (_, "U") => Value::Logical(None),
+                _ => Value::Enumeration(value.to_lowercase()),
+            },
+            UnTypedParameter::EntityRef(id) => Value::Entity(*id),
+            UnTypedParameter::List(items) => {
+                let item_type = match data_type {
+                    Some(DataType::Set { base_type, .. })
+                    | Some(DataType::Bag { base_type, .. })
+                    | Some(DataType::List { base_type, .. })
+                    | Some(DataType::Array { base_type, .. }) => Some(&**base_type),
+                    _ => None,
+                };
+                Value::Aggregate(items.iter().map(|item| self.value(item, item_type)).collect())
+            }
+            UnTypedParameter::ConstantRef(_)
+            | UnTypedParameter::ValueRef(_)
+            | UnTypedParameter::Resource(_)
+            | UnTypedParameter::Null => Value::Indeterminate,
+        }
+    }
+
+    /// Wrap a value in the defined type it is declared with, a SET drops duplicate elements.
+    pub fn typed_value(&self, value: Value, data_type: &DataType) -> Value {
+        match (data_type, value) {
+            (_, value @ Value::Indeterminate) | (_, value @ Value::Typed(..)) => value,
+            (DataType::Set { .. }, Value::Aggregate(values)) => {
+                let mut elements: Vec<Value> = Vec::with_capacity(values.len());
+                for value in values {
+                    if !elements
+                        .iter()
+                        .any(|known| known.compare(&value) == Some(Ordering::Equal))
+                    {
+                        elements.push(value);
+                    }
+                }
+                Value::Aggregate(elements)
+            }
+            (DataType::TypeRef { name }, value) => match self.type_def(name) {
+                Some(type_def) if !matches!(type_def.underlying_type, DataType::Select { .. }) => {
+                    let value = self.typed_value(value, &type_def.underlying_type);
+                    Value::Typed(type_def.name.to_lowercase(), Box::new(value))
+                }
+                _ => value,
+            },
+            (_, value) => value,
+        }
+    }
+
+    /// The type names of a value as returned by `TYPEOF`, qualified by the schema name and in upper case.
+    pub fn type_names(&self, value: &Value) -> Vec<String> {
+        let schema = self.schema.name.to_uppercase();
+        let mut names = Vec::new();
+        match value {
+            Value::Entity(id) => {
+                for entity in self.instance_supertypes(*id) {
+                    names.push(format!("{}.{}", schema, entity.name.to_uppercase()));
+                }
+            }
+            Value::Constructed(parts) => {
+                for (entity, _) in parts {
+                    for supertype in self.supertypes(entity) {
+                        let name = format!("{}.{}", schema, supertype.name.to_uppercase());
+                        if !names.contains(&name) {
+                            names.push(name);
+                        }
+                    }
+                }
+            }
+            Value::Typed(type_name, inner) => {
+                names.push(format!("{}.{}", schema, type_name.to_uppercase()));
+                // the defined types this one is based on
+                let mut data_type = self.type_def(type_name).map(|type_def| &type_def.underlying_type);
+                while let Some(DataType::TypeRef { name }) = data_type {
+                    match self.type_def(name) {
+                        Some(type_def) => {
+                            names.push(format!("{}.{}", schema, type_def.name.to_uppercase()));
+                            data_type = Some(&type_def.underlying_type);
+                        }
+                        None => break,
+                    }
+                }
+                for name in self.type_names(inner) {
+                    if !names.contains(&name) {
+                        names.push(name);
+                    }
+                }
+            }
+            Value::Integer(_) => names.extend(vec!["INTEGER".to_string(), "NUMBER".to_string()]),
+            Value::Real(_) => names.extend(vec!["REAL".to_string(), "NUMBER".to_string()]),
+            Value::Logical(_) => names.push("LOGICAL".to_string()),
+            Value::String(_) => names.push("STRING".to_string()),
+            Value::Binary(_) => names.push("BINARY".to_string()),
+            Value::Enumeration(item) => {
+                if let Some(type_def) = self.enumeration_of(item) {
+                    names.push(format!("{}.{}", schema, type_def.name.to_uppercase()));
+                }
+            }
+            Value::Aggregate(_) | Value::Indeterminate => {}
+        }
+        // a value is also of the select types listing one of its types
+        let mut index = 0;
+        while index < names.len() {
+            let name = names[index].rsplit('.').next().unwrap_or_default().to_lowercase();
+            for select in self.selects.get(&name).into_iter().flatten() {
+                let name = format!("{}.{}", schema, select.name.to_uppercase());
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+            index += 1;
+        }
+        names
+    }
+}
+
+/// Call `visit` with each entity instance name in a parameter.
+fn collect_references(parameter: &Parameter, visit: &mut dyn FnMut(i64)) {
+    match parameter {
+        Parameter::UnTypedParameter(UnTypedParameter::EntityRef(id)) => visit(*id),
+        Parameter::UnTypedParameter(UnTypedParameter::List(items)) => {
+            for item in items {
+                collect_references(item, visit);
+            }
+        }
+        Parameter::TypedParameter(typed) => {
+            for item in &typed.parameters {
+                collect_references(item, visit);
+            }
+        }
+        _ => {}
+    }
+}
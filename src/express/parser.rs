@@ -13,7 +13,8 @@ fn remark<'a>() -> Parser<'a, u8, ()> {
 }
 
 fn tail_remark<'a>() -> Parser<'a, u8, ()> {
-    seq(b"--") * none_of(b"\r\n").repeat(0..) * eol().discard()
+    // `//` is not EXPRESS, but found in function bodies of the IFC schemas
+    (seq(b"--") | seq(b"//")) * none_of(b"\r\n").repeat(0..) * eol().discard()
 }
 
 fn embedded_remark<'a>() -> Parser<'a, u8, ()> {
@@ -42,7 +43,7 @@ fn keyword<'a>(word: &'static str) -> Parser<'a, u8, ()> {
 }
 
 fn logical<'a>() -> Parser<'a, u8, Option<bool>> {
-    keyword("flase").map(|_| Some(false)) | keyword("true").map(|_| Some(true)) | keyword("unknown").map(|_| None)
+    keyword("false").map(|_| Some(false)) | keyword("true").map(|_| Some(true)) | keyword("unknown").map(|_| None)
 }
 
 fn binary<'a>() -> Parser<'a, u8, String> {
@@ -52,7 +53,7 @@ fn binary<'a>() -> Parser<'a, u8, String> {
 
 fn integer<'a>() -> Parser<'a, u8, i64> {
     let number = one_of(b"+-").opt() + is_a(digit).repeat(1..);
-    number.collect().convert(str::from_utf8).convert(|s| i64::from_str(s))
+    number.collect().convert(str::from_utf8).convert(i64::from_str)
 }
 
 fn real<'a>() -> Parser<'a, u8, f64> {
@@ -84,11 +85,11 @@ fn encoded_string<'a>() -> Parser<'a, u8, String> {
 }
 
 fn literal<'a>() -> Parser<'a, u8, Literal> {
-    real().map(|num| Literal::Real(num))
-        | integer().map(|num| Literal::Integer(num))
-        | logical().map(|val| Literal::Logical(val))
-        | string().map(|text| Literal::String(text))
-        | binary().map(|val| Literal::Binary(val))
+    real().map(Literal::Real)
+        | integer().map(Literal::Integer)
+        | logical().map(Literal::Logical)
+        | string().map(Literal::String)
+        | binary().map(Literal::Binary)
 }
 
 fn simple_data_type<'a>() -> Parser<'a, u8, DataType> {
@@ -132,7 +133,7 @@ fn simple_data_type<'a>() -> Parser<'a, u8, DataType> {
 
 fn bound_spec<'a>() -> Parser<'a, u8, BoundSpec> {
     (sym(b'[') * space() * simple_expression() - sym(b':') - space()
-        + (simple_expression().map(Option::Some) | sym(b'?').map(|_| None) - space())
+        + (simple_expression().map(Option::Some) | (sym(b'?').map(|_| None) - space()))
         - sym(b']'))
     .map(|(start, end)| BoundSpec { start, end })
 }
@@ -232,7 +233,7 @@ fn entity<'a>() -> Parser<'a, u8, Declaration> {
     (head + body - tail).map(
         |(
             ((entity_id, is_abstract), supertypes),
-            ((((attributes, derived_attributes), _inverse_attributes), unique_rules), domain_rules),
+            ((((attributes, derived_attributes), inverse_attributes), unique_rules), domain_rules),
         )| {
             Declaration::Entity(Entity {
                 name: entity_id.to_string(),
@@ -240,6 +241,7 @@ fn entity<'a>() -> Parser<'a, u8, Declaration> {
                 supertypes: supertypes.unwrap_or(Vec::new()),
                 attributes: attributes.into_iter().flatten().collect(),
                 derives: derived_attributes.unwrap_or(Vec::new()),
+                inverses: inverse_attributes.unwrap_or(Vec::new()),
                 domain_rules: domain_rules.unwrap_or(Vec::new()),
                 unique_rules: unique_rules.unwrap_or(Vec::new()),
             })
@@ -249,7 +251,7 @@ fn entity<'a>() -> Parser<'a, u8, Declaration> {
 
 fn supertype_declaration<'a>() -> Parser<'a, u8, bool> {
     (keyword("abstract") * space() * keyword("supertype") * space() * subtype_constraint().opt()).map(|_| true)
-        | keyword("supertype") * space() * subtype_constraint().map(|_| false)
+        | (keyword("supertype") * space() * subtype_constraint().map(|_| false))
 }
 
 fn subtype_constraint<'a>() -> Parser<'a, u8, ()> {
@@ -257,10 +259,10 @@ fn subtype_constraint<'a>() -> Parser<'a, u8, ()> {
 }
 
 fn supertype_term<'a>() -> Parser<'a, u8, ()> {
-    keyword("oneof") * space() * sym(b'(') * list(call(supertype_expr) - space(), sym(b',') - space()).discard()
-        - sym(b')')
+    (keyword("oneof") * space() * sym(b'(') * list(call(supertype_expr) - space(), sym(b',') - space()).discard()
+        - sym(b')'))
         | identifier().discard()
-        | sym(b'(') * call(supertype_expr).discard() - sym(b')')
+        | (sym(b'(') * call(supertype_expr).discard() - sym(b')'))
 }
 fn supertype_factor<'a>() -> Parser<'a, u8, ()> {
     list(supertype_term() - space(), keyword("and") - space()).discard()
@@ -338,19 +340,49 @@ fn derive_clause<'a>() -> Parser<'a, u8, Vec<DerivedAttribute>> {
     keyword("derive") * space() * derive_attribute.repeat(1..)
 }
 
-fn inverse_clause<'a>() -> Parser<'a, u8, ()> {
-    let inverse_attribute = identifier().map(str::to_string) - space() - sym(b':') - space()
-        + ((keyword("set") | keyword("bag")) - space() + bound_spec().opt() - space() - keyword("of")).opt()
+fn inverse_clause<'a>() -> Parser<'a, u8, Vec<InverseAttribute>> {
+    let aggregate = (keyword("set").map(|_| true) | keyword("bag").map(|_| false)) - space() + bound_spec().opt()
+        - space()
+        - keyword("of");
+    let inverse_attribute = ((keyword("self") * sym(b'\\') * space() * identifier().map(str::to_string) - sym(b'.'))
+        .opt()
+        + identifier().map(str::to_string)
+        - space()
+        - sym(b':')
+        - space()
+        + aggregate.opt()
         - space()
-        + identifier()
+        + identifier().map(str::to_string)
         - space()
         - keyword("for")
         - space()
-        + identifier()
+        // the entity of the forward attribute may be named in front of it
+        - (identifier() - sym(b'.')).opt()
+        + identifier().map(str::to_string)
         - space()
         - sym(b';')
-        - space();
-    keyword("inverse") * space() * inverse_attribute.repeat(1..).discard()
+        - space())
+    .map(|((((supertype, name), aggregate), entity), attribute)| {
+        let entity = DataType::TypeRef { name: entity };
+        let data_type = match aggregate {
+            Some((true, bound)) => DataType::Set {
+                bound,
+                base_type: Box::new(entity),
+            },
+            Some((_, bound)) => DataType::Bag {
+                bound,
+                base_type: Box::new(entity),
+            },
+            None => entity,
+        };
+        InverseAttribute {
+            name,
+            supertype,
+            data_type,
+            attribute,
+        }
+    });
+    keyword("inverse") * space() * inverse_attribute.repeat(1..)
 }
 
 fn unique_clause<'a>() -> Parser<'a, u8, Vec<UniqueRule>> {
@@ -395,10 +427,10 @@ fn additive_op<'a>() -> Parser<'a, u8, Operator> {
 }
 
 fn multiplicative_op<'a>() -> Parser<'a, u8, Operator> {
-    seq(b"||").map(|_| Operator::Or)
+    seq(b"||").map(|_| Operator::Combine)
         | sym(b'*').map(|_| Operator::Mul)
         | sym(b'/').map(|_| Operator::Div)
-        | keyword("div").map(|_| Operator::Div)
+        | keyword("div").map(|_| Operator::IntegerDiv)
         | keyword("mod").map(|_| Operator::Mod)
         | keyword("and").map(|_| Operator::And)
 }
@@ -437,10 +469,13 @@ fn simple_factor<'a>() -> Parser<'a, u8, SimpleFactor> {
 }
 
 fn aggregation_initiator<'a>() -> Parser<'a, u8, SimpleFactor> {
-    // let element = expression() + (sym(b':') * expression()).opt();
-    (sym(b'[') * space() * list(call(expression), sym(b',') - space()) - sym(b']')).map(|elements| {
+    let element = call(expression) - space() + (sym(b':') * space() * call(expression) - space()).opt();
+    (sym(b'[') * space() * list(element, sym(b',') - space()) - sym(b']')).map(|elements| {
         SimpleFactor::AggregateInitializer {
-            elements: elements.into_iter().map(|e| Box::new(e)).collect(),
+            elements: elements
+                .into_iter()
+                .map(|(element, repetition)| (Box::new(element), repetition.map(Box::new)))
+                .collect(),
         }
     })
 }
@@ -449,7 +484,7 @@ fn entity_constructor<'a>() -> Parser<'a, u8, SimpleFactor> {
     (identifier().map(str::to_string) - space() - sym(b'(') + list(call(expression), sym(b',') - space()) - sym(b')'))
         .map(|(entity, parameters)| SimpleFactor::EnityConstructor {
             entity,
-            parameters: parameters.into_iter().map(|e| Box::new(e)).collect(),
+            parameters: parameters.into_iter().map(Box::new).collect(),
         })
 }
 
@@ -489,31 +524,35 @@ fn unary_expression<'a>() -> Parser<'a, u8, SimpleFactor> {
 }
 
 fn primary<'a>() -> Parser<'a, u8, Primary> {
-    literal().map(|lit| Primary::Literal(lit))
-        | builtin_constant().map(|constant| Primary::Constant(constant))
+    literal().map(Primary::Literal)
+        | builtin_constant().map(Primary::Constant)
         | identifier().map(|name| Primary::Reference(name.to_string()))
-        | sym(b'(') * space() * call(expression).map(|e| Primary::Grouped(Box::new(e))) - sym(b')')
+        | (sym(b'(') * space() * call(expression).map(|e| Primary::Grouped(Box::new(e))) - sym(b')'))
 }
 
 fn qualified_access<'a>() -> Parser<'a, u8, QualifiedAccess> {
-    let qualifier = sym(b'.') * space() * identifier().map(|name| Accessor::Attribute { name: name.to_string() })
-        | sym(b'\\')
+    (primary() - space() + (qualifier() - space()).repeat(0..))
+        .map(|(base, accessors)| QualifiedAccess { base, accessors })
+}
+
+fn qualifier<'a>() -> Parser<'a, u8, Accessor> {
+    (sym(b'.') * space() * identifier().map(|name| Accessor::Attribute { name: name.to_string() }))
+        | (sym(b'\\')
             * space()
             * identifier().map(|entity| Accessor::Group {
                 entity: entity.to_string(),
-            })
-        | (sym(b'[') * space() * call(simple_expression) + (sym(b':') * call(simple_expression)).opt() - sym(b']'))
-            .map(|(start, end)| Accessor::Indexer {
+            }))
+        | (sym(b'[') * space() * call(simple_expression) + (sym(b':') * call(simple_expression)).opt() - sym(b']')).map(
+            |(start, end)| Accessor::Indexer {
                 start: Box::new(start),
-                end: end.map(|e| Box::new(e)),
-            })
+                end: end.map(Box::new),
+            },
+        )
         | (sym(b'(') * space() * list(call(expression), sym(b',') - space()) - sym(b')')).map(|parameters| {
             Accessor::FunctionCall {
-                parameters: parameters.into_iter().map(|e| Box::new(e)).collect(),
+                parameters: parameters.into_iter().map(Box::new).collect(),
             }
-        });
-    (primary() - space() + (qualifier - space()).repeat(0..))
-        .map(|(base, accessors)| QualifiedAccess { base, accessors })
+        })
 }
 
 fn builtin_constant<'a>() -> Parser<'a, u8, String> {
@@ -557,30 +596,217 @@ fn parameter_type<'a>() -> Parser<'a, u8, DataType> {
     aggregation_data_type() | simple_data_type() | generic_type() | aggregate_type() | type_ref()
 }
 
+const RESERVED_WORDS: &[&str] = &[
+    "alias",
+    "begin",
+    "case",
+    "else",
+    "end",
+    "end_alias",
+    "end_case",
+    "end_function",
+    "end_if",
+    "end_local",
+    "end_procedure",
+    "end_repeat",
+    "end_rule",
+    "escape",
+    "if",
+    "local",
+    "otherwise",
+    "repeat",
+    "return",
+    "skip",
+    "where",
+];
+
+/// A variable or procedure name, which can not be a reserved word starting or ending a statement.
+fn variable<'a>() -> Parser<'a, u8, String> {
+    identifier().convert(|name| {
+        if RESERVED_WORDS.contains(&name.to_ascii_lowercase().as_str()) {
+            Err(())
+        } else {
+            Ok(name.to_string())
+        }
+    })
+}
+
+fn end_statement<'a>(word: &'static str) -> Parser<'a, u8, ()> {
+    keyword(word) - space() - sym(b';') - space()
+}
+
 fn statement<'a>() -> Parser<'a, u8, Statement> {
-    !(keyword("end_function") | keyword("where") | keyword("end_rule"))
-        * none_of(b";").repeat(0..).map(|chars| Statement {
-            text: String::from_utf8(chars).unwrap(),
-        })
+    alias_statement()
+        | case_statement()
+        | compound_statement()
+        | if_statement()
+        | repeat_statement()
+        | return_statement()
+        | end_statement("escape").map(|_| Statement::Escape)
+        | end_statement("skip").map(|_| Statement::Skip)
+        | (sym(b';') - space()).map(|_| Statement::Null)
+        | assignment_statement()
+        | procedure_call_statement()
+}
+
+fn statements<'a>() -> Parser<'a, u8, Vec<Statement>> {
+    call(statement).repeat(0..)
+}
+
+fn alias_statement<'a>() -> Parser<'a, u8, Statement> {
+    (keyword("alias") * space() * variable() - space() - keyword("for") - space() + identifier().map(str::to_string)
+        - space()
+        + (qualifier() - space()).repeat(0..)
         - sym(b';')
         - space()
+        + statements()
+        - end_statement("end_alias"))
+    .map(|(((variable, target), accessors), statements)| Statement::Alias {
+        variable,
+        target,
+        accessors,
+        statements,
+    })
 }
 
-fn function<'a>() -> Parser<'a, u8, Function> {
-    let formal_parameter =
-        (list(identifier().map(str::to_string) - space(), sym(b',') - space()) - sym(b':') - space()
-            + parameter_type())
-        .map(|(names, data_type)| {
-            names
-                .into_iter()
-                .map(|name| Parameter {
-                    name,
-                    data_type: data_type.clone(),
-                })
-                .collect::<Vec<_>>()
+fn assignment_statement<'a>() -> Parser<'a, u8, Statement> {
+    (variable() - space() + (qualifier() - space()).repeat(0..) - seq(b":=") - space() + call(expression)
+        - space()
+        - sym(b';')
+        - space())
+    .map(|((target, accessors), value)| Statement::Assignment {
+        target,
+        accessors,
+        value,
+    })
+}
+
+fn case_statement<'a>() -> Parser<'a, u8, Statement> {
+    let labels = list(call(expression) - space(), sym(b',') - space());
+    let action = (!(keyword("otherwise") | keyword("end_case")) * labels - sym(b':') - space() + call(statement))
+        .map(|(labels, statement)| CaseAction { labels, statement });
+    let otherwise = keyword("otherwise") * space() * sym(b':') * space() * call(statement);
+    (keyword("case") * space() * call(expression) - space() - keyword("of") - space()
+        + action.repeat(0..)
+        + otherwise.opt()
+        - end_statement("end_case"))
+    .map(|((selector, actions), otherwise)| Statement::Case {
+        selector,
+        actions,
+        otherwise: otherwise.map(Box::new),
+    })
+}
+
+fn compound_statement<'a>() -> Parser<'a, u8, Statement> {
+    (keyword("begin") * space() * statements() - end_statement("end")).map(Statement::Compound)
+}
+
+fn if_statement<'a>() -> Parser<'a, u8, Statement> {
+    (keyword("if") * space() * call(expression) - space() - keyword("then") - space()
+        + statements()
+        + (keyword("else") * space() * statements()).opt()
+        - end_statement("end_if"))
+    .map(|((condition, statements), else_statements)| Statement::If {
+        condition,
+        statements,
+        else_statements: else_statements.unwrap_or_default(),
+    })
+}
+
+fn repeat_statement<'a>() -> Parser<'a, u8, Statement> {
+    let increment =
+        (variable() - space() - seq(b":=") - space() + call(expression) - space() - keyword("to") - space()
+            + call(expression)
+            - space()
+            + (keyword("by") * space() * call(expression) - space()).opt())
+        .map(|(((variable, start), end), step)| Increment {
+            variable,
+            start,
+            end,
+            step,
         });
-    let formal_parameters = sym(b'(') * space() * list(formal_parameter - space(), sym(b';') - space()) - sym(b')');
-    let head = keyword("function") * space() * identifier().map(str::to_string) - space() + formal_parameters.opt()
+    let while_control = keyword("while") * space() * call(expression) - space();
+    let until_control = keyword("until") * space() * call(expression) - space();
+    (keyword("repeat") * space() * increment.opt() + while_control.opt() + until_control.opt() - sym(b';') - space()
+        + statements()
+        - end_statement("end_repeat"))
+    .map(
+        |(((increment, while_condition), until_condition), statements)| Statement::Repeat {
+            increment: increment.map(Box::new),
+            while_condition: while_condition.map(Box::new),
+            until_condition: until_condition.map(Box::new),
+            statements,
+        },
+    )
+}
+
+fn return_statement<'a>() -> Parser<'a, u8, Statement> {
+    (keyword("return") * space() * call(expression).opt() - space() - sym(b';') - space()).map(Statement::Return)
+}
+
+fn procedure_call_statement<'a>() -> Parser<'a, u8, Statement> {
+    let parameters = sym(b'(') * space() * list(call(expression) - space(), sym(b',') - space()) - sym(b')');
+    (variable() - space() + parameters.opt() - space() - sym(b';') - space()).map(|(name, parameters)| {
+        Statement::ProcedureCall {
+            name,
+            parameters: parameters.unwrap_or_default(),
+        }
+    })
+}
+
+fn local_declaration<'a>() -> Parser<'a, u8, Vec<Local>> {
+    let local = (list(variable() - space(), sym(b',') - space()) - sym(b':') - space() + parameter_type() - space()
+        + (seq(b":=") * space() * call(expression) - space()).opt()
+        - sym(b';')
+        - space())
+    .map(|((names, data_type), expr)| {
+        names
+            .into_iter()
+            .map(|name| Local {
+                name,
+                data_type: data_type.clone(),
+                expr: expr.clone(),
+            })
+            .collect::<Vec<_>>()
+    });
+    (keyword("local") * space() * local.repeat(0..) - end_statement("end_local"))
+        .map(|locals| locals.into_iter().flatten().collect())
+}
+
+/// The declarations, constants and local variables of a function, procedure or rule.
+type AlgorithmHead = (Vec<Declaration>, Vec<Constant>, Vec<Local>);
+
+fn algorithm_head<'a>() -> Parser<'a, u8, AlgorithmHead> {
+    // look ahead before building the declaration parser, which is costly
+    let declaration_start =
+        keyword("type") | keyword("entity") | keyword("function") | keyword("procedure") | keyword("rule");
+    ((!!declaration_start * call(declaration)).repeat(0..) + constants().opt() + local_declaration().opt()).map(
+        |((declarations, constants), locals)| (declarations, constants.unwrap_or_default(), locals.unwrap_or_default()),
+    )
+}
+
+fn formal_parameters<'a>() -> Parser<'a, u8, Vec<Parameter>> {
+    let formal_parameter = (keyword("var").map(|_| true) - space()).opt()
+        + list(variable() - space(), sym(b',') - space())
+        - sym(b':')
+        - space()
+        + parameter_type();
+    let formal_parameter = formal_parameter.map(|((var, names), data_type)| {
+        names
+            .into_iter()
+            .map(|name| Parameter {
+                name,
+                data_type: data_type.clone(),
+                var: var.is_some(),
+            })
+            .collect::<Vec<_>>()
+    });
+    (sym(b'(') * space() * list(formal_parameter - space(), sym(b';') - space()) - sym(b')'))
+        .map(|parameters| parameters.into_iter().flatten().collect())
+}
+
+fn function<'a>() -> Parser<'a, u8, Function> {
+    let head = keyword("function") * space() * identifier().map(str::to_string) - space() + formal_parameters().opt()
         - space()
         - sym(b':')
         - space()
@@ -588,15 +814,36 @@ fn function<'a>() -> Parser<'a, u8, Function> {
         - space()
         - sym(b';')
         - space();
-    let body = call(declaration).repeat(0..) - constants().opt() + statement().repeat(0..);
-    let tail = keyword("end_function") - space() - sym(b';') - space();
+    let body = algorithm_head() + statements();
+    let tail = end_statement("end_function");
     (head + body - tail).map(
-        |(((name, parameters), return_type), (declarations, statements))| Function {
+        |(((name, parameters), return_type), ((declarations, constants, locals), statements))| Function {
             name,
             return_type,
-            parameters: parameters.unwrap_or(Vec::new()).into_iter().flatten().collect(),
+            parameters: parameters.unwrap_or_default(),
+            statements,
+            declarations,
+            constants,
+            locals,
+        },
+    )
+}
+
+fn procedure<'a>() -> Parser<'a, u8, Procedure> {
+    let head = keyword("procedure") * space() * identifier().map(str::to_string) - space() + formal_parameters().opt()
+        - space()
+        - sym(b';')
+        - space();
+    let body = algorithm_head() + statements();
+    let tail = end_statement("end_procedure");
+    (head + body - tail).map(
+        |((name, parameters), ((declarations, constants, locals), statements))| Procedure {
+            name,
+            parameters: parameters.unwrap_or_default(),
             statements,
             declarations,
+            constants,
+            locals,
         },
     )
 }
@@ -613,28 +860,64 @@ fn rule<'a>() -> Parser<'a, u8, Rule> {
         - space()
         - sym(b';')
         - space();
-    let body = call(declaration).repeat(0..) - constants().opt() + statement().repeat(0..) + where_clause();
-    let tail = keyword("end_rule") - space() - sym(b';') - space();
-    (head + body - tail).map(|((name, entities), ((declarations, statements), domain_rules))| Rule {
-        name,
-        entities,
-        statements,
-        declarations,
-        domain_rules,
-    })
+    let body = algorithm_head() + statements() + where_clause();
+    let tail = end_statement("end_rule");
+    (head + body - tail).map(
+        |((name, entities), (((declarations, constants, locals), statements), domain_rules))| Rule {
+            name,
+            entities,
+            statements,
+            declarations,
+            domain_rules,
+            constants,
+            locals,
+        },
+    )
 }
 
 fn declaration<'a>() -> Parser<'a, u8, Declaration> {
-    named_type() | entity() | function().map(Declaration::Function) | rule().map(Declaration::Rule)
+    named_type()
+        | entity()
+        | function().map(Declaration::Function)
+        | procedure().map(Declaration::Procedure)
+        | rule().map(Declaration::Rule)
+}
+
+fn interface<'a>() -> Parser<'a, u8, Interface> {
+    let kind = keyword("use").map(|_| InterfaceKind::Use) | keyword("reference").map(|_| InterfaceKind::Reference);
+    let item =
+        identifier().map(str::to_string) + (space() * keyword("as") * space() * identifier().map(str::to_string)).opt();
+    let items = sym(b'(') * space() * list(item - space(), sym(b',') * space()) - sym(b')') - space();
+    (kind - space() - keyword("from") - space() + identifier().map(str::to_string) - space() + items.opt()
+        - sym(b';')
+        - space())
+    .map(|((kind, schema), items)| Interface {
+        kind,
+        schema,
+        items: items
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(name, alias)| InterfaceItem { name, alias })
+            .collect(),
+    })
 }
 
 pub fn schema<'a>() -> Parser<'a, u8, Schema> {
-    let head = space() * keyword("schema") * space() * identifier().map(str::to_string) - space() - sym(b';') - space();
-    let body = constants().opt() + declaration().repeat(1..);
+    let version = string() - space();
+    let head = space() * keyword("schema") * space() * identifier().map(str::to_string) - space() - version.opt()
+        + sym(b';')
+        - space();
+    let body = interface().repeat(0..) + constants().opt() + declaration().repeat(0..);
     let tail = keyword("end_schema") - space() - sym(b';') - space();
-    (head + body - tail).map(|(name, (constants, declarations))| Schema {
+    (head + body - tail).map(|((name, _), ((interfaces, constants), declarations))| Schema {
         name,
+        interfaces,
         constants: constants.unwrap_or(Vec::new()),
         declarations,
     })
 }
+
+/// All schemas of an EXPRESS file.
+pub fn schemas<'a>() -> Parser<'a, u8, Vec<Schema>> {
+    schema().repeat(1..) - end()
+}
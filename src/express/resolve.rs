@@ -0,0 +1,403 @@
+use super::{Attribute, Constant, DataType, Declaration, Entity, Function, Local, Parameter, Schema, TypeDef};
+use std::collections::HashMap;
+use std::fmt;
+
+/// The index of an entity in a `ResolvedSchema`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EntityId(usize);
+
+/// The index of a defined type in a `ResolvedSchema`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TypeDefId(usize);
+
+/// The index of a function in a `ResolvedSchema`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FunctionId(usize);
+
+/// A declaration which data types can refer to by name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NamedType {
+    Entity(EntityId),
+    Type(TypeDefId),
+}
+
+/// An explicit attribute in the instances of an entity, with the entity declaring it first.
+#[derive(Debug, Clone, Copy)]
+pub struct AttributeSlot<'a> {
+    pub entity: &'a Entity,
+    /// The attribute as redeclared last, for its data type.
+    pub attribute: &'a Attribute,
+}
+
+/// A problem found resolving the names of a schema.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Diagnostic {
+    /// A name used in a declaration which the schema does not declare.
+    UnknownName { declaration: String, name: String },
+    /// A name declared more than once, the first declaration is used.
+    DuplicateDeclaration(String),
+    /// Entities which are supertypes of themselves, in order along the cycle.
+    SupertypeCycle(Vec<String>),
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Diagnostic::UnknownName { declaration, name } => {
+                write!(f, "{} used in {} is not declared", name, declaration)
+            }
+            Diagnostic::DuplicateDeclaration(name) => write!(f, "{} is declared more than once", name),
+            Diagnostic::SupertypeCycle(names) => write!(f, "supertype cycle {} -> {}", names.join(" -> "), names[0]),
+        }
+    }
+}
+
+pub struct ResolvedEntity<'a> {
+    pub entity: &'a Entity,
+    /// The direct supertypes.
+    pub supertypes: Vec<EntityId>,
+    /// The direct subtypes.
+    pub subtypes: Vec<EntityId>,
+    /// The entity and all its supertypes, supertypes first.
+    pub ancestors: Vec<EntityId>,
+    /// The explicit attributes in the order of its simple instances, a redeclaration keeps the position of the
+    /// inherited attribute.
+    pub attributes: Vec<AttributeSlot<'a>>,
+}
+
+pub struct ResolvedType<'a> {
+    pub type_def: &'a TypeDef,
+    /// The types a select type lists, empty for other types.
+    pub select: Vec<NamedType>,
+}
+
+/// A schema with the names in its declarations resolved, and the problems found doing so.
+///
+/// Names are resolved case insensitively, unknown names are left out of the resolved relations.
+pub struct ResolvedSchema<'a> {
+    schema: &'a Schema,
+    entities: Vec<ResolvedEntity<'a>>,
+    types: Vec<ResolvedType<'a>>,
+    functions: Vec<&'a Function>,
+    /// Entities, types and functions by lower case name.
+    names: HashMap<String, Named>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+#[derive(Clone, Copy)]
+enum Named {
+    Entity(EntityId),
+    Type(TypeDefId),
+    Function(FunctionId),
+    /// A procedure or rule, which no other declaration refers to by name.
+    Other,
+}
+
+impl<'a> ResolvedSchema<'a> {
+    pub fn new(schema: &'a Schema) -> Self {
+        let mut resolved = ResolvedSchema {
+            schema,
+            entities: Vec::new(),
+            types: Vec::new(),
+            functions: Vec::new(),
+            names: HashMap::new(),
+            diagnostics: Vec::new(),
+        };
+        for declaration in &schema.declarations {
+            let key = declaration.name().to_lowercase();
+            if resolved.names.contains_key(&key) {
+                let name = declaration.name().to_string();
+                resolved.diagnostics.push(Diagnostic::DuplicateDeclaration(name));
+                continue;
+            }
+            let named = match declaration {
+                Declaration::Entity(entity) => {
+                    resolved.entities.push(ResolvedEntity {
+                        entity,
+                        supertypes: Vec::new(),
+                        subtypes: Vec::new(),
+                        ancestors: Vec::new(),
+                        attributes: Vec::new(),
+                    });
+                    Named::Entity(EntityId(resolved.entities.len() - 1))
+                }
+                Declaration::TypeDef(type_def) => {
+                    resolved.types.push(ResolvedType {
+                        type_def,
+                        select: Vec::new(),
+                    });
+                    Named::Type(TypeDefId(resolved.types.len() - 1))
+                }
+                Declaration::Function(function) => {
+                    resolved.functions.push(function);
+                    Named::Function(FunctionId(resolved.functions.len() - 1))
+                }
+                Declaration::Procedure(_) | Declaration::Rule(_) => Named::Other,
+            };
+            resolved.names.insert(key, named);
+        }
+        resolved.resolve_supertypes();
+        resolved.resolve_attributes();
+        resolved.resolve_types();
+        resolved
+    }
+
+    pub fn schema(&self) -> &'a Schema {
+        self.schema
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    pub fn entities(&self) -> impl Iterator<Item = (EntityId, &ResolvedEntity<'a>)> {
+        self.entities
+            .iter()
+            .enumerate()
+            .map(|(index, entity)| (EntityId(index), entity))
+    }
+
+    pub fn types(&self) -> impl Iterator<Item = (TypeDefId, &ResolvedType<'a>)> {
+        self.types
+            .iter()
+            .enumerate()
+            .map(|(index, type_def)| (TypeDefId(index), type_def))
+    }
+
+    pub fn entity(&self, id: EntityId) -> &ResolvedEntity<'a> {
+        &self.entities[id.0]
+    }
+
+    pub fn type_def(&self, id: TypeDefId) -> &ResolvedType<'a> {
+        &self.types[id.0]
+    }
+
+    pub fn function(&self, id: FunctionId) -> &'a Function {
+        self.functions[id.0]
+    }
+
+    pub fn entity_id(&self, name: &str) -> Option<EntityId> {
+        match self.names.get(&name.to_lowercase()) {
+            Some(Named::Entity(id)) => Some(*id),
+            _ => None,
+        }
+    }
+
+    pub fn type_id(&self, name: &str) -> Option<TypeDefId> {
+        match self.names.get(&name.to_lowercase()) {
+            Some(Named::Type(id)) => Some(*id),
+            _ => None,
+        }
+    }
+
+    pub fn function_id(&self, name: &str) -> Option<FunctionId> {
+        match self.names.get(&name.to_lowercase()) {
+            Some(Named::Function(id)) => Some(*id),
+            _ => None,
+        }
+    }
+
+    /// The entity or defined type of a name.
+    pub fn named_type(&self, name: &str) -> Option<NamedType> {
+        match self.names.get(&name.to_lowercase()) {
+            Some(Named::Entity(id)) => Some(NamedType::Entity(*id)),
+            Some(Named::Type(id)) => Some(NamedType::Type(*id)),
+            _ => None,
+        }
+    }
+
+    /// Whether the entity is `supertype` or one of its subtypes.
+    pub fn is_subtype_of(&self, entity: EntityId, supertype: EntityId) -> bool {
+        self.entity(entity).ancestors.contains(&supertype)
+    }
+
+    fn unknown(&mut self, declaration: &str, name: &str) {
+        self.diagnostics.push(Diagnostic::UnknownName {
+            declaration: declaration.to_string(),
+            name: name.to_string(),
+        });
+    }
+
+    fn resolve_supertypes(&mut self) {
+        for index in 0..self.entities.len() {
+            let entity = self.entities[index].entity;
+            for name in &entity.supertypes {
+                match self.entity_id(name) {
+                    Some(supertype) => {
+                        self.entities[index].supertypes.push(supertype);
+                        self.entities[supertype.0].subtypes.push(EntityId(index));
+                    }
+                    None => self.unknown(&entity.name, name),
+                }
+            }
+        }
+        // each cycle is reported once, from the entity it is entered at
+        let mut finished = vec![false; self.entities.len()];
+        for index in 0..self.entities.len() {
+            let mut path = Vec::new();
+            self.find_cycles(EntityId(index), &mut path, &mut finished);
+        }
+        for index in 0..self.entities.len() {
+            let mut ancestors = Vec::new();
+            self.collect_ancestors(EntityId(index), &mut Vec::new(), &mut ancestors);
+            self.entities[index].ancestors = ancestors;
+        }
+    }
+
+    fn find_cycles(&mut self, id: EntityId, path: &mut Vec<EntityId>, finished: &mut [bool]) {
+        if finished[id.0] {
+            return;
+        }
+        if let Some(start) = path.iter().position(|known| *known == id) {
+            let names = path[start..]
+                .iter()
+                .map(|id| self.entity(*id).entity.name.clone())
+                .collect();
+            self.diagnostics.push(Diagnostic::SupertypeCycle(names));
+            return;
+        }
+        path.push(id);
+        for supertype in self.entity(id).supertypes.clone() {
+            self.find_cycles(supertype, path, finished);
+        }
+        path.pop();
+        finished[id.0] = true;
+    }
+
+    fn collect_ancestors(&self, id: EntityId, path: &mut Vec<EntityId>, ancestors: &mut Vec<EntityId>) {
+        if path.contains(&id) {
+            return;
+        }
+        path.push(id);
+        for supertype in &self.entity(id).supertypes {
+            self.collect_ancestors(*supertype, path, ancestors);
+        }
+        path.pop();
+        if !ancestors.contains(&id) {
+            ancestors.push(id);
+        }
+    }
+
+    fn resolve_attributes(&mut self) {
+        for index in 0..self.entities.len() {
+            let mut slots: Vec<AttributeSlot<'a>> = Vec::new();
+            for ancestor in self.entities[index].ancestors.clone() {
+                let entity = self.entity(ancestor).entity;
+                for attribute in &entity.attributes {
+                    let inherited = slots
+                        .iter_mut()
+                        .find(|slot| slot.attribute.name.eq_ignore_ascii_case(&attribute.name));
+                    match (inherited, &attribute.supertype) {
+                        // a redeclaration keeps the position of the inherited attribute
+                        (Some(slot), Some(_)) => slot.attribute = attribute,
+                        (None, Some(supertype)) if ancestor.0 == index => {
+                            let name = format!("{}.{}", supertype, attribute.name);
+                            self.unknown(&entity.name, &name);
+                            slots.push(AttributeSlot { entity, attribute });
+                        }
+                        _ => slots.push(AttributeSlot { entity, attribute }),
+                    }
+                }
+            }
+            self.entities[index].attributes = slots;
+        }
+    }
+
+    /// Resolve the members of select types and check the names of the data types in all declarations.
+    fn resolve_types(&mut self) {
+        for index in 0..self.types.len() {
+            let type_def = self.types[index].type_def;
+            if let DataType::Select { types } = &type_def.underlying_type {
+                for name in types {
+                    match self.named_type(name) {
+                        Some(member) => self.types[index].select.push(member),
+                        None => self.unknown(&type_def.name, name),
+                    }
+                }
+            } else {
+                self.check_data_type(&type_def.name, &type_def.underlying_type, &[]);
+            }
+        }
+        let schema = self.schema;
+        for constant in &schema.constants {
+            self.check_data_type(&constant.name, &constant.data_type, &[]);
+        }
+        for declaration in &schema.declarations {
+            match declaration {
+                Declaration::Entity(entity) => {
+                    for attribute in &entity.attributes {
+                        self.check_data_type(&entity.name, &attribute.data_type, &[]);
+                    }
+                    for derive in &entity.derives {
+                        self.check_data_type(&entity.name, &derive.data_type, &[]);
+                    }
+                    for inverse in &entity.inverses {
+                        self.check_data_type(&entity.name, &inverse.data_type, &[]);
+                    }
+                }
+                Declaration::Function(function) => {
+                    self.check_data_type(&function.name, &function.return_type, &function.declarations);
+                    self.check_algorithm(
+                        &function.name,
+                        &function.parameters,
+                        &function.declarations,
+                        &function.constants,
+                        &function.locals,
+                    );
+                }
+                Declaration::Procedure(procedure) => self.check_algorithm(
+                    &procedure.name,
+                    &procedure.parameters,
+                    &procedure.declarations,
+                    &procedure.constants,
+                    &procedure.locals,
+                ),
+                Declaration::Rule(rule) => {
+                    for entity in &rule.entities {
+                        if self.entity_id(entity).is_none() {
+                            self.unknown(&rule.name, entity);
+                        }
+                    }
+                    self.check_algorithm(&rule.name, &[], &rule.declarations, &rule.constants, &rule.locals);
+                }
+                Declaration::TypeDef(_) => {}
+            }
+        }
+    }
+
+    fn check_algorithm(
+        &mut self,
+        name: &str,
+        parameters: &[Parameter],
+        declarations: &[Declaration],
+        constants: &[Constant],
+        locals: &[Local],
+    ) {
+        let data_types = parameters
+            .iter()
+            .map(|parameter| &parameter.data_type)
+            .chain(constants.iter().map(|constant| &constant.data_type))
+            .chain(locals.iter().map(|local| &local.data_type));
+        for data_type in data_types {
+            self.check_data_type(name, data_type, declarations);
+        }
+    }
+
+    /// Report the names in a data type which are neither declared in the schema nor among the local declarations.
+    fn check_data_type(&mut self, declaration: &str, data_type: &DataType, local: &[Declaration]) {
+        match data_type {
+            DataType::TypeRef { name } => {
+                let is_local = local.iter().any(|local| local.name().eq_ignore_ascii_case(name));
+                if self.named_type(name).is_none() && !is_local {
+                    self.unknown(declaration, name);
+                }
+            }
+            DataType::Array { base_type, .. }
+            | DataType::List { base_type, .. }
+            | DataType::Bag { base_type, .. }
+            | DataType::Set { base_type, .. }
+            | DataType::Aggregate { base_type, .. } => self.check_data_type(declaration, base_type, local),
+            _ => {}
+        }
+    }
+}
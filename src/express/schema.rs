@@ -3,6 +3,35 @@ use super::{Constant, Declaration};
 #[derive(Debug)]
 pub struct Schema {
     pub name: String,
+    /// The `USE FROM` and `REFERENCE FROM` specifications, empty in a linked schema.
+    pub interfaces: Vec<Interface>,
     pub constants: Vec<Constant>,
     pub declarations: Vec<Declaration>,
 }
+
+/// A `USE FROM` or `REFERENCE FROM` interface specification.
+#[derive(Debug, Clone)]
+pub struct Interface {
+    pub kind: InterfaceKind,
+    pub schema: String,
+    /// The items taken from the schema, all of them when empty.
+    pub items: Vec<InterfaceItem>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterfaceKind {
+    /// `USE FROM`, entities become instantiable in the interfacing schema.
+    Use,
+    /// `REFERENCE FROM`, items may be referred to.
+    ///
+    /// ISO 10303-11 allows instances of referenced entities only as attribute values, `link` does not enforce this
+    /// and links them like used ones.
+    Reference,
+}
+
+/// An item of an interface specification, `name AS alias`.
+#[derive(Debug, Clone)]
+pub struct InterfaceItem {
+    pub name: String,
+    pub alias: Option<String>,
+}
@@ -0,0 +1,61 @@
+use super::{Accessor, Expression};
+
+#[derive(Debug, Clone)]
+pub enum Statement {
+    /// `ALIAS variable FOR target; ... END_ALIAS;`
+    Alias {
+        variable: String,
+        target: String,
+        accessors: Vec<Accessor>,
+        statements: Vec<Statement>,
+    },
+    /// `target := value;`, the target is a variable, optionally followed by attribute and index accessors.
+    Assignment {
+        target: String,
+        accessors: Vec<Accessor>,
+        value: Expression,
+    },
+    Case {
+        selector: Expression,
+        actions: Vec<CaseAction>,
+        otherwise: Option<Box<Statement>>,
+    },
+    /// `BEGIN ... END;`
+    Compound(Vec<Statement>),
+    Escape,
+    If {
+        condition: Expression,
+        statements: Vec<Statement>,
+        else_statements: Vec<Statement>,
+    },
+    /// `;`
+    Null,
+    /// A call of a schema procedure or of the built-in procedures `INSERT` and `REMOVE`.
+    ProcedureCall {
+        name: String,
+        parameters: Vec<Expression>,
+    },
+    Repeat {
+        increment: Option<Box<Increment>>,
+        while_condition: Option<Box<Expression>>,
+        until_condition: Option<Box<Expression>>,
+        statements: Vec<Statement>,
+    },
+    Return(Option<Expression>),
+    Skip,
+}
+
+#[derive(Debug, Clone)]
+pub struct CaseAction {
+    pub labels: Vec<Expression>,
+    pub statement: Statement,
+}
+
+/// `variable := start TO end BY step` of a REPEAT statement.
+#[derive(Debug, Clone)]
+pub struct Increment {
+    pub variable: String,
+    pub start: Expression,
+    pub end: Expression,
+    pub step: Option<Expression>,
+}
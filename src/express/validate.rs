@@ -0,0 +1,308 @@
+use super::{DataType, Declaration, DomainRule, Entity, EvalError, Evaluator, Model, Rule, UniqueRule, Value};
+use std::collections::HashMap;
+use std::fmt;
+
+/// A rule which an instance does not satisfy or which could not be evaluated for it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleViolation {
+    pub id: i64,
+    /// The entity or defined type declaring the rule.
+    pub declaration: String,
+    /// The attribute holding the value of a defined type, `None` for a rule of the entity.
+    pub attribute: Option<String>,
+    /// The rule label, `None` for an unlabelled rule.
+    pub label: Option<String>,
+    /// Why the rule could not be evaluated, `None` when it evaluated to FALSE.
+    pub error: Option<EvalError>,
+}
+
+/// Instances of an entity sharing the values of the attributes of a UNIQUE rule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UniqueViolation {
+    /// The entity declaring the rule.
+    pub entity: String,
+    pub label: Option<String>,
+    /// The instances with the same values, in ascending order.
+    pub ids: Vec<i64>,
+}
+
+/// A global rule which the population does not satisfy or which could not be evaluated.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GlobalRuleViolation {
+    pub rule: String,
+    /// The label of the WHERE rule, `None` for an unlabelled one or when the body of the rule failed.
+    pub label: Option<String>,
+    /// The instances of the entities the rule is for, in ascending order.
+    pub ids: Vec<i64>,
+    /// Why the rule could not be evaluated, `None` when it evaluated to FALSE.
+    pub error: Option<EvalError>,
+}
+
+/// The outcome of checking the rules of the instances of a `Model`.
+#[derive(Debug, Default)]
+pub struct ValidationReport {
+    /// The number of rules evaluated, once per instance or value checked.
+    pub checked: usize,
+    pub failed: Vec<RuleViolation>,
+    /// Rules which could not be evaluated, such as rules assigning to an attribute of an instance.
+    pub indeterminate: Vec<RuleViolation>,
+    pub duplicates: Vec<UniqueViolation>,
+    /// Global rules which are violated or could not be evaluated.
+    pub global_rules: Vec<GlobalRuleViolation>,
+}
+
+impl ValidationReport {
+    /// Whether all rules evaluated to TRUE or UNKNOWN and all UNIQUE rules hold.
+    pub fn is_valid(&self) -> bool {
+        self.failed.is_empty()
+            && self.indeterminate.is_empty()
+            && self.duplicates.is_empty()
+            && self.global_rules.is_empty()
+    }
+
+    fn check(&mut self, evaluator: &mut Evaluator, rule: &DomainRule, violation: impl FnOnce() -> RuleViolation) {
+        self.checked += 1;
+        // a WHERE rule is violated only when it is FALSE
+        match evaluator.evaluate_logical(&rule.expr) {
+            Ok(Some(true)) | Ok(None) => {}
+            Ok(Some(false)) => self.failed.push(violation()),
+            Err(error) => self.indeterminate.push(RuleViolation {
+                error: Some(error),
+                ..violation()
+            }),
+        }
+    }
+}
+
+impl fmt::Display for RuleViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{}: {}", self.id, self.declaration)?;
+        if let Some(label) = &self.label {
+            write!(f, ".{}", label)?;
+        }
+        if let Some(attribute) = &self.attribute {
+            write!(f, " of attribute {}", attribute)?;
+        }
+        match &self.error {
+            Some(error) => write!(f, " can not be evaluated: {}", error),
+            None => write!(f, " is violated"),
+        }
+    }
+}
+
+impl fmt::Display for UniqueViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.entity)?;
+        if let Some(label) = &self.label {
+            write!(f, ".{}", label)?;
+        }
+        let ids = self.ids.iter().map(|id| format!("#{}", id)).collect::<Vec<_>>();
+        write!(f, " is violated by {}", ids.join(", "))
+    }
+}
+
+impl fmt::Display for GlobalRuleViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "RULE {}", self.rule)?;
+        if let Some(label) = &self.label {
+            write!(f, ".{}", label)?;
+        }
+        match &self.error {
+            Some(error) => write!(f, " can not be evaluated: {}", error)?,
+            None => write!(f, " is violated")?,
+        }
+        let ids = self.ids.iter().map(|id| format!("#{}", id)).collect::<Vec<_>>();
+        write!(f, " for {}", ids.join(", "))
+    }
+}
+
+impl<'a> Model<'a> {
+    /// Check every instance against the WHERE rules of its entities and of the defined types of its attribute values,
+    /// and the population against the UNIQUE rules of the entities and the global rules of the schema.
+    pub fn validate(&self) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        for (id, parts) in self.instances() {
+            self.validate_instance(id, &mut report);
+            let mut values = Vec::new();
+            self.visit_slots(parts, |slot, parameter| {
+                values.push((slot, self.value(parameter, Some(&slot.attribute.data_type))));
+            });
+            for (slot, value) in values {
+                self.validate_value(id, &slot.attribute.name, &value, &mut report);
+            }
+        }
+        for entity in self
+            .schema()
+            .declarations
+            .iter()
+            .filter_map(|declaration| match declaration {
+                Declaration::Entity(entity) => Some(entity),
+                _ => None,
+            })
+        {
+            for rule in &entity.unique_rules {
+                self.validate_unique(entity, rule, &mut report);
+            }
+        }
+        for declaration in &self.schema().declarations {
+            if let Declaration::Rule(rule) = declaration {
+                self.validate_rule(rule, &mut report);
+            }
+        }
+        report
+    }
+
+    /// Check that no two instances of an entity share the values of the attributes of a UNIQUE rule.
+    ///
+    /// Instances with an indeterminate value for one of the attributes are not compared.
+    pub fn validate_unique(&self, entity: &Entity, rule: &UniqueRule, report: &mut ValidationReport) {
+        let mut instances: HashMap<String, Vec<i64>> = HashMap::new();
+        for id in self.instances_of(&entity.name) {
+            report.checked += 1;
+            let mut values = Vec::with_capacity(rule.attributes.len());
+            for attribute in &rule.attributes {
+                let group = attribute.entity.as_deref();
+                match self.attribute(id, group, &attribute.name) {
+                    Ok(value) => {
+                        let data_type = self.attribute_type(id, group, &attribute.name);
+                        values.push(self.unique_key(&value, data_type));
+                    }
+                    Err(error) => {
+                        report.indeterminate.push(RuleViolation {
+                            id,
+                            declaration: entity.name.clone(),
+                            attribute: Some(attribute.name.clone()),
+                            label: rule.label.clone(),
+                            error: Some(error),
+                        });
+                        break;
+                    }
+                }
+            }
+            if values.len() < rule.attributes.len() || values.iter().any(Value::is_indeterminate) {
+                continue;
+            }
+            // equal values have the same key, so they print the same
+            instances.entry(format!("{:?}", values)).or_default().push(id);
+        }
+        let mut duplicates = instances
+            .into_iter()
+            .filter(|(_, ids)| ids.len() > 1)
+            .map(|(_, ids)| UniqueViolation {
+                entity: entity.name.clone(),
+                label: rule.label.clone(),
+                ids,
+            })
+            .collect::<Vec<_>>();
+        duplicates.sort_by_key(|duplicate| duplicate.ids[0]);
+        report.duplicates.extend(duplicates);
+    }
+
+    /// The value with the same form for all values EXPRESS considers equal.
+    ///
+    /// Defined types are unwrapped, integral REAL values become INTEGER values and the elements of a SET or BAG
+    /// are sorted.
+    fn unique_key(&self, value: &Value, data_type: Option<&'a DataType>) -> Value {
+        let data_type = data_type.map(|data_type| self.underlying_type(data_type));
+        match value.plain() {
+            Value::Real(real) if real.fract() == 0.0 && real.abs() < i64::MAX as f64 => Value::Integer(*real as i64),
+            Value::Aggregate(values) => {
+                let (base_type, unordered) = match data_type {
+                    Some(DataType::Set { base_type, .. }) | Some(DataType::Bag { base_type, .. }) => {
+                        (Some(&**base_type), true)
+                    }
+                    Some(DataType::List { base_type, .. }) | Some(DataType::Array { base_type, .. }) => {
+                        (Some(&**base_type), false)
+                    }
+                    _ => (None, false),
+                };
+                let mut keys = values
+                    .iter()
+                    .map(|value| self.unique_key(value, base_type))
+                    .collect::<Vec<_>>();
+                if unordered {
+                    keys.sort_by_cached_key(|key| format!("{:?}", key));
+                }
+                Value::Aggregate(keys)
+            }
+            value => value.clone(),
+        }
+    }
+
+    /// Check the instances of the entities a global rule is for against the WHERE rules of the rule.
+    pub fn validate_rule(&self, rule: &Rule, report: &mut ValidationReport) {
+        let mut ids = rule
+            .entities
+            .iter()
+            .flat_map(|entity| self.instances_of(entity))
+            .collect::<Vec<_>>();
+        ids.sort_unstable();
+        ids.dedup();
+        let violation = |label: Option<&String>, error| GlobalRuleViolation {
+            rule: rule.name.clone(),
+            label: label.cloned(),
+            ids: ids.clone(),
+            error,
+        };
+        let mut evaluator = Evaluator::new(self);
+        if let Err(error) = evaluator.execute_rule(rule) {
+            report.checked += rule.domain_rules.len();
+            report.global_rules.push(violation(None, Some(error)));
+            return;
+        }
+        for domain_rule in &rule.domain_rules {
+            report.checked += 1;
+            match evaluator.evaluate_logical(&domain_rule.expr) {
+                Ok(Some(true)) | Ok(None) => {}
+                Ok(Some(false)) => report.global_rules.push(violation(domain_rule.label.as_ref(), None)),
+                Err(error) => report
+                    .global_rules
+                    .push(violation(domain_rule.label.as_ref(), Some(error))),
+            }
+        }
+    }
+
+    /// Check an instance against the WHERE rules of its entities.
+    pub fn validate_instance(&self, id: i64, report: &mut ValidationReport) {
+        let mut evaluator = Evaluator::with_self(self, id);
+        for entity in self.instance_supertypes(id) {
+            for rule in &entity.domain_rules {
+                report.check(&mut evaluator, rule, || RuleViolation {
+                    id,
+                    declaration: entity.name.clone(),
+                    attribute: None,
+                    label: rule.label.clone(),
+                    error: None,
+                });
+            }
+        }
+    }
+
+    /// Check the value of an attribute against the WHERE rules of the defined types it is of.
+    fn validate_value(&self, id: i64, attribute: &str, value: &Value, report: &mut ValidationReport) {
+        match value {
+            Value::Typed(type_name, inner) => {
+                if let Some(type_def) = self.type_def(type_name) {
+                    let mut evaluator = Evaluator::new(self);
+                    evaluator.bind("self", value.clone());
+                    for rule in &type_def.domain_rules {
+                        report.check(&mut evaluator, rule, || RuleViolation {
+                            id,
+                            declaration: type_def.name.clone(),
+                            attribute: Some(attribute.to_string()),
+                            label: rule.label.clone(),
+                            error: None,
+                        });
+                    }
+                }
+                self.validate_value(id, attribute, inner, report);
+            }
+            Value::Aggregate(values) => {
+                for value in values {
+                    self.validate_value(id, attribute, value, report);
+                }
+            }
+            _ => {}
+        }
+    }
+}
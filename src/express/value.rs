@@ -0,0 +1,142 @@
+use crate::step::Binary;
+use std::cmp::Ordering;
+
+/// The value of an EXPRESS expression evaluated against a `Model`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// The indeterminate value `?`, also the value of an unset optional attribute.
+    Indeterminate,
+    Integer(i64),
+    Real(f64),
+    /// A BOOLEAN or LOGICAL value, `None` is UNKNOWN.
+    Logical(Option<bool>),
+    String(String),
+    Binary(Binary),
+    /// An enumeration item in lower case.
+    Enumeration(String),
+    /// The name of an entity instance of the model.
+    Entity(i64),
+    /// A value of a defined type, the type name is in lower case.
+    Typed(String, Box<Value>),
+    /// A LIST, ARRAY, SET or BAG, all indexed from 1.
+    Aggregate(Vec<Value>),
+    /// An entity value built by entity constructors, which is not an instance of the model.
+    ///
+    /// Each part is an entity name in lower case with the values of the explicit attributes the entity declares,
+    /// partial entity values are combined with `||`.
+    Constructed(Vec<(String, Vec<Value>)>),
+}
+
+impl Value {
+    /// The value without the defined types wrapped around it.
+    pub fn plain(&self) -> &Value {
+        match self {
+            Value::Typed(_, value) => value.plain(),
+            value => value,
+        }
+    }
+
+    pub fn plain_mut(&mut self) -> &mut Value {
+        match self {
+            Value::Typed(_, value) => value.plain_mut(),
+            value => value,
+        }
+    }
+
+    pub fn is_indeterminate(&self) -> bool {
+        matches!(self.plain(), Value::Indeterminate)
+    }
+
+    /// The value as LOGICAL, the indeterminate value is UNKNOWN.
+    pub fn as_logical(&self) -> Option<Option<bool>> {
+        match self.plain() {
+            Value::Logical(value) => Some(*value),
+            Value::Indeterminate => Some(None),
+            _ => None,
+        }
+    }
+
+    pub fn as_integer(&self) -> Option<i64> {
+        match self.plain() {
+            Value::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The value of a number as `f64`.
+    pub fn as_real(&self) -> Option<f64> {
+        match self.plain() {
+            Value::Integer(value) => Some(*value as f64),
+            Value::Real(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self.plain() {
+            Value::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_entity(&self) -> Option<i64> {
+        match self.plain() {
+            Value::Entity(id) => Some(*id),
+            _ => None,
+        }
+    }
+
+    pub fn as_aggregate(&self) -> Option<&[Value]> {
+        match self.plain() {
+            Value::Aggregate(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// Compare values of the same kind, numbers of both kinds compare with each other.
+    ///
+    /// `None` for values of different kinds and for different instances, which are not equal.
+    pub fn compare(&self, other: &Value) -> Option<Ordering> {
+        match (self.plain(), other.plain()) {
+            (Value::Integer(a), Value::Integer(b)) => Some(a.cmp(b)),
+            (a, b) if a.as_real().is_some() && b.as_real().is_some() => {
+                a.as_real().unwrap().partial_cmp(&b.as_real().unwrap())
+            }
+            (Value::Logical(a), Value::Logical(b)) => Some(logical_rank(*a).cmp(&logical_rank(*b))),
+            (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+            (Value::Binary(a), Value::Binary(b)) => Some(a.iter().cmp(b.iter())),
+            (Value::Enumeration(a), Value::Enumeration(b)) if a == b => Some(Ordering::Equal),
+            (Value::Entity(a), Value::Entity(b)) if a == b => Some(Ordering::Equal),
+            (Value::Constructed(a), Value::Constructed(b)) if a == b => Some(Ordering::Equal),
+            (Value::Aggregate(a), Value::Aggregate(b)) => {
+                if a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.compare(b) == Some(Ordering::Equal)) {
+                    Some(Ordering::Equal)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Logical(Some(value))
+    }
+}
+
+impl From<Option<bool>> for Value {
+    fn from(value: Option<bool>) -> Self {
+        Value::Logical(value)
+    }
+}
+
+/// FALSE < UNKNOWN < TRUE
+fn logical_rank(value: Option<bool>) -> u8 {
+    match value {
+        Some(false) => 0,
+        None => 1,
+        Some(true) => 2,
+    }
+}
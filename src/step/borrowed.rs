@@ -0,0 +1,571 @@
+//! Parameter values borrowed from the input instead of copied, for scanning large files without allocating per token.
+//!
+//! Type names, enumeration values and resources are slices of the input, strings and binaries are kept encoded
+//! until they are converted to the owned values of the `step` module.
+//!
+//! Type names are not interned: as slices of the input they cost no allocation already, and interning them would
+//! add a hash lookup to every instance. Callers counting instances by type can key a map by the slice.
+//!
+//! Statements are read from a `MappedFile`, from any input in memory with `Statements`, or combined with other
+//! parsers through `parser::borrowed_statement`.
+use super::parser::{anchor, decode_binary, decode_string, reference};
+use super::{Anchor, ConversionError, Reference, StepError};
+use memmap2::Mmap;
+use std::convert::{TryFrom, TryInto};
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Parameter<'a> {
+    TypedParameter(TypedParameter<'a>),
+    UnTypedParameter(UnTypedParameter<'a>),
+    OmittedParameter,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypedParameter<'a> {
+    pub type_name: &'a str,
+    pub parameters: Vec<Parameter<'a>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UnTypedParameter<'a> {
+    List(Vec<Parameter<'a>>),
+    EnumValue(&'a str),
+    EntityRef(i64),
+    ConstantRef(&'a str),
+    Integer(i64),
+    Real(f64),
+    String(RawString<'a>),
+    /// The hex digits of a BINARY, starting with the number of unused bits.
+    Binary(&'a str),
+    ValueRef(i64),
+    Resource(&'a str),
+    Null,
+}
+
+/// The characters of a STRING between its quotes, with quotes and control directives not decoded yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RawString<'a>(pub &'a [u8]);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntityInstance<'a> {
+    pub id: i64,
+    pub value: Vec<TypedParameter<'a>>,
+}
+
+/// A single statement of an exchange file, the rare anchors and references are owned.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement<'a> {
+    Start,
+    Header,
+    Anchor,
+    Reference,
+    Data(Vec<Parameter<'a>>),
+    EndSection,
+    End,
+    HeaderEntity(TypedParameter<'a>),
+    AnchorItem(Anchor),
+    ReferenceItem(Reference),
+    Instance(EntityInstance<'a>),
+}
+
+impl RawString<'_> {
+    pub fn decode(&self) -> Result<String, ConversionError> {
+        decode_string(self.0).map_err(|error| ConversionError::new("string", error))
+    }
+}
+
+impl<'a> TryFrom<Parameter<'a>> for super::Parameter {
+    type Error = ConversionError;
+
+    fn try_from(parameter: Parameter<'a>) -> Result<Self, Self::Error> {
+        Ok(match parameter {
+            Parameter::TypedParameter(typed_parameter) => super::Parameter::TypedParameter(typed_parameter.try_into()?),
+            Parameter::UnTypedParameter(parameter) => super::Parameter::UnTypedParameter(parameter.try_into()?),
+            Parameter::OmittedParameter => super::Parameter::OmittedParameter,
+        })
+    }
+}
+
+impl<'a> TryFrom<TypedParameter<'a>> for super::TypedParameter {
+    type Error = ConversionError;
+
+    fn try_from(typed_parameter: TypedParameter<'a>) -> Result<Self, Self::Error> {
+        Ok(super::TypedParameter {
+            type_name: typed_parameter.type_name.to_string(),
+            parameters: to_owned_parameters(typed_parameter.parameters)?,
+        })
+    }
+}
+
+impl<'a> TryFrom<UnTypedParameter<'a>> for super::UnTypedParameter {
+    type Error = ConversionError;
+
+    fn try_from(parameter: UnTypedParameter<'a>) -> Result<Self, Self::Error> {
+        use super::UnTypedParameter as Owned;
+        Ok(match parameter {
+            UnTypedParameter::List(parameters) => Owned::List(to_owned_parameters(parameters)?),
+            UnTypedParameter::EnumValue(value) => Owned::EnumValue(value.to_string()),
+            UnTypedParameter::EntityRef(id) => Owned::EntityRef(id),
+            UnTypedParameter::ConstantRef(name) => Owned::ConstantRef(name.to_string()),
+            UnTypedParameter::Integer(value) => Owned::Integer(value),
+            UnTypedParameter::Real(value) => Owned::Real(value),
+            UnTypedParameter::String(value) => Owned::String(value.decode()?),
+            UnTypedParameter::Binary(digits) => Owned::Binary(decode_binary(digits)),
+            UnTypedParameter::ValueRef(id) => Owned::ValueRef(id),
+            UnTypedParameter::Resource(uri) => Owned::Resource(uri.to_string()),
+            UnTypedParameter::Null => Owned::Null,
+        })
+    }
+}
+
+impl<'a> TryFrom<Statement<'a>> for super::parser::Statement {
+    type Error = ConversionError;
+
+    fn try_from(statement: Statement<'a>) -> Result<Self, Self::Error> {
+        use super::parser::Statement as Owned;
+        Ok(match statement {
+            Statement::Start => Owned::Start,
+            Statement::Header => Owned::Header,
+            Statement::Anchor => Owned::Anchor,
+            Statement::Reference => Owned::Reference,
+            Statement::Data(parameters) => Owned::Data(to_owned_parameters(parameters)?),
+            Statement::EndSection => Owned::EndSection,
+            Statement::End => Owned::End,
+            Statement::HeaderEntity(entity) => Owned::HeaderEntity(entity.try_into()?),
+            Statement::AnchorItem(anchor) => Owned::AnchorItem(anchor),
+            Statement::ReferenceItem(reference) => Owned::ReferenceItem(reference),
+            Statement::Instance(instance) => Owned::Instance(instance.try_into()?),
+        })
+    }
+}
+
+impl<'a> TryFrom<EntityInstance<'a>> for super::EntityInstance {
+    type Error = ConversionError;
+
+    fn try_from(instance: EntityInstance<'a>) -> Result<Self, Self::Error> {
+        Ok(super::EntityInstance {
+            id: instance.id,
+            value: instance
+                .value
+                .into_iter()
+                .map(TryInto::try_into)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+fn to_owned_parameters(parameters: Vec<Parameter>) -> Result<Vec<super::Parameter>, ConversionError> {
+    parameters.into_iter().map(TryInto::try_into).collect()
+}
+
+/// A STEP file mapped into memory, for parsing without reading it into a buffer first.
+pub struct MappedFile {
+    map: Mmap,
+}
+
+impl MappedFile {
+    /// Map a file into memory.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated while it is mapped, by this or any other process. The bytes and
+    /// the statements borrowed from them would change underneath, or reading them would fault, which is undefined
+    /// behaviour.
+    pub unsafe fn open<P: AsRef<Path>>(path: P) -> Result<Self, StepError> {
+        let file = std::fs::File::open(path)?;
+        let map = Mmap::map(&file)?;
+        Ok(MappedFile { map })
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.map
+    }
+
+    pub fn statements(&self) -> Statements<'_> {
+        Statements::new(&self.map)
+    }
+}
+
+/// Iterates the statements of an exchange file held in memory, with the position of each in the input.
+///
+/// A syntax error ends the iteration, so does `END-ISO-10303-21;` as signature sections may follow it.
+pub struct Statements<'a> {
+    input: &'a [u8],
+    position: usize,
+    finished: bool,
+}
+
+impl<'a> Statements<'a> {
+    pub fn new(input: &'a [u8]) -> Self {
+        Statements {
+            input,
+            position: 0,
+            finished: false,
+        }
+    }
+}
+
+impl<'a> Iterator for Statements<'a> {
+    type Item = Result<(usize, Statement<'a>), StepError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut cursor = Cursor {
+            input: self.input,
+            position: self.position,
+        };
+        cursor.space();
+        if self.finished || cursor.position == self.input.len() {
+            return None;
+        }
+        let start = cursor.position;
+        match cursor.statement() {
+            Ok(statement) => {
+                self.position = cursor.position;
+                self.finished = statement == Statement::End;
+                Some(Ok((start, statement)))
+            }
+            Err(error) => {
+                self.finished = true;
+                Some(Err(StepError::syntax(self.input, error)))
+            }
+        }
+    }
+}
+
+pub(super) fn parse_statement<'a>(input: &'a [u8], start: usize) -> pom::Result<(Statement<'a>, usize)> {
+    let mut cursor = Cursor { input, position: start };
+    cursor.space();
+    let statement = cursor.statement()?;
+    Ok((statement, cursor.position))
+}
+
+/// A hand written parser, as the parser combinators allocate an error message for each alternative which fails.
+struct Cursor<'a> {
+    input: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.position).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<u8> {
+        self.input.get(self.position + offset).copied()
+    }
+
+    fn starts_with(&self, text: &[u8]) -> bool {
+        self.input[self.position..].starts_with(text)
+    }
+
+    fn error<T>(&self, message: &str) -> pom::Result<T> {
+        if self.position == self.input.len() {
+            Err(pom::Error::Incomplete)
+        } else {
+            Err(pom::Error::Mismatch {
+                message: message.to_string(),
+                position: self.position,
+            })
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> pom::Result<()> {
+        if self.peek() == Some(byte) {
+            self.position += 1;
+            Ok(())
+        } else {
+            self.error(&format!("expected '{}'", byte as char))
+        }
+    }
+
+    fn space(&mut self) {
+        while let Some(byte) = self.peek() {
+            match byte {
+                b' ' | b'\t' | b'\n' | b'\r' | 0 => self.position += 1,
+                b'/' if self.peek_at(1) == Some(b'*') => {
+                    match self.input[self.position + 2..]
+                        .windows(2)
+                        .position(|window| window == b"*/")
+                    {
+                        Some(index) => self.position += index + 4,
+                        None => return,
+                    }
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn identifier(&mut self) -> pom::Result<&'a str> {
+        let start = self.position;
+        if !self.peek().is_some_and(|byte| byte.is_ascii_alphabetic()) {
+            return self.error("expected identifier");
+        }
+        while self
+            .peek()
+            .is_some_and(|byte| byte.is_ascii_alphanumeric() || byte == b'_')
+        {
+            self.position += 1;
+        }
+        // only ASCII characters were taken
+        Ok(std::str::from_utf8(&self.input[start..self.position]).unwrap())
+    }
+
+    fn integer(&mut self) -> pom::Result<i64> {
+        let start = self.position;
+        if let Some(b'+') | Some(b'-') = self.peek() {
+            self.position += 1;
+        }
+        let digits = self.position;
+        while self.peek().is_some_and(|byte| byte.is_ascii_digit()) {
+            self.position += 1;
+        }
+        if self.position == digits {
+            return self.error("expected digit");
+        }
+        let text = std::str::from_utf8(&self.input[start..self.position]).unwrap();
+        text.parse()
+            .map_err(|error: std::num::ParseIntError| pom::Error::Conversion {
+                message: error.to_string(),
+                position: start,
+            })
+    }
+
+    fn number(&mut self) -> pom::Result<UnTypedParameter<'a>> {
+        let rest = &self.input[self.position..];
+        if let Ok((value, length)) = fast_float::parse_partial::<f64, _>(rest) {
+            if rest[..length].contains(&b'.') {
+                self.position += length;
+                return Ok(UnTypedParameter::Real(value));
+            }
+        }
+        self.integer().map(UnTypedParameter::Integer)
+    }
+
+    /// Take the bytes up to the closing `end`, which is skipped.
+    fn until(&mut self, end: u8) -> pom::Result<&'a [u8]> {
+        let start = self.position;
+        match self.input[start..].iter().position(|&byte| byte == end) {
+            Some(length) => {
+                self.position += length + 1;
+                Ok(&self.input[start..start + length])
+            }
+            None => Err(pom::Error::Incomplete),
+        }
+    }
+
+    fn string(&mut self) -> pom::Result<RawString<'a>> {
+        self.expect(b'\'')?;
+        let start = self.position;
+        loop {
+            self.until(b'\'')?;
+            if self.peek() == Some(b'\'') {
+                self.position += 1;
+            } else {
+                return Ok(RawString(&self.input[start..self.position - 1]));
+            }
+        }
+    }
+
+    fn binary(&mut self) -> pom::Result<&'a str> {
+        self.expect(b'"')?;
+        let start = self.position;
+        if !matches!(self.peek(), Some(b'0'..=b'3')) {
+            return self.error("expected unused bits");
+        }
+        self.position += 1;
+        while self.peek().is_some_and(|byte| byte.is_ascii_hexdigit()) {
+            self.position += 1;
+        }
+        let digits = std::str::from_utf8(&self.input[start..self.position]).unwrap();
+        if digits.len() == 1 && digits != "0" {
+            return Err(pom::Error::Conversion {
+                message: format!("{} unused bits in an empty binary", digits),
+                position: start,
+            });
+        }
+        self.expect(b'"')?;
+        Ok(digits)
+    }
+
+    fn resource(&mut self) -> pom::Result<&'a str> {
+        self.expect(b'<')?;
+        let start = self.position;
+        let uri = self.until(b'>')?;
+        std::str::from_utf8(uri).map_err(|error| pom::Error::Conversion {
+            message: error.to_string(),
+            position: start,
+        })
+    }
+
+    fn parameter(&mut self) -> pom::Result<Parameter<'a>> {
+        match self.peek() {
+            Some(byte) if byte.is_ascii_alphabetic() => self.typed_parameter().map(Parameter::TypedParameter),
+            Some(b'*') => {
+                self.position += 1;
+                Ok(Parameter::OmittedParameter)
+            }
+            _ => self.untyped_parameter().map(Parameter::UnTypedParameter),
+        }
+    }
+
+    fn parameter_list(&mut self) -> pom::Result<Vec<Parameter<'a>>> {
+        self.expect(b'(')?;
+        self.space();
+        let mut parameters = Vec::new();
+        if self.peek() == Some(b')') {
+            self.position += 1;
+            return Ok(parameters);
+        }
+        loop {
+            parameters.push(self.parameter()?);
+            self.space();
+            match self.peek() {
+                Some(b',') => {
+                    self.position += 1;
+                    self.space();
+                }
+                _ => {
+                    self.expect(b')')?;
+                    return Ok(parameters);
+                }
+            }
+        }
+    }
+
+    fn typed_parameter(&mut self) -> pom::Result<TypedParameter<'a>> {
+        let type_name = self.identifier()?;
+        self.space();
+        let parameters = self.parameter_list()?;
+        self.space();
+        Ok(TypedParameter { type_name, parameters })
+    }
+
+    fn untyped_parameter(&mut self) -> pom::Result<UnTypedParameter<'a>> {
+        let parameter = match self.peek() {
+            Some(b'(') => UnTypedParameter::List(self.parameter_list()?),
+            Some(b'.') if self.peek_at(1).is_some_and(|byte| byte.is_ascii_alphabetic()) => {
+                self.position += 1;
+                let value = self.identifier()?;
+                self.expect(b'.')?;
+                UnTypedParameter::EnumValue(value)
+            }
+            Some(b'#') => {
+                self.position += 1;
+                if self.peek().is_some_and(|byte| byte.is_ascii_alphabetic()) {
+                    UnTypedParameter::ConstantRef(self.identifier()?)
+                } else {
+                    UnTypedParameter::EntityRef(self.integer()?)
+                }
+            }
+            Some(b'@') => {
+                self.position += 1;
+                UnTypedParameter::ValueRef(self.integer()?)
+            }
+            Some(b'<') => UnTypedParameter::Resource(self.resource()?),
+            Some(b'\'') => UnTypedParameter::String(self.string()?),
+            Some(b'"') => UnTypedParameter::Binary(self.binary()?),
+            Some(b'$') => {
+                self.position += 1;
+                UnTypedParameter::Null
+            }
+            Some(b'+') | Some(b'-') | Some(b'.') | Some(b'0'..=b'9') => self.number()?,
+            _ => return self.error("expected parameter"),
+        };
+        Ok(parameter)
+    }
+
+    fn end_statement(&mut self) -> pom::Result<()> {
+        self.space();
+        self.expect(b';')?;
+        self.space();
+        Ok(())
+    }
+
+    fn data_section_start(&mut self) -> pom::Result<Vec<Parameter<'a>>> {
+        self.position += b"DATA".len();
+        self.space();
+        let parameters = match self.peek() {
+            Some(b'(') => self.parameter_list()?,
+            _ => Vec::new(),
+        };
+        self.end_statement()?;
+        Ok(parameters)
+    }
+
+    fn entity_instance(&mut self) -> pom::Result<EntityInstance<'a>> {
+        self.expect(b'#')?;
+        let id = self.integer()?;
+        self.space();
+        self.expect(b'=')?;
+        self.space();
+        let value = if self.peek() == Some(b'(') {
+            self.position += 1;
+            self.space();
+            let mut value = vec![self.typed_parameter()?];
+            while self.peek() != Some(b')') {
+                value.push(self.typed_parameter()?);
+            }
+            self.position += 1;
+            value
+        } else {
+            vec![self.typed_parameter()?]
+        };
+        self.end_statement()?;
+        Ok(EntityInstance { id, value })
+    }
+
+    /// Parse the rare anchors and references with the parsers of the owned values.
+    fn owned<T>(&mut self, parser: pom::parser::Parser<'a, u8, T>) -> pom::Result<T> {
+        let (value, end) = parser.parse_at(self.input, self.position)?;
+        self.position = end;
+        Ok(value)
+    }
+
+    fn statement(&mut self) -> pom::Result<Statement<'a>> {
+        let keywords = [
+            (&b"ISO-10303-21;"[..], Statement::Start),
+            (b"HEADER;", Statement::Header),
+            (b"ANCHOR;", Statement::Anchor),
+            (b"REFERENCE;", Statement::Reference),
+            (b"ENDSEC;", Statement::EndSection),
+            (b"END-ISO-10303-21;", Statement::End),
+        ];
+        for (keyword, statement) in keywords {
+            if self.starts_with(keyword) {
+                self.position += keyword.len();
+                return Ok(statement);
+            }
+        }
+        let start = self.position;
+        if self.starts_with(b"DATA") {
+            match self.data_section_start() {
+                Ok(parameters) => return Ok(Statement::Data(parameters)),
+                // a header entity may start with DATA as well
+                Err(_) => self.position = start,
+            }
+        }
+        match self.peek() {
+            Some(b'#') => {
+                // an instance name is followed by a resource in the REFERENCE section
+                let statement = self.entity_instance().map(Statement::Instance);
+                if statement.is_err() {
+                    self.position = start;
+                    if let Ok(reference) = self.owned(reference()) {
+                        return Ok(Statement::ReferenceItem(reference));
+                    }
+                    self.position = start;
+                }
+                statement
+            }
+            Some(b'@') => self.owned(reference()).map(Statement::ReferenceItem),
+            Some(b'<') => self.owned(anchor()).map(Statement::AnchorItem),
+            _ => {
+                let entity = self.typed_parameter()?;
+                self.end_statement()?;
+                Ok(Statement::HeaderEntity(entity))
+            }
+        }
+    }
+}
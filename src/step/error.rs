@@ -0,0 +1,179 @@
+use super::Parameter;
+use std::fmt;
+
+/// Errors reported while reading a STEP file.
+///
+/// `Io`, `Syntax` and `UnsupportedSchema` abort the read, the other variants are collected as diagnostics
+/// for the offending instance, which is then skipped. `DuplicateId`, `DanglingReference` and `TypeMismatch`
+/// are reported by the validation of the loaded entities.
+#[derive(Debug)]
+pub enum StepError {
+    Io(std::io::Error),
+    Syntax {
+        line: usize,
+        column: usize,
+        snippet: String,
+        message: String,
+    },
+    UnknownEntity {
+        id: i64,
+        type_name: String,
+    },
+    Conversion {
+        id: i64,
+        index: usize,
+        error: ConversionError,
+    },
+    Header {
+        type_name: String,
+        index: usize,
+        error: ConversionError,
+    },
+    UnsupportedSchema {
+        schemas: Vec<String>,
+    },
+    /// A later instance with the same name, which is dropped.
+    DuplicateId {
+        id: i64,
+    },
+    DanglingReference {
+        id: i64,
+        attribute: &'static str,
+        reference: i64,
+        expected: &'static str,
+    },
+    TypeMismatch {
+        id: i64,
+        attribute: &'static str,
+        reference: i64,
+        expected: &'static str,
+    },
+}
+
+/// A parameter value does not match the attribute type it is converted to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConversionError {
+    pub expected: &'static str,
+    pub actual: String,
+}
+
+impl ConversionError {
+    pub fn new<S: Into<String>>(expected: &'static str, actual: S) -> Self {
+        ConversionError {
+            expected,
+            actual: actual.into(),
+        }
+    }
+
+    pub fn unexpected(expected: &'static str, parameter: &Parameter) -> Self {
+        ConversionError::new(expected, parameter.kind())
+    }
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "expected {}, found {}", self.expected, self.actual)
+    }
+}
+
+impl std::error::Error for ConversionError {}
+
+impl StepError {
+    /// Locate a parser error in the input and capture the offending line.
+    pub fn syntax(input: &[u8], error: pom::Error) -> Self {
+        let (position, message) = match error {
+            pom::Error::Incomplete => (input.len(), "unexpected end of file".to_string()),
+            pom::Error::Mismatch { message, position }
+            | pom::Error::Conversion { message, position }
+            | pom::Error::Expect { message, position, .. }
+            | pom::Error::Custom { message, position, .. } => (position, message),
+        };
+        let position = position.min(input.len());
+        let line_start = input[..position]
+            .iter()
+            .rposition(|&byte| byte == b'\n')
+            .map_or(0, |index| index + 1);
+        let line_end = input[position..]
+            .iter()
+            .position(|&byte| byte == b'\n' || byte == b'\r')
+            .map_or(input.len(), |index| position + index);
+        let line = input[..line_start].iter().filter(|&&byte| byte == b'\n').count() + 1;
+        let snippet = String::from_utf8_lossy(&input[line_start..line_end.min(line_start + 80)])
+            .trim_end()
+            .to_string();
+        StepError::Syntax {
+            line,
+            column: position - line_start + 1,
+            snippet,
+            message,
+        }
+    }
+
+    pub fn is_fatal(&self) -> bool {
+        matches!(
+            self,
+            StepError::Io(_) | StepError::Syntax { .. } | StepError::UnsupportedSchema { .. }
+        )
+    }
+}
+
+impl fmt::Display for StepError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StepError::Io(error) => write!(f, "{}", error),
+            StepError::Syntax {
+                line,
+                column,
+                snippet,
+                message,
+            } => write!(f, "syntax error at {}:{}: {} near `{}`", line, column, message, snippet),
+            StepError::UnknownEntity { id, type_name } => write!(f, "#{}: {} is not implemented", id, type_name),
+            StepError::Conversion { id, index, error } => {
+                write!(f, "#{}: attribute {} can not be converted: {}", id, index, error)
+            }
+            StepError::Header {
+                type_name,
+                index,
+                error,
+            } => write!(f, "{}: attribute {} can not be converted: {}", type_name, index, error),
+            StepError::UnsupportedSchema { schemas } => write!(f, "unsupported schema: {}", schemas.join(", ")),
+            StepError::DuplicateId { id } => write!(f, "#{}: instance name is used more than once", id),
+            StepError::DanglingReference {
+                id,
+                attribute,
+                reference,
+                expected,
+            } => write!(
+                f,
+                "#{}: attribute {} refers to #{} which does not exist, expected {}",
+                id, attribute, reference, expected
+            ),
+            StepError::TypeMismatch {
+                id,
+                attribute,
+                reference,
+                expected,
+            } => write!(
+                f,
+                "#{}: attribute {} refers to #{} which is not {}",
+                id, attribute, reference, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for StepError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StepError::Io(error) => Some(error),
+            StepError::Conversion { error, .. } | StepError::Header { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for StepError {
+    fn from(error: std::io::Error) -> Self {
+        StepError::Io(error)
+    }
+}
@@ -0,0 +1,279 @@
+use super::stream::InstanceStream;
+use super::{optional_parameter, ConversionError, Parameter, StepError, ToParameter, TypedParameter};
+use std::convert::{TryFrom, TryInto};
+use std::fmt::{self, Display, Formatter};
+use std::io::BufReader;
+use std::path::Path;
+use std::str::FromStr;
+
+/// The HEADER section of an exchange file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Header {
+    /// FILE_DESCRIPTION
+    pub description: Vec<String>,
+    pub implementation_level: String,
+    /// FILE_NAME
+    pub name: String,
+    pub time_stamp: String,
+    pub author: Vec<String>,
+    pub organization: Vec<String>,
+    pub preprocessor_version: String,
+    pub originating_system: String,
+    pub authorization: String,
+    /// FILE_SCHEMA
+    pub schema_identifiers: Vec<SchemaName>,
+    /// Optional FILE_POPULATION entities.
+    pub file_populations: Vec<FilePopulation>,
+    /// Optional SECTION_LANGUAGE entities.
+    pub section_languages: Vec<SectionLanguage>,
+    /// Optional SECTION_CONTEXT entities.
+    pub section_contexts: Vec<SectionContext>,
+    /// User defined header entities.
+    pub others: Vec<TypedParameter>,
+}
+
+/// A schema identifier like `AUTOMOTIVE_DESIGN { 1 0 10303 214 2 1 1 }`.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct SchemaName {
+    pub name: String,
+    /// The components of the object identifier, empty if there is none.
+    pub object_identifier: Vec<String>,
+}
+
+/// A FILE_POPULATION header entity, listing the sections governed by a schema.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FilePopulation {
+    pub governing_schema: String,
+    pub determination_method: String,
+    pub governed_sections: Option<Vec<String>>,
+}
+
+/// A SECTION_LANGUAGE header entity, `section` is `None` for the default of all data sections.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SectionLanguage {
+    pub section: Option<String>,
+    pub default_language: String,
+}
+
+/// A SECTION_CONTEXT header entity, `section` is `None` for the default of all data sections.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SectionContext {
+    pub section: Option<String>,
+    pub context_identifiers: Vec<String>,
+}
+
+impl Default for Header {
+    fn default() -> Self {
+        Header {
+            description: vec![String::new()],
+            implementation_level: "2;1".to_string(),
+            name: String::new(),
+            time_stamp: String::new(),
+            author: vec![String::new()],
+            organization: vec![String::new()],
+            preprocessor_version: String::new(),
+            originating_system: String::new(),
+            authorization: String::new(),
+            schema_identifiers: Vec::new(),
+            file_populations: Vec::new(),
+            section_languages: Vec::new(),
+            section_contexts: Vec::new(),
+            others: Vec::new(),
+        }
+    }
+}
+
+struct Attributes {
+    type_name: String,
+    parameters: std::vec::IntoIter<Parameter>,
+    index: usize,
+}
+
+impl Attributes {
+    fn next<T: TryFrom<Parameter, Error = ConversionError>>(&mut self) -> Result<T, StepError> {
+        let index = self.index;
+        self.index += 1;
+        let result = match self.parameters.next() {
+            Some(parameter) => parameter.try_into(),
+            None => Err(ConversionError::new("attribute", "end of parameters")),
+        };
+        result.map_err(|error| StepError::Header {
+            type_name: self.type_name.clone(),
+            index,
+            error,
+        })
+    }
+
+    fn next_optional<T: TryFrom<Parameter, Error = ConversionError>>(&mut self) -> Result<Option<T>, StepError> {
+        match self.parameters.as_slice().first() {
+            Some(parameter) if parameter.is_null() => {
+                self.parameters.next();
+                self.index += 1;
+                Ok(None)
+            }
+            _ => self.next().map(Some),
+        }
+    }
+}
+
+impl Header {
+    /// Read only the HEADER section of a STEP file.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<(Header, Vec<StepError>), StepError> {
+        let file = std::fs::File::open(path)?;
+        let entities = InstanceStream::read_header(BufReader::new(file))?;
+        Ok(Header::from_entities(entities))
+    }
+
+    /// Convert the header entities of an exchange file, returning the ones which can not be converted as diagnostics.
+    pub fn from_entities(entities: Vec<TypedParameter>) -> (Header, Vec<StepError>) {
+        let mut header = Header {
+            description: Vec::new(),
+            author: Vec::new(),
+            organization: Vec::new(),
+            ..Header::default()
+        };
+        let mut diagnostics = Vec::new();
+        for entity in entities {
+            if let Err(error) = header.add_entity(entity) {
+                diagnostics.push(error);
+            }
+        }
+        (header, diagnostics)
+    }
+
+    fn add_entity(&mut self, entity: TypedParameter) -> Result<(), StepError> {
+        let type_name = entity.type_name.clone();
+        let mut attributes = Attributes {
+            type_name: entity.type_name,
+            parameters: entity.parameters.into_iter(),
+            index: 0,
+        };
+        match type_name.as_str() {
+            "FILE_DESCRIPTION" => {
+                self.description = attributes.next()?;
+                self.implementation_level = attributes.next()?;
+            }
+            "FILE_NAME" => {
+                self.name = attributes.next()?;
+                self.time_stamp = attributes.next()?;
+                self.author = attributes.next()?;
+                self.organization = attributes.next()?;
+                self.preprocessor_version = attributes.next()?;
+                self.originating_system = attributes.next()?;
+                self.authorization = attributes.next()?;
+            }
+            "FILE_SCHEMA" => {
+                let names: Vec<String> = attributes.next()?;
+                self.schema_identifiers = names.iter().map(|name| name.parse().unwrap()).collect();
+            }
+            "FILE_POPULATION" => self.file_populations.push(FilePopulation {
+                governing_schema: attributes.next()?,
+                determination_method: attributes.next()?,
+                governed_sections: attributes.next_optional()?,
+            }),
+            "SECTION_LANGUAGE" => self.section_languages.push(SectionLanguage {
+                section: attributes.next_optional()?,
+                default_language: attributes.next()?,
+            }),
+            "SECTION_CONTEXT" => self.section_contexts.push(SectionContext {
+                section: attributes.next_optional()?,
+                context_identifiers: attributes.next()?,
+            }),
+            _ => self.others.push(TypedParameter {
+                type_name,
+                parameters: attributes.parameters.collect(),
+            }),
+        }
+        Ok(())
+    }
+
+    /// The header entities to be written to an exchange file.
+    pub fn to_entities(&self) -> Vec<TypedParameter> {
+        let entity = |type_name: &str, parameters: Vec<Parameter>| TypedParameter {
+            type_name: type_name.to_string(),
+            parameters,
+        };
+        let schema_names = self
+            .schema_identifiers
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        let mut entities = vec![
+            entity(
+                "FILE_DESCRIPTION",
+                vec![
+                    self.description.to_parameter(),
+                    self.implementation_level.to_parameter(),
+                ],
+            ),
+            entity(
+                "FILE_NAME",
+                vec![
+                    self.name.to_parameter(),
+                    self.time_stamp.to_parameter(),
+                    self.author.to_parameter(),
+                    self.organization.to_parameter(),
+                    self.preprocessor_version.to_parameter(),
+                    self.originating_system.to_parameter(),
+                    self.authorization.to_parameter(),
+                ],
+            ),
+            entity("FILE_SCHEMA", vec![schema_names.to_parameter()]),
+        ];
+        for population in &self.file_populations {
+            entities.push(entity(
+                "FILE_POPULATION",
+                vec![
+                    population.governing_schema.to_parameter(),
+                    population.determination_method.to_parameter(),
+                    optional_parameter(&population.governed_sections),
+                ],
+            ));
+        }
+        for language in &self.section_languages {
+            entities.push(entity(
+                "SECTION_LANGUAGE",
+                vec![
+                    optional_parameter(&language.section),
+                    language.default_language.to_parameter(),
+                ],
+            ));
+        }
+        for context in &self.section_contexts {
+            entities.push(entity(
+                "SECTION_CONTEXT",
+                vec![
+                    optional_parameter(&context.section),
+                    context.context_identifiers.to_parameter(),
+                ],
+            ));
+        }
+        entities.extend(self.others.iter().cloned());
+        entities
+    }
+}
+
+impl FromStr for SchemaName {
+    type Err = std::convert::Infallible;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let (name, object_identifier) = match text.find('{') {
+            Some(index) => (&text[..index], text[index + 1..].trim_end().trim_end_matches('}')),
+            None => (text, ""),
+        };
+        Ok(SchemaName {
+            name: name.trim().to_string(),
+            object_identifier: object_identifier.split_whitespace().map(str::to_string).collect(),
+        })
+    }
+}
+
+impl Display for SchemaName {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(&self.name)?;
+        if !self.object_identifier.is_empty() {
+            write!(f, " {{ {} }}", self.object_identifier.join(" "))?;
+        }
+        Ok(())
+    }
+}
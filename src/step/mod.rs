@@ -1,8 +1,19 @@
+pub mod borrowed;
+mod error;
+mod header;
 pub mod parser;
 mod reader;
 mod real;
+mod stream;
 mod structure;
+mod validate;
+mod writer;
 
+pub use error::*;
+pub use header::*;
 pub use reader::*;
 pub use real::*;
+pub use stream::*;
 pub use structure::*;
+pub use validate::*;
+pub use writer::*;
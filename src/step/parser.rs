@@ -20,7 +20,7 @@ fn identifier<'a>() -> Parser<'a, u8, &'a str> {
 
 fn integer<'a>() -> Parser<'a, u8, i64> {
     let number = one_of(b"+-").opt() + is_a(digit).repeat(1..);
-    number.collect().convert(str::from_utf8).convert(|s| i64::from_str(s))
+    number.collect().convert(str::from_utf8).convert(i64::from_str)
 }
 
 fn real<'a>() -> Parser<'a, u8, f64> {
@@ -48,16 +48,105 @@ fn real<'a>() -> Parser<'a, u8, f64> {
     })
 }
 
+/// The encoded characters of a STRING between its quotes.
+fn raw_string<'a>() -> Parser<'a, u8, &'a [u8]> {
+    let chars = (none_of(b"'").discard() | seq(b"''").discard()).repeat(0..);
+    sym(b'\'') * chars.collect() - sym(b'\'')
+}
+
 fn string<'a>() -> Parser<'a, u8, String> {
-    let special_char = sym(b'\\')
-        | sym(b'b').map(|_| b'\x08')
-        | sym(b'f').map(|_| b'\x0C')
-        | sym(b'n').map(|_| b'\n')
-        | sym(b'r').map(|_| b'\r')
-        | sym(b't').map(|_| b'\t');
-    let escape_sequence = sym(b'\\') * special_char;
-    let chars = sym(b'\'') * (none_of(b"\\'") | escape_sequence).repeat(0..) - sym(b'\'');
-    chars.convert(String::from_utf8)
+    raw_string().convert(decode_string)
+}
+
+/// Decode the quotes and control directives of a STRING: `''`, `\\\\`, `\S\`, `\P?\`, `\X\hh`, `\X2\..\X0\`
+/// and `\X4\..\X0\`.
+///
+/// A backslash which does not start a directive is kept as is, other non-ASCII bytes are read as UTF-8,
+/// falling back to ISO 8859-1.
+pub(super) fn decode_string(chars: &[u8]) -> Result<String, String> {
+    let mut text = String::with_capacity(chars.len());
+    let mut page = 'A';
+    let mut index = 0;
+    while index < chars.len() {
+        let rest = &chars[index..];
+        if rest.starts_with(b"''") {
+            text.push('\'');
+            index += 2;
+        } else if rest.starts_with(b"\\\\") {
+            text.push('\\');
+            index += 2;
+        } else if rest.starts_with(b"\\S\\") && rest.len() > 3 {
+            text.push(decode_page_char(page, rest[3] | 0x80));
+            index += 4;
+        } else if rest.len() > 3 && rest.starts_with(b"\\P") && rest[3] == b'\\' && (b'A'..=b'I').contains(&rest[2]) {
+            page = rest[2] as char;
+            index += 4;
+        } else if rest.starts_with(b"\\X\\") && rest.len() > 4 {
+            let code = decode_hex(&rest[3..5])?;
+            text.push(char::from(code as u8));
+            index += 5;
+        } else if rest.starts_with(b"\\X2\\") || rest.starts_with(b"\\X4\\") {
+            let end = find(&rest[4..], b"\\X0\\").ok_or("\\X0\\ expected")? + 4;
+            let digits = &rest[4..end];
+            let width = if rest[2] == b'2' { 4 } else { 8 };
+            if !digits.len().is_multiple_of(width) {
+                return Err(format!("invalid hex digits {}", String::from_utf8_lossy(digits)));
+            }
+            if width == 4 {
+                let units = digits.chunks(4).map(decode_hex).collect::<Result<Vec<_>, _>>()?;
+                let units = units.into_iter().map(|unit| unit as u16).collect::<Vec<_>>();
+                text.push_str(&String::from_utf16(&units).map_err(|error| error.to_string())?);
+            } else {
+                for code in digits.chunks(8) {
+                    let code = decode_hex(code)?;
+                    text.push(char::from_u32(code).ok_or_else(|| format!("invalid character {:X}", code))?);
+                }
+            }
+            index += end + 4;
+        } else if rest[0] < 0x80 {
+            text.push(rest[0] as char);
+            index += 1;
+        } else {
+            let length = rest.iter().position(|&byte| byte < 0x80).unwrap_or(rest.len());
+            match str::from_utf8(&rest[..length]) {
+                Ok(value) => text.push_str(value),
+                Err(_) => text.extend(rest[..length].iter().map(|&byte| byte as char)),
+            }
+            index += length;
+        }
+    }
+    Ok(text)
+}
+
+fn decode_hex(digits: &[u8]) -> Result<u32, String> {
+    let text = str::from_utf8(digits).map_err(|error| error.to_string())?;
+    if text.is_empty() || !digits.iter().all(u8::is_ascii_hexdigit) {
+        return Err(format!("invalid hex digits {}", text));
+    }
+    u32::from_str_radix(text, 16).map_err(|error| error.to_string())
+}
+
+fn find(chars: &[u8], pattern: &[u8]) -> Option<usize> {
+    chars.windows(pattern.len()).position(|window| window == pattern)
+}
+
+/// Map the upper half of an ISO 8859 page, selected by `\PA\` to `\PI\`, to a character.
+fn decode_page_char(page: char, code: u8) -> char {
+    let encoding = match page {
+        'B' => encoding_rs::ISO_8859_2,
+        'C' => encoding_rs::ISO_8859_3,
+        'D' => encoding_rs::ISO_8859_4,
+        'E' => encoding_rs::ISO_8859_5,
+        'F' => encoding_rs::ISO_8859_6,
+        'G' => encoding_rs::ISO_8859_7,
+        'H' => encoding_rs::ISO_8859_8,
+        // ISO 8859-9 is only available as its superset windows-1254
+        'I' => encoding_rs::WINDOWS_1254,
+        _ => return char::from(code),
+    };
+    let bytes = [code];
+    let (text, _) = encoding.decode_without_bom_handling(&bytes);
+    text.chars().next().unwrap_or(char::REPLACEMENT_CHARACTER)
 }
 
 fn entity_id<'a>() -> Parser<'a, u8, i64> {
@@ -68,17 +157,39 @@ fn constant_ref<'a>() -> Parser<'a, u8, String> {
     sym(b'#') * identifier().map(str::to_string)
 }
 
-// fn value_name<'a>() -> Parser<'a, u8, String> {
-//     sym(b'@') * identifier().map(str::to_string)
-// }
+fn value_ref<'a>() -> Parser<'a, u8, i64> {
+    sym(b'@') * integer()
+}
 
-// fn resource<'a>() -> Parser<'a, u8, String> {
-//     sym(b'<') * identifier().map(str::to_string) - sym(b'>')
-// }
+fn resource<'a>() -> Parser<'a, u8, String> {
+    sym(b'<') * none_of(b">").repeat(0..).convert(String::from_utf8) - sym(b'>')
+}
 
-fn binary<'a>() -> Parser<'a, u8, ()> {
-    // todo: decode binary value
-    seq(b"\"\"") * one_of(b"0123") * is_a(hex_digit).repeat(0..).discard() - seq(b"\"\"")
+/// The digits of a BINARY, starting with the number of unused bits.
+fn raw_binary<'a>() -> Parser<'a, u8, &'a str> {
+    let digits = one_of(b"0123") + is_a(hex_digit).discard().repeat(0..);
+    let digits = digits.collect().convert(str::from_utf8).convert(|digits| {
+        if digits.len() == 1 && digits != "0" {
+            Err(format!("{} unused bits in an empty binary", digits))
+        } else {
+            Ok(digits)
+        }
+    });
+    sym(b'"') * digits - sym(b'"')
+}
+
+fn binary<'a>() -> Parser<'a, u8, Binary> {
+    raw_binary().map(decode_binary)
+}
+
+/// Decode the digits of a BINARY, the bits counted by the first digit are padding of the second.
+pub(super) fn decode_binary(digits: &str) -> Binary {
+    let unused = digits.as_bytes()[0] - b'0';
+    let bits = digits[1..].chars().flat_map(|digit| {
+        let value = digit.to_digit(16).unwrap();
+        (0..4).rev().map(move |shift| value & (1 << shift) != 0)
+    });
+    bits.skip(unused as usize).collect()
 }
 
 fn enum_value<'a>() -> Parser<'a, u8, String> {
@@ -86,8 +197,8 @@ fn enum_value<'a>() -> Parser<'a, u8, String> {
 }
 
 fn parameter<'a>() -> Parser<'a, u8, Parameter> {
-    typed_parameter().map(|param| Parameter::TypedParameter(param))
-        | untyped_parameter().map(|param| Parameter::UnTypedParameter(param))
+    typed_parameter().map(Parameter::TypedParameter)
+        | untyped_parameter().map(Parameter::UnTypedParameter)
         | sym(b'*').map(|_| Parameter::OmittedParameter)
 }
 
@@ -101,33 +212,235 @@ fn typed_parameter<'a>() -> Parser<'a, u8, TypedParameter> {
 }
 
 fn untyped_parameter<'a>() -> Parser<'a, u8, UnTypedParameter> {
-    parameter_list().map(|parameters| UnTypedParameter::List(parameters))
-        | enum_value().map(|value| UnTypedParameter::EnumValue(value))
-        | entity_id().map(|id| UnTypedParameter::EntityRef(id))
-        | constant_ref().map(|name| UnTypedParameter::ConstantRef(name))
-        | real().map(|value| UnTypedParameter::Real(value))
-        | integer().map(|value| UnTypedParameter::Integer(value))
-        | string().map(|value| UnTypedParameter::String(value))
-        | binary().map(|value| UnTypedParameter::Binary(value))
+    parameter_list().map(UnTypedParameter::List)
+        | enum_value().map(UnTypedParameter::EnumValue)
+        | entity_id().map(UnTypedParameter::EntityRef)
+        | constant_ref().map(UnTypedParameter::ConstantRef)
+        | value_ref().map(UnTypedParameter::ValueRef)
+        | resource().map(UnTypedParameter::Resource)
+        | real().map(UnTypedParameter::Real)
+        | integer().map(UnTypedParameter::Integer)
+        | string().map(UnTypedParameter::String)
+        | binary().map(UnTypedParameter::Binary)
         | sym(b'$').map(|_| UnTypedParameter::Null)
 }
 
+/// Try `first`, then `second`, failing with the error which got further into the input if both fail.
+fn furthest<'a, O: 'a>(first: Parser<'a, u8, O>, second: Parser<'a, u8, O>) -> Parser<'a, u8, O> {
+    fn position(error: &pom::Error) -> usize {
+        match error {
+            pom::Error::Incomplete => usize::MAX,
+            pom::Error::Mismatch { position, .. }
+            | pom::Error::Conversion { position, .. }
+            | pom::Error::Expect { position, .. }
+            | pom::Error::Custom { position, .. } => *position,
+        }
+    }
+    Parser::new(move |input: &'a [u8], start: usize| {
+        (first.method)(input, start).or_else(|error| {
+            (second.method)(input, start).map_err(|other| {
+                if position(&other) > position(&error) {
+                    other
+                } else {
+                    error
+                }
+            })
+        })
+    })
+}
+
 fn entity_instance<'a>() -> Parser<'a, u8, EntityInstance> {
-    (entity_id() - space() - sym(b'=') - space()
-        + (typed_parameter().map(|value| vec![value])
-            | sym(b'(') * space() * typed_parameter().repeat(1..) - space() - sym(b')'))
+    let value = furthest(
+        typed_parameter().map(|value| vec![value]),
+        sym(b'(') * space() * typed_parameter().repeat(1..) - space() - sym(b')'),
+    );
+    (entity_id() - space() - sym(b'=') - space() + value - space() - sym(b';') - space())
+        .map(|(id, value)| EntityInstance { id, value })
+}
+
+fn header_entity<'a>() -> Parser<'a, u8, TypedParameter> {
+    typed_parameter() - space() - sym(b';') - space()
+}
+
+/// The start of an exchange file up to the end of its HEADER section.
+pub fn header_section<'a>() -> Parser<'a, u8, Vec<TypedParameter>> {
+    space() * seq(b"ISO-10303-21;") * space() * seq(b"HEADER;") * space() * header_entity().repeat(3..)
+        - seq(b"ENDSEC;")
         - space()
+}
+
+pub(super) fn anchor<'a>() -> Parser<'a, u8, Anchor> {
+    let tag = sym(b'{') * space() * identifier().map(str::to_string) - space() - sym(b':') - space()
+        + untyped_parameter()
+        - space()
+        - sym(b'}')
+        - space();
+    let anchor = resource() - space() - sym(b'=') - space() + untyped_parameter() - space() + tag.repeat(0..)
         - sym(b';')
-        - space())
-    .map(|(id, value)| EntityInstance { id, value })
+        - space();
+    anchor.map(|((name, item), tags)| Anchor { name, item, tags })
+}
+
+fn anchor_section<'a>() -> Parser<'a, u8, Vec<Anchor>> {
+    seq(b"ANCHOR;") * space() * anchor().repeat(0..) - seq(b"ENDSEC;") - space()
+}
+
+pub(super) fn reference<'a>() -> Parser<'a, u8, Reference> {
+    let name = entity_id().map(UnTypedParameter::EntityRef) | value_ref().map(UnTypedParameter::ValueRef);
+    (name - space() - sym(b'=') - space() + resource() - space() - sym(b';') - space())
+        .map(|(name, resource)| Reference { name, resource })
+}
+
+fn reference_section<'a>() -> Parser<'a, u8, Vec<Reference>> {
+    seq(b"REFERENCE;") * space() * reference().repeat(0..) - seq(b"ENDSEC;") - space()
+}
+
+fn data_section_start<'a>() -> Parser<'a, u8, Vec<Parameter>> {
+    let parameters = parameter_list().opt().map(Option::unwrap_or_default);
+    seq(b"DATA") * space() * parameters - space() - sym(b';') - space()
+}
+
+fn data_section<'a>() -> Parser<'a, u8, DataSection> {
+    (data_section_start() + entity_instance().repeat(0..) - seq(b"ENDSEC;") - space())
+        .map(|(parameters, instances)| DataSection { parameters, instances })
+}
+
+fn signature_section<'a>() -> Parser<'a, u8, String> {
+    let content = (!seq(b"ENDSEC;") * any()).repeat(0..).collect();
+    seq(b"SIGNATURE") * content.map(|content| String::from_utf8_lossy(content).into_owned()) - seq(b"ENDSEC;") - space()
+}
+
+/// The SIGNATURE sections following the end of an exchange structure, up to the end of the file.
+pub fn signature_sections<'a>() -> Parser<'a, u8, Vec<String>> {
+    space() * signature_section().repeat(0..) - end()
 }
 
 pub fn exchange_file<'a>() -> Parser<'a, u8, ExchangeFile> {
-    let header_entity = typed_parameter() - space() - sym(b';') - space();
-    let head = seq(b"ISO-10303-21;") * space() * seq(b"HEADER;") * space() * header_entity.repeat(3..)
-        - seq(b"ENDSEC;")
-        - space();
-    let data = seq(b"DATA;") * space() * entity_instance().repeat(0..) - seq(b"ENDSEC;") - space();
-    let tail = seq(b"END-ISO-10303-21;") - space();
-    (head + data - tail).map(|(header, data)| ExchangeFile { header, data })
+    let head = header_section() + anchor_section().opt() + reference_section().opt();
+    let data = data_section().repeat(0..);
+    let tail = seq(b"END-ISO-10303-21;") * signature_sections();
+    (head + data + tail).map(|((((header, anchors), references), data), signatures)| ExchangeFile {
+        header,
+        anchors: anchors.unwrap_or_default(),
+        references: references.unwrap_or_default(),
+        data,
+        signatures,
+    })
+}
+
+/// A single statement of an exchange file, terminated by a semicolon.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    Start,
+    Header,
+    Anchor,
+    Reference,
+    Data(Vec<Parameter>),
+    EndSection,
+    End,
+    HeaderEntity(TypedParameter),
+    AnchorItem(Anchor),
+    ReferenceItem(Reference),
+    Instance(EntityInstance),
+}
+
+fn statement<'a>() -> Parser<'a, u8, Statement> {
+    let keyword = seq(b"ISO-10303-21;").map(|_| Statement::Start)
+        | seq(b"HEADER;").map(|_| Statement::Header)
+        | seq(b"ANCHOR;").map(|_| Statement::Anchor)
+        | seq(b"REFERENCE;").map(|_| Statement::Reference)
+        | seq(b"ENDSEC;").map(|_| Statement::EndSection)
+        | seq(b"END-ISO-10303-21;").map(|_| Statement::End);
+    // report the mismatch of the statement which matched the most, not of the last one tried
+    vec![
+        data_section_start().map(Statement::Data),
+        entity_instance().map(Statement::Instance),
+        reference().map(Statement::ReferenceItem),
+        anchor().map(Statement::AnchorItem),
+        header_entity().map(Statement::HeaderEntity),
+    ]
+    .into_iter()
+    .fold(keyword, furthest)
+}
+
+/// The next statement of a batch as split by `InstanceStream` with its position, `None` at the end of the batch.
+pub fn next_statement<'a>() -> Parser<'a, u8, Option<(usize, Statement)>> {
+    space() * (end().map(|_| None) | (empty().pos() + statement()).map(Some))
+}
+
+/// Parse a single statement into values borrowed from the input, after any spaces and comments before it.
+pub fn borrowed_statement<'a>() -> Parser<'a, u8, borrowed::Statement<'a>> {
+    Parser::new(borrowed::parse_statement)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(chars: &str) -> Result<String, String> {
+        decode_string(chars.as_bytes())
+    }
+
+    fn encode(value: &str) -> String {
+        let mut text = String::new();
+        write_string(&mut text, value).unwrap();
+        text
+    }
+
+    #[test]
+    fn decode_quotes_and_backslashes() {
+        assert_eq!(decode("it''s").unwrap(), "it's");
+        assert_eq!(decode(r"a\\b").unwrap(), r"a\b");
+        // a backslash which does not start a directive is kept
+        assert_eq!(decode(r"a\b").unwrap(), r"a\b");
+    }
+
+    #[test]
+    fn decode_code_pages() {
+        assert_eq!(decode(r"\S\D").unwrap(), "Ä");
+        assert_eq!(decode(r"\PB\\S\9").unwrap(), "š");
+        assert_eq!(decode(r"\PE\\S\a\PA\\S\a").unwrap(), "сá");
+    }
+
+    #[test]
+    fn decode_hex_directives() {
+        assert_eq!(decode(r"\X\E4").unwrap(), "ä");
+        assert_eq!(decode(r"\X2\00E400F6\X0\").unwrap(), "äö");
+        assert_eq!(decode(r"\X2\D83DDE00\X0\").unwrap(), "\u{1F600}");
+        assert_eq!(decode(r"\X4\0001F600\X0\!").unwrap(), "\u{1F600}!");
+        assert!(decode(r"\X2\00E\X0\").is_err());
+        assert!(decode(r"\X2\00E4").is_err());
+        assert!(decode(r"\X4\00110000\X0\").is_err());
+    }
+
+    #[test]
+    fn decode_utf8_with_latin1_fallback() {
+        assert_eq!(decode("grün").unwrap(), "grün");
+        assert_eq!(decode_string(b"gr\xFCn").unwrap(), "grün");
+    }
+
+    #[test]
+    fn encode_with_basic_alphabet() {
+        assert_eq!(encode(r"it's \"), r"'it''s \\'");
+        assert_eq!(encode("äĀ\u{1F600}"), r"'\X\E4\X2\0100\X0\\X4\0001F600\X0\'");
+        for value in &["", "it's", r"\S\", "äöü", "ĀāĂ", "\u{1F600}a\u{10FFFF}"] {
+            let text = encode(value);
+            assert_eq!(decode(&text[1..text.len() - 1]).unwrap(), *value);
+        }
+    }
+
+    #[test]
+    fn binary_skips_unused_bits() {
+        let binary = decode_binary("3F");
+        assert_eq!(binary.iter().collect::<Vec<_>>(), [true]);
+        assert_eq!(binary.to_string(), "\"31\"");
+
+        let binary = decode_binary("170");
+        assert_eq!(binary.len(), 7);
+        assert_eq!(binary.to_string(), "\"170\"");
+        // set padding bits are dropped
+        assert_eq!(decode_binary("1F0"), binary);
+        assert_eq!(decode_binary("0").to_string(), "\"0\"");
+        assert!(raw_binary().parse(b"\"3\"").is_err());
+    }
 }
@@ -1,58 +1,254 @@
-use super::parser::exchange_file;
+use super::error::StepError;
+use super::header::Header;
+use super::parser::Statement;
+use super::stream::{Batch, InstanceStream};
 use super::structure::*;
 use std::any::{Any, TypeId};
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader};
 use std::path::Path;
+use std::sync::{mpsc, Mutex};
+
+pub type EntityResult = Result<(TypeId, &'static str, Box<dyn Any + Send>), StepError>;
+
+/// The parts of a complex entity instance which have an entity struct.
+pub type ComplexEntity = Vec<Box<dyn Any + Send>>;
+
+/// An entity struct or `dyn` entity trait which an entity instance can be resolved to.
+///
+/// Entity structs get it for free, the generated code implements it for the traits of entities with subtypes.
+pub trait FromEntity {
+    /// Downcast a simple entity instance.
+    fn from_entity(entity: &dyn Any) -> Option<&Self>;
+
+    /// Downcast an entity instance, looking into the parts of a complex one.
+    fn resolve(entity: &dyn Any) -> Option<&Self> {
+        Self::from_entity(entity).or_else(|| {
+            let parts = entity.downcast_ref::<ComplexEntity>()?;
+            parts.iter().find_map(|part| Self::from_entity(&**part))
+        })
+    }
+}
+
+impl<T: Any> FromEntity for T {
+    fn from_entity(entity: &dyn Any) -> Option<&Self> {
+        entity.downcast_ref()
+    }
+}
+
+/// The outcome of reading a STEP file which could be parsed.
+#[derive(Debug)]
+pub struct ReadReport {
+    pub header: Header,
+    pub anchors: Vec<Anchor>,
+    pub references: Vec<Reference>,
+    /// The parameters of each DATA section with the section name and governing schema, empty for a single unnamed
+    /// section.
+    pub data_sections: Vec<Vec<Parameter>>,
+    /// The SIGNATURE sections, verbatim.
+    pub signatures: Vec<String>,
+    /// Header entities and instances which were skipped.
+    pub diagnostics: Vec<StepError>,
+}
+
+/// Statements of a batch, with the entities created from the instances taken out of them.
+type BuiltStatements = Vec<(usize, Statement, Option<EntityResult>)>;
 
 pub trait StepReader {
     fn insert_entity(&mut self, id: i64, type_id: TypeId, type_name: &'static str, entity: Box<dyn Any>);
-    fn create_simple_entity(
-        &self,
-        typed_parameter: TypedParameter,
-        own_parameters_only: bool,
-    ) -> Option<(TypeId, &'static str, Box<dyn Any>)>;
-    fn create_complex_entity(
-        &self,
-        typed_parameters: Vec<TypedParameter>,
-    ) -> Option<(TypeId, &'static str, Box<dyn Any>)> {
-        let values = typed_parameters
-            .into_iter()
-            .filter_map(|typed_parameter| {
-                if let Some((_, _, entity)) = self.create_simple_entity(typed_parameter, true) {
-                    return Some(entity);
-                }
-                return None;
-            })
-            .collect::<Vec<Box<dyn Any>>>();
+    fn create_simple_entity(id: i64, typed_parameter: TypedParameter, own_parameters_only: bool) -> EntityResult;
+    fn create_complex_entity(id: i64, typed_parameters: Vec<TypedParameter>) -> EntityResult {
+        let mut values = ComplexEntity::new();
+        for typed_parameter in typed_parameters {
+            match Self::create_simple_entity(id, typed_parameter, true) {
+                Ok((_, _, entity)) => values.push(entity),
+                // abstract supertypes of a complex instance have no entity struct
+                Err(StepError::UnknownEntity { .. }) => {}
+                Err(error) => return Err(error),
+            }
+        }
         let type_id = values.type_id();
-        let type_name = std::any::type_name::<Vec<Box<dyn Any>>>();
-        Some((type_id, type_name, Box::new(values)))
-    }
-
-    fn read<P: AsRef<Path>>(&mut self, path: P) -> std::io::Result<()> {
-        let bytes = std::fs::read(path)?;
-        match exchange_file().parse(&bytes) {
-            Ok(file) => {
-                println!("entities: {}", file.data.len());
-                for instance in file.data {
-                    // println!("read #{}", instance.id);
-                    if instance.value.len() == 1 {
-                        for typed_parameter in instance.value {
-                            if let Some((type_id, type_name, entity)) =
-                                self.create_simple_entity(typed_parameter, false)
-                            {
-                                self.insert_entity(instance.id, type_id, type_name, entity);
-                            }
+        let type_name = std::any::type_name::<ComplexEntity>();
+        Ok((type_id, type_name, Box::new(values)))
+    }
+    fn create_entity(instance: EntityInstance) -> EntityResult {
+        if instance.value.len() == 1 {
+            let typed_parameter = instance.value.into_iter().next().unwrap();
+            Self::create_simple_entity(instance.id, typed_parameter, false)
+        } else {
+            Self::create_complex_entity(instance.id, instance.value)
+        }
+    }
+
+    /// Read a STEP file, returning its header and the non-fatal diagnostics.
+    fn read<P: AsRef<Path>>(&mut self, path: P) -> Result<ReadReport, StepError> {
+        let file = std::fs::File::open(path)?;
+        self.read_from(BufReader::with_capacity(0x10000, file))
+    }
+
+    /// Read a STEP file, creating each entity as soon as its instance is parsed.
+    fn read_from<R: BufRead>(&mut self, reader: R) -> Result<ReadReport, StepError> {
+        self.read_stream(InstanceStream::new(reader)?)
+    }
+
+    /// Read the entities of an exchange file whose header was already read, e.g. to choose the reader by its schema.
+    fn read_stream<R: BufRead>(&mut self, mut instances: InstanceStream<R>) -> Result<ReadReport, StepError> {
+        let (header, mut diagnostics) = Header::from_entities(instances.header().to_vec());
+        let anchors = instances.anchors().to_vec();
+        let references = instances.references().to_vec();
+        for instance in &mut instances {
+            let instance = instance?;
+            let id = instance.id;
+            match Self::create_entity(instance) {
+                Ok((type_id, type_name, entity)) => self.insert_entity(id, type_id, type_name, entity),
+                Err(error) => diagnostics.push(error),
+            }
+        }
+        instances.finish()?;
+        Ok(ReadReport {
+            header,
+            anchors,
+            references,
+            data_sections: instances.data_sections().to_vec(),
+            signatures: instances.signatures().to_vec(),
+            diagnostics,
+        })
+    }
+
+    /// Read a STEP file like `read`, parsing and creating the entities on all available cores.
+    fn read_parallel<P: AsRef<Path>>(&mut self, path: P) -> Result<ReadReport, StepError>
+    where
+        Self: Sized,
+    {
+        let threads = std::thread::available_parallelism().map_or(1, |threads| threads.get());
+        let file = std::fs::File::open(path)?;
+        self.read_from_parallel(BufReader::with_capacity(0x10000, file), threads)
+    }
+
+    /// Read a STEP file in batches of statements, which are parsed and converted on `threads` worker threads.
+    ///
+    /// The entities and diagnostics are collected in the order of the file, the same as with `read_from`.
+    fn read_from_parallel<R: BufRead>(&mut self, reader: R, threads: usize) -> Result<ReadReport, StepError>
+    where
+        Self: Sized,
+    {
+        // a single worker only adds the overhead of passing batches around
+        if threads <= 1 {
+            return self.read_from(reader);
+        }
+        let mut instances = InstanceStream::new(reader)?;
+        let (header, mut diagnostics) = Header::from_entities(instances.header().to_vec());
+        let anchors = instances.anchors().to_vec();
+        let references = instances.references().to_vec();
+
+        let (batch, statements, result) = instances.take_statements();
+        insert_statements(
+            self,
+            &mut instances,
+            &mut diagnostics,
+            &batch,
+            build_statements::<Self>(statements),
+        )?;
+        result?;
+
+        let (job_sender, job_receiver) = mpsc::channel::<(usize, Batch)>();
+        let (result_sender, result_receiver) = mpsc::channel();
+        let job_receiver = Mutex::new(job_receiver);
+        std::thread::scope(|scope| {
+            for _ in 0..threads {
+                let job_receiver = &job_receiver;
+                let result_sender = result_sender.clone();
+                scope.spawn(move || {
+                    loop {
+                        // receive in a statement of its own, so the lock is released before the batch is parsed
+                        let job = job_receiver.lock().unwrap().recv();
+                        let (index, batch) = match job {
+                            Ok(job) => job,
+                            Err(_) => break,
+                        };
+                        let (statements, result) = batch.parse();
+                        let statements = build_statements::<Self>(statements);
+                        if result_sender.send((index, batch, statements, result)).is_err() {
+                            break;
                         }
-                    } else {
-                        if let Some((type_id, type_name, entity)) = self.create_complex_entity(instance.value) {
-                            self.insert_entity(instance.id, type_id, type_name, entity);
+                    }
+                });
+            }
+            // dropping the senders on return lets the workers finish
+            let job_sender = job_sender;
+            let result_receiver = result_receiver;
+            drop(result_sender);
+
+            let mut sent = 0;
+            let mut done = 0;
+            let mut reading = true;
+            let mut pending = BTreeMap::new();
+            loop {
+                while reading && sent - done < threads * 2 {
+                    match instances.read_batch()? {
+                        Some(batch) => {
+                            job_sender.send((sent, batch)).unwrap();
+                            sent += 1;
                         }
+                        None => reading = false,
                     }
                 }
+                if done == sent {
+                    return Ok::<(), StepError>(());
+                }
+                let (index, batch, statements, result) = result_receiver.recv().unwrap();
+                pending.insert(index, (batch, statements, result));
+                while let Some((batch, statements, result)) = pending.remove(&done) {
+                    insert_statements(self, &mut instances, &mut diagnostics, &batch, statements)?;
+                    result?;
+                    done += 1;
+                }
             }
-            Err(err) => println!("{:?}", err),
-        }
+        })?;
+        instances.finish()?;
+        Ok(ReadReport {
+            header,
+            anchors,
+            references,
+            data_sections: instances.data_sections().to_vec(),
+            signatures: instances.signatures().to_vec(),
+            diagnostics,
+        })
+    }
+}
 
-        Ok(())
+fn build_statements<T: StepReader>(statements: Vec<(usize, Statement)>) -> BuiltStatements {
+    statements
+        .into_iter()
+        .map(|(position, statement)| match statement {
+            Statement::Instance(instance) => {
+                let id = instance.id;
+                let entity = T::create_entity(instance);
+                let statement = Statement::Instance(EntityInstance { id, value: Vec::new() });
+                (position, statement, Some(entity))
+            }
+            statement => (position, statement, None),
+        })
+        .collect()
+}
+
+fn insert_statements<T: StepReader, R: BufRead>(
+    reader: &mut T,
+    instances: &mut InstanceStream<R>,
+    diagnostics: &mut Vec<StepError>,
+    batch: &Batch,
+    statements: BuiltStatements,
+) -> Result<(), StepError> {
+    for (position, statement, entity) in statements {
+        if !instances.accept(&statement) {
+            return Err(batch.unexpected_statement(position));
+        }
+        if let (Statement::Instance(instance), Some(entity)) = (statement, entity) {
+            match entity {
+                Ok((type_id, type_name, entity)) => reader.insert_entity(instance.id, type_id, type_name, entity),
+                Err(error) => diagnostics.push(error),
+            }
+        }
     }
+    Ok(())
 }
@@ -0,0 +1,364 @@
+use super::error::StepError;
+use super::parser::{header_section, next_statement, signature_sections, Statement};
+use super::structure::*;
+use pom::parser::end;
+use std::collections::VecDeque;
+use std::io::BufRead;
+
+/// The statements are parsed in batches of about this many bytes.
+const BATCH_SIZE: usize = 0x100000;
+
+enum Section {
+    None,
+    Start,
+    Header,
+    Anchor,
+    Reference,
+    End,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Scan {
+    Normal,
+    String,
+    Comment,
+    Resource,
+}
+
+/// Complete statements read from an exchange file, starting at `line` and `column`.
+#[derive(Default)]
+pub struct Batch {
+    bytes: Vec<u8>,
+    line: usize,
+    column: usize,
+}
+
+impl Batch {
+    /// Parse the statements, with the position of each in the batch, up to the first malformed one.
+    pub fn parse(&self) -> (Vec<(usize, Statement)>, Result<(), StepError>) {
+        let parser = next_statement();
+        let mut statements = Vec::new();
+        let mut position = 0;
+        loop {
+            match parser.parse_at(&self.bytes, position) {
+                Ok((Some(statement), next)) => {
+                    statements.push(statement);
+                    position = next;
+                }
+                Ok((None, _)) => return (statements, Ok(())),
+                Err(error) => return (statements, Err(self.syntax_error(error))),
+            }
+        }
+    }
+
+    /// Locate a parser error in the file instead of the batch.
+    pub fn syntax_error(&self, error: pom::Error) -> StepError {
+        match StepError::syntax(&self.bytes, error) {
+            StepError::Syntax {
+                line,
+                column,
+                snippet,
+                message,
+            } => StepError::Syntax {
+                line: line + self.line - 1,
+                column: if line == 1 { column + self.column - 1 } else { column },
+                snippet,
+                message,
+            },
+            error => error,
+        }
+    }
+
+    pub fn unexpected_statement(&self, position: usize) -> StepError {
+        self.syntax_error(pom::Error::Mismatch {
+            message: "unexpected statement".to_string(),
+            position,
+        })
+    }
+}
+
+/// Reads the entity instances of an exchange file one at a time, holding only a batch of statements in memory.
+///
+/// The header, anchor and reference sections are read when the stream is created.
+pub struct InstanceStream<R> {
+    reader: R,
+    batch: Batch,
+    statements: VecDeque<(usize, Statement)>,
+    /// The syntax error after the statements of the batch, reported once they are read.
+    error: Option<StepError>,
+    position: usize,
+    line: usize,
+    column: usize,
+    at_end: bool,
+    in_data: bool,
+    finished: bool,
+    header: Vec<TypedParameter>,
+    anchors: Vec<Anchor>,
+    references: Vec<Reference>,
+    data_sections: Vec<Vec<Parameter>>,
+    signatures: Vec<String>,
+}
+
+impl<R: BufRead> InstanceStream<R> {
+    fn open(reader: R) -> Self {
+        InstanceStream {
+            reader,
+            batch: Batch::default(),
+            statements: VecDeque::new(),
+            error: None,
+            position: 0,
+            line: 1,
+            column: 1,
+            at_end: false,
+            in_data: false,
+            finished: false,
+            header: Vec::new(),
+            anchors: Vec::new(),
+            references: Vec::new(),
+            data_sections: Vec::new(),
+            signatures: Vec::new(),
+        }
+    }
+
+    /// Read the entities of the HEADER section, without reading anything after its `ENDSEC;`.
+    pub fn read_header(reader: R) -> Result<Vec<TypedParameter>, StepError> {
+        let mut stream = InstanceStream::open(reader);
+        let mut bytes = Vec::new();
+        while !bytes.ends_with(b"ENDSEC;") {
+            if !stream.read_statement(&mut bytes)? {
+                return Err(stream.end_of_file());
+            }
+        }
+        let batch = Batch {
+            bytes,
+            line: 1,
+            column: 1,
+        };
+        let header = (header_section() - end()).parse(&batch.bytes);
+        header.map_err(|error| batch.syntax_error(error))
+    }
+
+    pub fn new(reader: R) -> Result<Self, StepError> {
+        let mut stream = InstanceStream::open(reader);
+        let mut section = Section::None;
+        loop {
+            let statement = stream.next_statement()?;
+            section = match (section, statement) {
+                (Section::None, Statement::Start) => Section::Start,
+                (Section::Start, Statement::Header) => Section::Header,
+                (Section::Header, Statement::HeaderEntity(entity)) => {
+                    stream.header.push(entity);
+                    Section::Header
+                }
+                (Section::Anchor, Statement::AnchorItem(anchor)) => {
+                    stream.anchors.push(anchor);
+                    Section::Anchor
+                }
+                (Section::Reference, Statement::ReferenceItem(reference)) => {
+                    stream.references.push(reference);
+                    Section::Reference
+                }
+                (Section::Header, Statement::EndSection)
+                | (Section::Anchor, Statement::EndSection)
+                | (Section::Reference, Statement::EndSection) => Section::End,
+                (Section::End, Statement::Anchor) => Section::Anchor,
+                (Section::End, Statement::Reference) => Section::Reference,
+                (Section::End, Statement::Data(parameters)) => {
+                    stream.data_sections.push(parameters);
+                    stream.in_data = true;
+                    return Ok(stream);
+                }
+                (Section::End, Statement::End) => {
+                    stream.finished = true;
+                    return Ok(stream);
+                }
+                _ => return Err(stream.batch.unexpected_statement(stream.position)),
+            };
+        }
+    }
+
+    /// The entities of the HEADER section.
+    pub fn header(&self) -> &[TypedParameter] {
+        &self.header
+    }
+
+    pub fn anchors(&self) -> &[Anchor] {
+        &self.anchors
+    }
+
+    pub fn references(&self) -> &[Reference] {
+        &self.references
+    }
+
+    /// The parameters of the DATA sections read so far, empty for a single unnamed section.
+    pub fn data_sections(&self) -> &[Vec<Parameter>] {
+        &self.data_sections
+    }
+
+    /// The SIGNATURE sections, read by `finish`.
+    pub fn signatures(&self) -> &[String] {
+        &self.signatures
+    }
+
+    /// Append the bytes up to the next semicolon outside of strings, comments and resources to the batch.
+    fn read_statement(&mut self, batch: &mut Vec<u8>) -> Result<bool, StepError> {
+        let mut scan = Scan::Normal;
+        let mut previous = 0;
+        loop {
+            let buffer = self.reader.fill_buf()?;
+            if buffer.is_empty() {
+                return Ok(false);
+            }
+            let mut length = buffer.len();
+            let mut complete = false;
+            for (index, &byte) in buffer.iter().enumerate() {
+                if byte == b'\n' {
+                    self.line += 1;
+                    self.column = 1;
+                } else {
+                    self.column += 1;
+                }
+                scan = match (scan, byte) {
+                    (Scan::Normal, b'\'') => Scan::String,
+                    (Scan::Normal, b'<') => Scan::Resource,
+                    (Scan::Normal, b'*') if previous == b'/' => Scan::Comment,
+                    (Scan::Normal, b';') => {
+                        length = index + 1;
+                        complete = true;
+                        break;
+                    }
+                    (Scan::String, b'\'') | (Scan::Resource, b'>') => Scan::Normal,
+                    (Scan::Comment, b'/') if previous == b'*' => Scan::Normal,
+                    (scan, _) => scan,
+                };
+                // the star opening a comment does not close it
+                previous = if scan == Scan::Comment && byte == b'*' && previous == b'/' {
+                    0
+                } else {
+                    byte
+                };
+            }
+            batch.extend_from_slice(&buffer[..length]);
+            self.reader.consume(length);
+            if complete {
+                return Ok(true);
+            }
+        }
+    }
+
+    /// Read the next batch of statements without parsing them, `None` at the end of the exchange structure.
+    ///
+    /// Statements which are already parsed have to be taken with `take_statements` first.
+    pub fn read_batch(&mut self) -> Result<Option<Batch>, StepError> {
+        if self.at_end {
+            return Ok(None);
+        }
+        let mut bytes = Vec::with_capacity(BATCH_SIZE + 0x1000);
+        let (line, column) = (self.line, self.column);
+        while bytes.len() < BATCH_SIZE && !self.at_end {
+            // anything after END-ISO-10303-21; belongs to signature sections
+            if !self.read_statement(&mut bytes)? || bytes.ends_with(b"END-ISO-10303-21;") {
+                self.at_end = true;
+            }
+        }
+        Ok(Some(Batch { bytes, line, column }))
+    }
+
+    /// Take the current batch, its statements which are not read yet and the syntax error after them.
+    pub fn take_statements(&mut self) -> (Batch, Vec<(usize, Statement)>, Result<(), StepError>) {
+        let result = self.error.take().map_or(Ok(()), Err);
+        (
+            std::mem::take(&mut self.batch),
+            self.statements.drain(..).collect(),
+            result,
+        )
+    }
+
+    fn next_statement(&mut self) -> Result<Statement, StepError> {
+        while self.statements.is_empty() {
+            if let Some(error) = self.error.take() {
+                return Err(error);
+            }
+            match self.read_batch()? {
+                Some(batch) => {
+                    let (statements, result) = batch.parse();
+                    self.statements.extend(statements);
+                    self.error = result.err();
+                    self.batch = batch;
+                }
+                None => return Err(self.end_of_file()),
+            }
+        }
+        let (position, statement) = self.statements.pop_front().unwrap();
+        self.position = position;
+        Ok(statement)
+    }
+
+    /// Check that the exchange structure was read up to `END-ISO-10303-21;`, then read the SIGNATURE sections
+    /// after it.
+    pub fn finish(&mut self) -> Result<(), StepError> {
+        if !self.finished {
+            return Err(self.end_of_file());
+        }
+        let mut bytes = Vec::new();
+        self.reader.read_to_end(&mut bytes)?;
+        let batch = Batch {
+            bytes,
+            line: self.line,
+            column: self.column,
+        };
+        let signatures = signature_sections().parse(&batch.bytes);
+        self.signatures = signatures.map_err(|error| batch.syntax_error(error))?;
+        Ok(())
+    }
+
+    fn end_of_file(&self) -> StepError {
+        let end = Batch {
+            bytes: Vec::new(),
+            line: self.line,
+            column: self.column,
+        };
+        end.syntax_error(pom::Error::Incomplete)
+    }
+
+    /// Follow the DATA sections after the header, returning `false` for a statement out of place.
+    pub fn accept(&mut self, statement: &Statement) -> bool {
+        match (self.in_data, statement) {
+            (true, Statement::Instance(_)) => {}
+            (true, Statement::EndSection) => self.in_data = false,
+            (false, Statement::Data(parameters)) => {
+                self.data_sections.push(parameters.clone());
+                self.in_data = true;
+            }
+            (false, Statement::End) => self.finished = true,
+            _ => {
+                self.finished = true;
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl<R: BufRead> Iterator for InstanceStream<R> {
+    type Item = Result<EntityInstance, StepError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.finished {
+            let statement = match self.next_statement() {
+                Ok(statement) => statement,
+                Err(error) => {
+                    self.finished = true;
+                    return Some(Err(error));
+                }
+            };
+            if !self.accept(&statement) {
+                return Some(Err(self.batch.unexpected_statement(self.position)));
+            }
+            if let Statement::Instance(instance) = statement {
+                return Some(Ok(instance));
+            }
+        }
+        None
+    }
+}
@@ -1,22 +1,24 @@
-use super::Real;
+use super::{ConversionError, Real};
 use std::collections::HashSet;
-use std::hash::Hash;
-use std::iter::FromIterator;
+use std::convert::{TryFrom, TryInto};
+use std::fmt::{self, Debug, Formatter};
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Parameter {
     TypedParameter(TypedParameter),
     UnTypedParameter(UnTypedParameter),
     OmittedParameter,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct TypedParameter {
     pub type_name: String,
     pub parameters: Vec<Parameter>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum UnTypedParameter {
     List(Vec<Parameter>),
     EnumValue(String),
@@ -25,186 +27,437 @@ pub enum UnTypedParameter {
     Integer(i64),
     Real(f64),
     String(String),
-    Binary(()),
+    Binary(Binary),
+    /// A value instance name `@1`, defined in the REFERENCE section.
+    ValueRef(i64),
+    /// A resource `<uri>` of an external file.
+    Resource(String),
     Null,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct EntityInstance {
     pub id: i64,
     pub value: Vec<TypedParameter>,
 }
 
-#[derive(Eq, PartialEq, Hash, Debug, Default)]
+#[derive(Eq, PartialEq, Hash, Debug, Default, Clone, Copy)]
 pub struct EntityRef(pub i64);
 
-#[derive(Debug)]
+/// A reference to an entity instance of type `T`, an entity struct or the `dyn` trait of an entity with subtypes.
+pub struct TypedRef<T: ?Sized> {
+    id: i64,
+    entity_type: PhantomData<fn() -> Box<T>>,
+}
+
+/// A BINARY value, a sequence of bits stored most significant bit first.
+#[derive(Eq, PartialEq, Hash, Debug, Default, Clone)]
+pub struct Binary {
+    bytes: Vec<u8>,
+    len: usize,
+}
+
+/// An anchor `<name>=item{tag:item};` exposing an instance or value to other files.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Anchor {
+    pub name: String,
+    pub item: UnTypedParameter,
+    pub tags: Vec<(String, UnTypedParameter)>,
+}
+
+/// A reference `#1=<uri>;` or `@1=<uri>;` to an instance or value in an external file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reference {
+    /// Either an `EntityRef` or a `ValueRef`.
+    pub name: UnTypedParameter,
+    pub resource: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataSection {
+    /// The section name and governing schema of `DATA(...)`, empty for a single unnamed section.
+    pub parameters: Vec<Parameter>,
+    pub instances: Vec<EntityInstance>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct ExchangeFile {
     pub header: Vec<TypedParameter>,
-    pub data: Vec<EntityInstance>,
+    pub anchors: Vec<Anchor>,
+    pub references: Vec<Reference>,
+    pub data: Vec<DataSection>,
+    /// The content of SIGNATURE sections, kept verbatim.
+    pub signatures: Vec<String>,
 }
 
 impl Parameter {
     pub fn is_null(&self) -> bool {
+        matches!(
+            self,
+            Parameter::UnTypedParameter(UnTypedParameter::Null) | Parameter::OmittedParameter
+        )
+    }
+
+    pub fn kind(&self) -> &'static str {
         match self {
-            Parameter::UnTypedParameter(parameter) => match parameter {
-                UnTypedParameter::Null => true,
-                _ => false,
-            },
-            Parameter::OmittedParameter => true,
-            _ => false,
+            Parameter::TypedParameter(_) => "typed parameter",
+            Parameter::UnTypedParameter(parameter) => parameter.kind(),
+            Parameter::OmittedParameter => "omitted parameter",
         }
     }
 }
 
-impl From<Parameter> for bool {
-    fn from(parameter: Parameter) -> Self {
+impl TypedParameter {
+    /// Take the value of a defined type instance, e.g. `LENGTH_MEASURE(2.5)`.
+    pub fn into_value(self) -> Result<Parameter, ConversionError> {
+        let type_name = self.type_name;
+        let mut parameters = self.parameters.into_iter();
+        match (parameters.next(), parameters.next()) {
+            (Some(parameter), None) => Ok(parameter),
+            _ => Err(ConversionError::new(
+                "single value",
+                format!("{} parameter list", type_name),
+            )),
+        }
+    }
+}
+
+impl<T: ?Sized> TypedRef<T> {
+    pub fn new(id: i64) -> Self {
+        TypedRef {
+            id,
+            entity_type: PhantomData,
+        }
+    }
+
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+
+    /// The reference without its entity type.
+    pub fn untyped(&self) -> EntityRef {
+        EntityRef(self.id)
+    }
+}
+
+impl<T: ?Sized> Clone for TypedRef<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: ?Sized> Copy for TypedRef<T> {}
+
+impl<T: ?Sized> Default for TypedRef<T> {
+    fn default() -> Self {
+        TypedRef::new(0)
+    }
+}
+
+impl<T: ?Sized> PartialEq for TypedRef<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl<T: ?Sized> Eq for TypedRef<T> {}
+
+impl<T: ?Sized> Hash for TypedRef<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state)
+    }
+}
+
+impl<T: ?Sized> Debug for TypedRef<T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_tuple("TypedRef").field(&self.id).finish()
+    }
+}
+
+impl<T: ?Sized> From<TypedRef<T>> for EntityRef {
+    fn from(entity_ref: TypedRef<T>) -> Self {
+        entity_ref.untyped()
+    }
+}
+
+impl Binary {
+    pub fn new() -> Self {
+        Binary::default()
+    }
+
+    /// Number of bits.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, index: usize) -> Option<bool> {
+        if index < self.len {
+            Some(self.bytes[index / 8] & (0x80 >> (index % 8)) != 0)
+        } else {
+            None
+        }
+    }
+
+    pub fn push(&mut self, bit: bool) {
+        if self.len.is_multiple_of(8) {
+            self.bytes.push(0);
+        }
+        if bit {
+            self.bytes[self.len / 8] |= 0x80 >> (self.len % 8);
+        }
+        self.len += 1;
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+        (0..self.len).map(move |index| self.bytes[index / 8] & (0x80 >> (index % 8)) != 0)
+    }
+
+    /// The bits packed into bytes, the unused low bits of the last byte are zero.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl std::iter::FromIterator<bool> for Binary {
+    fn from_iter<I: IntoIterator<Item = bool>>(iter: I) -> Self {
+        let mut binary = Binary::new();
+        for bit in iter {
+            binary.push(bit);
+        }
+        binary
+    }
+}
+
+impl UnTypedParameter {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            UnTypedParameter::List(_) => "list",
+            UnTypedParameter::EnumValue(_) => "enum value",
+            UnTypedParameter::EntityRef(_) => "entity reference",
+            UnTypedParameter::ConstantRef(_) => "constant reference",
+            UnTypedParameter::Integer(_) => "integer",
+            UnTypedParameter::Real(_) => "real",
+            UnTypedParameter::String(_) => "string",
+            UnTypedParameter::Binary(_) => "binary",
+            UnTypedParameter::ValueRef(_) => "value reference",
+            UnTypedParameter::Resource(_) => "resource",
+            UnTypedParameter::Null => "null",
+        }
+    }
+}
+
+impl TryFrom<Parameter> for bool {
+    type Error = ConversionError;
+
+    fn try_from(parameter: Parameter) -> Result<Self, Self::Error> {
+        match parameter {
+            Parameter::UnTypedParameter(parameter) => parameter.try_into(),
+            _ => Err(ConversionError::unexpected("boolean", &parameter)),
+        }
+    }
+}
+
+impl TryFrom<Parameter> for Option<bool> {
+    type Error = ConversionError;
+
+    fn try_from(parameter: Parameter) -> Result<Self, Self::Error> {
         match parameter {
-            Parameter::UnTypedParameter(parameter) => parameter.into(),
-            _ => panic!("can not convert"),
+            Parameter::UnTypedParameter(parameter) => parameter.try_into(),
+            Parameter::OmittedParameter => Ok(None),
+            _ => Err(ConversionError::unexpected("logical", &parameter)),
         }
     }
 }
 
-impl From<Parameter> for Option<bool> {
-    fn from(parameter: Parameter) -> Self {
+impl TryFrom<Parameter> for i64 {
+    type Error = ConversionError;
+
+    fn try_from(parameter: Parameter) -> Result<Self, Self::Error> {
         match parameter {
-            Parameter::UnTypedParameter(parameter) => parameter.into(),
-            Parameter::OmittedParameter => None,
-            _ => panic!("can not convert"),
+            Parameter::UnTypedParameter(parameter) => parameter.try_into(),
+            _ => Err(ConversionError::unexpected("integer", &parameter)),
         }
     }
 }
 
-impl From<Parameter> for i64 {
-    fn from(parameter: Parameter) -> Self {
+impl TryFrom<Parameter> for Real {
+    type Error = ConversionError;
+
+    fn try_from(parameter: Parameter) -> Result<Self, Self::Error> {
         match parameter {
-            Parameter::UnTypedParameter(parameter) => parameter.into(),
-            _ => panic!("can not convert"),
+            Parameter::UnTypedParameter(parameter) => parameter.try_into(),
+            _ => Err(ConversionError::unexpected("real", &parameter)),
         }
     }
 }
 
-impl From<Parameter> for Real {
-    fn from(parameter: Parameter) -> Self {
+impl TryFrom<Parameter> for String {
+    type Error = ConversionError;
+
+    fn try_from(parameter: Parameter) -> Result<Self, Self::Error> {
         match parameter {
-            Parameter::UnTypedParameter(parameter) => parameter.into(),
-            _ => panic!("can not convert"),
+            Parameter::UnTypedParameter(parameter) => parameter.try_into(),
+            _ => Err(ConversionError::unexpected("string", &parameter)),
         }
     }
 }
 
-impl From<Parameter> for String {
-    fn from(parameter: Parameter) -> Self {
+impl TryFrom<Parameter> for EntityRef {
+    type Error = ConversionError;
+
+    fn try_from(parameter: Parameter) -> Result<Self, Self::Error> {
         match parameter {
-            Parameter::UnTypedParameter(parameter) => parameter.into(),
-            _ => panic!("can not convert"),
+            Parameter::UnTypedParameter(parameter) => parameter.try_into(),
+            Parameter::OmittedParameter => Ok(EntityRef(0)),
+            _ => Err(ConversionError::unexpected("entity reference", &parameter)),
         }
     }
 }
 
-impl From<Parameter> for EntityRef {
-    fn from(parameter: Parameter) -> Self {
+impl<T: ?Sized> TryFrom<Parameter> for TypedRef<T> {
+    type Error = ConversionError;
+
+    fn try_from(parameter: Parameter) -> Result<Self, Self::Error> {
+        let EntityRef(id) = parameter.try_into()?;
+        Ok(TypedRef::new(id))
+    }
+}
+
+impl TryFrom<Parameter> for Binary {
+    type Error = ConversionError;
+
+    fn try_from(parameter: Parameter) -> Result<Self, Self::Error> {
         match parameter {
-            Parameter::UnTypedParameter(parameter) => parameter.into(),
-            Parameter::OmittedParameter => EntityRef(0),
-            _ => panic!("can not convert"),
+            Parameter::UnTypedParameter(parameter) => parameter.try_into(),
+            _ => Err(ConversionError::unexpected("binary", &parameter)),
         }
     }
 }
 
-impl<T: From<Parameter>> From<Parameter> for Vec<T> {
-    fn from(parameter: Parameter) -> Self {
+impl<T: TryFrom<Parameter, Error = ConversionError>> TryFrom<Parameter> for Vec<T> {
+    type Error = ConversionError;
+
+    fn try_from(parameter: Parameter) -> Result<Self, Self::Error> {
         match parameter {
-            Parameter::UnTypedParameter(untyped_parameter) => match untyped_parameter {
-                UnTypedParameter::List(list) => list.into_iter().map(|item| item.into()).collect(),
-                _ => panic!("parameter is not an list"),
-            },
-            Parameter::OmittedParameter => Vec::new(),
-            _ => panic!("parameter is not an list"),
+            Parameter::UnTypedParameter(UnTypedParameter::List(list)) => {
+                list.into_iter().map(|item| item.try_into()).collect()
+            }
+            Parameter::OmittedParameter => Ok(Vec::new()),
+            _ => Err(ConversionError::unexpected("list", &parameter)),
         }
     }
 }
 
-impl<T: From<Parameter> + Eq + Hash> From<Parameter> for HashSet<T> {
-    fn from(parameter: Parameter) -> Self {
+impl<T: TryFrom<Parameter, Error = ConversionError> + Eq + Hash> TryFrom<Parameter> for HashSet<T> {
+    type Error = ConversionError;
+
+    fn try_from(parameter: Parameter) -> Result<Self, Self::Error> {
         match parameter {
-            Parameter::UnTypedParameter(parameter) => parameter.into(),
-            Parameter::OmittedParameter => HashSet::new(),
-            _ => panic!("can not convert"),
+            Parameter::UnTypedParameter(parameter) => parameter.try_into(),
+            Parameter::OmittedParameter => Ok(HashSet::new()),
+            _ => Err(ConversionError::unexpected("set", &parameter)),
         }
     }
 }
 
-impl From<UnTypedParameter> for bool {
-    fn from(parameter: UnTypedParameter) -> Self {
+impl TryFrom<UnTypedParameter> for bool {
+    type Error = ConversionError;
+
+    fn try_from(parameter: UnTypedParameter) -> Result<Self, Self::Error> {
         match parameter {
             UnTypedParameter::EnumValue(value) => match value.as_str() {
-                "T" => true,
-                "F" => false,
-                _ => panic!("invalid boolean value {}", value),
+                "T" => Ok(true),
+                "F" => Ok(false),
+                _ => Err(ConversionError::new("boolean", format!(".{}.", value))),
             },
-            _ => panic!("can not convert to boolean"),
+            _ => Err(ConversionError::new("boolean", parameter.kind())),
         }
     }
 }
 
-impl From<UnTypedParameter> for Option<bool> {
-    fn from(parameter: UnTypedParameter) -> Self {
+impl TryFrom<UnTypedParameter> for Option<bool> {
+    type Error = ConversionError;
+
+    fn try_from(parameter: UnTypedParameter) -> Result<Self, Self::Error> {
         match parameter {
             UnTypedParameter::EnumValue(value) => match value.as_str() {
-                "T" => Some(true),
-                "F" => Some(false),
-                "U" => None,
-                _ => panic!("invalid boolean value {}", value),
+                "T" => Ok(Some(true)),
+                "F" => Ok(Some(false)),
+                "U" => Ok(None),
+                _ => Err(ConversionError::new("logical", format!(".{}.", value))),
             },
-            _ => panic!("can not convert to boolean"),
+            _ => Err(ConversionError::new("logical", parameter.kind())),
         }
     }
 }
 
-impl From<UnTypedParameter> for i64 {
-    fn from(parameter: UnTypedParameter) -> Self {
+impl TryFrom<UnTypedParameter> for i64 {
+    type Error = ConversionError;
+
+    fn try_from(parameter: UnTypedParameter) -> Result<Self, Self::Error> {
         match parameter {
-            UnTypedParameter::Integer(number) => number,
-            _ => panic!("can not convert to integer"),
+            UnTypedParameter::Integer(number) => Ok(number),
+            _ => Err(ConversionError::new("integer", parameter.kind())),
         }
     }
 }
 
-impl From<UnTypedParameter> for Real {
-    fn from(parameter: UnTypedParameter) -> Self {
+impl TryFrom<UnTypedParameter> for Real {
+    type Error = ConversionError;
+
+    fn try_from(parameter: UnTypedParameter) -> Result<Self, Self::Error> {
         match parameter {
-            UnTypedParameter::Real(number) => Real(number),
-            _ => panic!("can not convert to real"),
+            UnTypedParameter::Real(number) => Ok(Real(number)),
+            _ => Err(ConversionError::new("real", parameter.kind())),
         }
     }
 }
 
-impl From<UnTypedParameter> for String {
-    fn from(parameter: UnTypedParameter) -> Self {
+impl TryFrom<UnTypedParameter> for String {
+    type Error = ConversionError;
+
+    fn try_from(parameter: UnTypedParameter) -> Result<Self, Self::Error> {
         match parameter {
-            UnTypedParameter::String(string) => string,
-            UnTypedParameter::Null => String::default(),
-            _ => panic!("can not convert to string"),
+            UnTypedParameter::String(string) => Ok(string),
+            UnTypedParameter::Null => Ok(String::default()),
+            _ => Err(ConversionError::new("string", parameter.kind())),
         }
     }
 }
 
-impl From<UnTypedParameter> for EntityRef {
-    fn from(parameter: UnTypedParameter) -> Self {
+impl TryFrom<UnTypedParameter> for EntityRef {
+    type Error = ConversionError;
+
+    fn try_from(parameter: UnTypedParameter) -> Result<Self, Self::Error> {
         match parameter {
-            UnTypedParameter::EntityRef(id) => EntityRef(id),
-            _ => panic!("can not convert"),
+            UnTypedParameter::EntityRef(id) => Ok(EntityRef(id)),
+            _ => Err(ConversionError::new("entity reference", parameter.kind())),
         }
     }
 }
 
-impl<T: From<Parameter> + Eq + Hash> From<UnTypedParameter> for HashSet<T> {
-    fn from(parameter: UnTypedParameter) -> Self {
+impl TryFrom<UnTypedParameter> for Binary {
+    type Error = ConversionError;
+
+    fn try_from(parameter: UnTypedParameter) -> Result<Self, Self::Error> {
+        match parameter {
+            UnTypedParameter::Binary(binary) => Ok(binary),
+            _ => Err(ConversionError::new("binary", parameter.kind())),
+        }
+    }
+}
+
+impl<T: TryFrom<Parameter, Error = ConversionError> + Eq + Hash> TryFrom<UnTypedParameter> for HashSet<T> {
+    type Error = ConversionError;
+
+    fn try_from(parameter: UnTypedParameter) -> Result<Self, Self::Error> {
         match parameter {
-            UnTypedParameter::List(values) => HashSet::from_iter(values.into_iter().map(|value| T::from(value))),
-            _ => panic!("can not convert"),
+            UnTypedParameter::List(values) => values.into_iter().map(T::try_from).collect(),
+            _ => Err(ConversionError::new("set", parameter.kind())),
         }
     }
 }
@@ -0,0 +1,174 @@
+use super::{EntityRef, FromEntity, StepError, TypedRef};
+use std::any::{Any, TypeId};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Tells whether an entity instance has the type a reference expects.
+pub type EntityCheck = fn(&dyn Any) -> bool;
+
+pub fn entity_check<T: FromEntity + ?Sized>(entity: &dyn Any) -> bool {
+    T::resolve(entity).is_some()
+}
+
+/// Attribute values which may hold entity references.
+pub trait References {
+    /// Call `visit` with each referenced id and the check of its type, `None` for an untyped `EntityRef`.
+    fn references(&self, visit: &mut dyn FnMut(i64, Option<EntityCheck>));
+}
+
+impl<T: FromEntity + ?Sized> References for TypedRef<T> {
+    fn references(&self, visit: &mut dyn FnMut(i64, Option<EntityCheck>)) {
+        visit(self.id(), Some(entity_check::<T>))
+    }
+}
+
+impl References for EntityRef {
+    fn references(&self, visit: &mut dyn FnMut(i64, Option<EntityCheck>)) {
+        visit(self.0, None)
+    }
+}
+
+impl<T: References> References for Option<T> {
+    fn references(&self, visit: &mut dyn FnMut(i64, Option<EntityCheck>)) {
+        if let Some(value) = self {
+            value.references(visit)
+        }
+    }
+}
+
+impl<T: References> References for Vec<T> {
+    fn references(&self, visit: &mut dyn FnMut(i64, Option<EntityCheck>)) {
+        for value in self {
+            value.references(visit)
+        }
+    }
+}
+
+impl<T: References> References for HashSet<T> {
+    fn references(&self, visit: &mut dyn FnMut(i64, Option<EntityCheck>)) {
+        for value in self {
+            value.references(visit)
+        }
+    }
+}
+
+/// Entity data types whose references can be validated.
+pub trait AttributeReferences {
+    /// Call `visit` with the attribute name, the expected EXPRESS type, the referenced id and its check.
+    fn attribute_references(&self, visit: &mut dyn FnMut(&'static str, &'static str, i64, EntityCheck));
+}
+
+pub type ReferenceVisitor = fn(&dyn Any, &mut dyn FnMut(&'static str, &'static str, i64, EntityCheck));
+
+pub fn reference_visitor<T: AttributeReferences + Any>(
+    entity: &dyn Any,
+    visit: &mut dyn FnMut(&'static str, &'static str, i64, EntityCheck),
+) {
+    let entity = entity
+        .downcast_ref::<T>()
+        .expect("entity type does not match its reference visitor");
+    entity.attribute_references(visit)
+}
+
+/// Call the reference visitors of an entity instance, or of the parts of a complex one.
+pub fn visit_references(
+    entity: &dyn Any,
+    visitors: &HashMap<TypeId, ReferenceVisitor>,
+    visit: &mut dyn FnMut(&'static str, &'static str, i64, EntityCheck),
+) {
+    if let Some(parts) = entity.downcast_ref::<super::ComplexEntity>() {
+        for part in parts {
+            if let Some(visitor) = visitors.get(&(**part).type_id()) {
+                visitor(&**part, visit);
+            }
+        }
+    } else if let Some(visitor) = visitors.get(&entity.type_id()) {
+        visitor(entity, visit);
+    }
+}
+
+/// Check that the references of the entities point to existing entities of the expected types.
+///
+/// Omitted references and the parts of complex instances without entity struct are not checked.
+pub fn validate_references(
+    entities: &BTreeMap<i64, Box<dyn Any>>,
+    visitors: &HashMap<TypeId, ReferenceVisitor>,
+) -> Vec<StepError> {
+    let mut errors = Vec::new();
+    for (&id, entity) in entities {
+        let mut visit = |attribute: &'static str, expected: &'static str, reference: i64, check: EntityCheck| {
+            if reference == 0 {
+                return;
+            }
+            match entities.get(&reference) {
+                None => errors.push(StepError::DanglingReference {
+                    id,
+                    attribute,
+                    reference,
+                    expected,
+                }),
+                Some(target) if !check(&**target) => errors.push(StepError::TypeMismatch {
+                    id,
+                    attribute,
+                    reference,
+                    expected,
+                }),
+                Some(_) => {}
+            }
+        };
+        visit_references(&**entity, visitors, &mut visit);
+    }
+    errors
+}
+
+/// The entity instances referring to each entity instance, as the EXPRESS `USEDIN` function sees them.
+#[derive(Debug, Default)]
+pub struct UsedInIndex {
+    referrers: HashMap<i64, Vec<(i64, &'static str)>>,
+}
+
+impl UsedInIndex {
+    /// Record the references of the entity instance `id`.
+    pub fn insert(&mut self, id: i64, entity: &dyn Any, visitors: &HashMap<TypeId, ReferenceVisitor>) {
+        visit_references(entity, visitors, &mut |attribute, _, reference, _| {
+            if reference != 0 {
+                self.referrers.entry(reference).or_default().push((id, attribute));
+            }
+        });
+    }
+
+    /// Forget the references of the entity instance `id`, when it is removed or replaced.
+    pub fn remove(&mut self, id: i64, entity: &dyn Any, visitors: &HashMap<TypeId, ReferenceVisitor>) {
+        visit_references(entity, visitors, &mut |_, _, reference, _| {
+            if let Some(referrers) = self.referrers.get_mut(&reference) {
+                referrers.retain(|(referrer, _)| *referrer != id);
+            }
+        });
+    }
+
+    /// The ids and attribute names referring to the entity instance `id`, once per reference.
+    pub fn used_in(&self, id: i64) -> &[(i64, &'static str)] {
+        self.referrers.get(&id).map_or(&[], Vec::as_slice)
+    }
+
+    /// The entity instances of type `T` referring to the entity instance `id` by their `attribute`.
+    ///
+    /// Like `USEDIN`, an instance is listed once per reference and an empty `attribute` matches any attribute.
+    /// Attribute names are in snake case as in the entity structs.
+    pub fn used_in_typed<T: FromEntity + ?Sized>(
+        &self,
+        entities: &BTreeMap<i64, Box<dyn Any>>,
+        id: i64,
+        attribute: &str,
+    ) -> Vec<TypedRef<T>> {
+        self.used_in(id)
+            .iter()
+            .filter(|(referrer, name)| {
+                (attribute.is_empty() || *name == attribute)
+                    && entities
+                        .get(referrer)
+                        .is_some_and(|entity| entity_check::<T>(&**entity))
+            })
+            .map(|(referrer, _)| TypedRef::new(*referrer))
+            .collect()
+    }
+}
@@ -0,0 +1,397 @@
+use super::header::*;
+use super::reader::ComplexEntity;
+use super::structure::*;
+use super::Real;
+use std::any::{Any, TypeId};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::{self, Display, Formatter, Write as _};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Write a REAL so that it always contains a decimal point, e.g. `1.`, `0.5` or `1.E-07`.
+///
+/// NaN and infinity have no encoding in ISO 10303-21 and fail with `fmt::Error`.
+pub fn write_real(f: &mut dyn fmt::Write, value: f64) -> fmt::Result {
+    if !value.is_finite() {
+        return Err(fmt::Error);
+    }
+    let text = format!("{:?}", value);
+    let (mantissa, exponent) = match text.find('e') {
+        Some(index) => (&text[..index], Some(&text[index + 1..])),
+        None => (text.as_str(), None),
+    };
+    f.write_str(mantissa.strip_suffix(".0").unwrap_or(mantissa))?;
+    if !mantissa.contains('.') || mantissa.ends_with(".0") {
+        f.write_char('.')?;
+    }
+    if let Some(exponent) = exponent {
+        write!(f, "E{}", exponent)?;
+    }
+    Ok(())
+}
+
+/// Write a STRING enclosed in apostrophes, using only the basic alphabet.
+///
+/// Other characters are encoded as `\X\hh` up to U+00FF, and as `\X2\` or `\X4\` runs above.
+pub fn write_string(f: &mut dyn fmt::Write, value: &str) -> fmt::Result {
+    f.write_char('\'')?;
+    let mut run = 0;
+    for c in value.chars() {
+        let width = match c {
+            '\0'..='\u{FF}' => 0,
+            '\u{100}'..='\u{FFFF}' => 2,
+            _ => 4,
+        };
+        if width != run && run != 0 {
+            f.write_str("\\X0\\")?;
+        }
+        if width != run && width != 0 {
+            write!(f, "\\X{}\\", width)?;
+        }
+        run = width;
+        match c {
+            '\'' => f.write_str("''")?,
+            '\\' => f.write_str("\\\\")?,
+            ' '..='~' => f.write_char(c)?,
+            _ if width == 0 => write!(f, "\\X\\{:02X}", c as u32)?,
+            _ if width == 2 => write!(f, "{:04X}", c as u32)?,
+            _ => write!(f, "{:08X}", c as u32)?,
+        }
+    }
+    if run != 0 {
+        f.write_str("\\X0\\")?;
+    }
+    f.write_char('\'')
+}
+
+fn write_list(f: &mut Formatter, parameters: &[Parameter]) -> fmt::Result {
+    f.write_char('(')?;
+    for (index, parameter) in parameters.iter().enumerate() {
+        if index > 0 {
+            f.write_char(',')?;
+        }
+        write!(f, "{}", parameter)?;
+    }
+    f.write_char(')')
+}
+
+impl Display for Parameter {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Parameter::TypedParameter(parameter) => parameter.fmt(f),
+            Parameter::UnTypedParameter(parameter) => parameter.fmt(f),
+            Parameter::OmittedParameter => f.write_char('*'),
+        }
+    }
+}
+
+impl Display for TypedParameter {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(&self.type_name)?;
+        write_list(f, &self.parameters)
+    }
+}
+
+impl Display for UnTypedParameter {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            UnTypedParameter::List(parameters) => write_list(f, parameters),
+            UnTypedParameter::EnumValue(value) => write!(f, ".{}.", value),
+            UnTypedParameter::EntityRef(id) => write!(f, "#{}", id),
+            UnTypedParameter::ConstantRef(name) => write!(f, "#{}", name),
+            UnTypedParameter::Integer(value) => write!(f, "{}", value),
+            UnTypedParameter::Real(value) => write_real(f, *value),
+            UnTypedParameter::String(value) => write_string(f, value),
+            UnTypedParameter::Binary(binary) => binary.fmt(f),
+            UnTypedParameter::ValueRef(id) => write!(f, "@{}", id),
+            UnTypedParameter::Resource(uri) => write!(f, "<{}>", uri),
+            UnTypedParameter::Null => f.write_char('$'),
+        }
+    }
+}
+
+impl Display for Binary {
+    /// Write the bits as hex digits, preceded by the number of padding bits in the first digit.
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let unused = (4 - self.len() % 4) % 4;
+        write!(f, "\"{}", unused)?;
+        let bits = std::iter::repeat_n(false, unused)
+            .chain(self.iter())
+            .collect::<Vec<_>>();
+        for digit in bits.chunks(4) {
+            let value = digit.iter().fold(0, |value, &bit| value << 1 | bit as u32);
+            write!(f, "{:X}", value)?;
+        }
+        f.write_char('"')
+    }
+}
+
+impl Display for EntityInstance {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "#{}=", self.id)?;
+        if self.value.len() == 1 {
+            write!(f, "{}", self.value[0])?;
+        } else {
+            f.write_char('(')?;
+            for typed_parameter in &self.value {
+                write!(f, "{}", typed_parameter)?;
+            }
+            f.write_char(')')?;
+        }
+        f.write_char(';')
+    }
+}
+
+impl Display for Anchor {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "<{}>={}", self.name, self.item)?;
+        for (name, item) in &self.tags {
+            write!(f, "{{{}:{}}}", name, item)?;
+        }
+        f.write_char(';')
+    }
+}
+
+impl Display for Reference {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}=<{}>;", self.name, self.resource)
+    }
+}
+
+impl Display for DataSection {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str("DATA")?;
+        if !self.parameters.is_empty() {
+            write_list(f, &self.parameters)?;
+        }
+        writeln!(f, ";")?;
+        for instance in &self.instances {
+            writeln!(f, "{}", instance)?;
+        }
+        writeln!(f, "ENDSEC;")
+    }
+}
+
+impl Display for ExchangeFile {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(f, "ISO-10303-21;")?;
+        writeln!(f, "HEADER;")?;
+        for header_entity in &self.header {
+            writeln!(f, "{};", header_entity)?;
+        }
+        writeln!(f, "ENDSEC;")?;
+        if !self.anchors.is_empty() {
+            writeln!(f, "ANCHOR;")?;
+            for anchor in &self.anchors {
+                writeln!(f, "{}", anchor)?;
+            }
+            writeln!(f, "ENDSEC;")?;
+        }
+        if !self.references.is_empty() {
+            writeln!(f, "REFERENCE;")?;
+            for reference in &self.references {
+                writeln!(f, "{}", reference)?;
+            }
+            writeln!(f, "ENDSEC;")?;
+        }
+        for section in &self.data {
+            write!(f, "{}", section)?;
+        }
+        writeln!(f, "END-ISO-10303-21;")?;
+        for signature in &self.signatures {
+            writeln!(f, "SIGNATURE{}ENDSEC;", signature)?;
+        }
+        Ok(())
+    }
+}
+
+impl ExchangeFile {
+    /// Write the exchange file in ISO 10303-21 clear text encoding.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        let mut writer = BufWriter::new(std::fs::File::create(path)?);
+        write!(writer, "{}", self)?;
+        writer.flush()
+    }
+}
+
+/// Conversion of an attribute value back to a parameter, the inverse of `TryFrom<Parameter>`.
+pub trait ToParameter {
+    fn to_parameter(&self) -> Parameter;
+}
+
+/// Entity data types which can be written as (a part of) an entity instance.
+pub trait ToParameters {
+    const TYPE_NAME: &'static str;
+
+    /// All explicit attributes, inherited ones first.
+    fn to_parameters(&self) -> Vec<Parameter>;
+
+    /// The attributes declared by this entity only, as written in a complex instance.
+    fn to_own_parameters(&self) -> Vec<Parameter> {
+        self.to_parameters()
+    }
+}
+
+pub type TypedParameterWriter = fn(&dyn Any, bool) -> TypedParameter;
+
+pub fn typed_parameter_writer<T: ToParameters + Any>(entity: &dyn Any, own_parameters_only: bool) -> TypedParameter {
+    let entity = entity
+        .downcast_ref::<T>()
+        .expect("entity type does not match its writer");
+    TypedParameter {
+        type_name: T::TYPE_NAME.to_string(),
+        parameters: if own_parameters_only {
+            entity.to_own_parameters()
+        } else {
+            entity.to_parameters()
+        },
+    }
+}
+
+pub fn optional_parameter<T: ToParameter>(value: &Option<T>) -> Parameter {
+    match value {
+        Some(value) => value.to_parameter(),
+        None => Parameter::UnTypedParameter(UnTypedParameter::Null),
+    }
+}
+
+pub trait StepWriter {
+    fn schema_name(&self) -> &'static str;
+    fn entities(&self) -> &BTreeMap<i64, Box<dyn Any>>;
+    fn typed_parameter_writers(&self) -> HashMap<TypeId, TypedParameterWriter>;
+
+    fn create_typed_parameters(
+        &self,
+        writers: &HashMap<TypeId, TypedParameterWriter>,
+        entity: &dyn Any,
+    ) -> Option<Vec<TypedParameter>> {
+        if let Some(parts) = entity.downcast_ref::<ComplexEntity>() {
+            let mut value = parts
+                .iter()
+                .map(|part| match part.downcast_ref::<TypedParameter>() {
+                    Some(typed_parameter) => Some(typed_parameter.clone()),
+                    None => writers.get(&(**part).type_id()).map(|writer| writer(&**part, true)),
+                })
+                .collect::<Option<Vec<_>>>()?;
+            value.sort_by(|a, b| a.type_name.cmp(&b.type_name));
+            Some(value)
+        } else {
+            writers.get(&entity.type_id()).map(|writer| vec![writer(entity, false)])
+        }
+    }
+
+    /// Write all entities of the model as an ISO 10303-21 file, with a minimal header.
+    fn write<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        let name = path
+            .as_ref()
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let header = Header {
+            name,
+            schema_identifiers: vec![SchemaName {
+                name: self.schema_name().to_string(),
+                object_identifier: Vec::new(),
+            }],
+            ..Header::default()
+        };
+        self.write_with_header(path, &header)
+    }
+
+    /// Write all entities of the model as an ISO 10303-21 file, e.g. with the header of the file it was read from.
+    fn write_with_header<P: AsRef<Path>>(&self, path: P, header: &Header) -> std::io::Result<()> {
+        let writers = self.typed_parameter_writers();
+        let mut writer = BufWriter::new(std::fs::File::create(path)?);
+        writeln!(writer, "ISO-10303-21;")?;
+        writeln!(writer, "HEADER;")?;
+        for header_entity in header.to_entities() {
+            writeln!(writer, "{};", header_entity)?;
+        }
+        writeln!(writer, "ENDSEC;")?;
+        writeln!(writer, "DATA;")?;
+        for (id, entity) in self.entities() {
+            let value = self.create_typed_parameters(&writers, &**entity).ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::InvalidData, format!("#{} can not be written", id))
+            })?;
+            writeln!(writer, "{}", EntityInstance { id: *id, value })?;
+        }
+        writeln!(writer, "ENDSEC;")?;
+        writeln!(writer, "END-ISO-10303-21;")?;
+        writer.flush()
+    }
+}
+
+impl ToParameter for bool {
+    fn to_parameter(&self) -> Parameter {
+        let value = if *self { "T" } else { "F" };
+        Parameter::UnTypedParameter(UnTypedParameter::EnumValue(value.to_string()))
+    }
+}
+
+impl ToParameter for Option<bool> {
+    fn to_parameter(&self) -> Parameter {
+        let value = match self {
+            Some(true) => "T",
+            Some(false) => "F",
+            None => "U",
+        };
+        Parameter::UnTypedParameter(UnTypedParameter::EnumValue(value.to_string()))
+    }
+}
+
+impl ToParameter for i64 {
+    fn to_parameter(&self) -> Parameter {
+        Parameter::UnTypedParameter(UnTypedParameter::Integer(*self))
+    }
+}
+
+impl ToParameter for Real {
+    fn to_parameter(&self) -> Parameter {
+        Parameter::UnTypedParameter(UnTypedParameter::Real(self.0))
+    }
+}
+
+impl ToParameter for String {
+    fn to_parameter(&self) -> Parameter {
+        Parameter::UnTypedParameter(UnTypedParameter::String(self.clone()))
+    }
+}
+
+impl ToParameter for EntityRef {
+    fn to_parameter(&self) -> Parameter {
+        // an omitted reference is read as `EntityRef(0)`
+        if self.0 == 0 {
+            Parameter::OmittedParameter
+        } else {
+            Parameter::UnTypedParameter(UnTypedParameter::EntityRef(self.0))
+        }
+    }
+}
+
+impl<T: ?Sized> ToParameter for TypedRef<T> {
+    fn to_parameter(&self) -> Parameter {
+        self.untyped().to_parameter()
+    }
+}
+
+impl ToParameter for Binary {
+    fn to_parameter(&self) -> Parameter {
+        Parameter::UnTypedParameter(UnTypedParameter::Binary(self.clone()))
+    }
+}
+
+impl<T: ToParameter> ToParameter for Vec<T> {
+    fn to_parameter(&self) -> Parameter {
+        Parameter::UnTypedParameter(UnTypedParameter::List(
+            self.iter().map(ToParameter::to_parameter).collect(),
+        ))
+    }
+}
+
+impl<T: ToParameter> ToParameter for HashSet<T> {
+    fn to_parameter(&self) -> Parameter {
+        Parameter::UnTypedParameter(UnTypedParameter::List(
+            self.iter().map(ToParameter::to_parameter).collect(),
+        ))
+    }
+}
@@ -0,0 +1,28 @@
+use iso_10303::step::StepReader;
+
+#[path = "../examples/family/reader.rs"]
+mod reader;
+use reader::*;
+
+#[test]
+fn add_entity_replaces_instance() {
+    let mut reader = ExampleReader::new();
+    reader.read("examples/family/family.stp").unwrap();
+    assert_eq!(reader.used_in(2), &[(1, "wife")]);
+    assert_eq!(reader.used_in(3), &[(1, "children"), (2, "children")]);
+
+    // a male without wife and children in place of #1
+    reader.add_entity(1, Male::default());
+    assert!(reader.used_in(2).is_empty());
+    assert_eq!(reader.used_in(3), &[(2, "children")]);
+    let males = reader.get_entities::<Male>().map(|(id, _)| id).collect::<Vec<_>>();
+    assert_eq!(males, vec![3, 4, 1]);
+    assert!(reader.validate().is_empty());
+
+    // a female in place of a male
+    reader.add_entity(3, Female::default());
+    let males = reader.get_entities::<Male>().map(|(id, _)| id).collect::<Vec<_>>();
+    assert_eq!(males, vec![4, 1]);
+    assert_eq!(reader.get_type_name(3), std::any::type_name::<Female>());
+    assert!(reader.validate().is_empty());
+}
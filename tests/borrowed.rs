@@ -0,0 +1,93 @@
+use iso_10303::step::borrowed::{self, MappedFile, Statements, UnTypedParameter};
+use iso_10303::step::parser::{self, Statement};
+use std::convert::TryInto;
+
+/// Parse all statements of `input` with the parser of owned values.
+fn owned_statements(input: &[u8]) -> Vec<(usize, Statement)> {
+    let parser = parser::next_statement();
+    let mut statements = Vec::new();
+    let mut position = 0;
+    while let (Some(statement), next) = parser.parse_at(input, position).unwrap() {
+        statements.push(statement);
+        position = next;
+    }
+    statements
+}
+
+fn assert_same_statements(input: &[u8], borrowed: Statements) {
+    let borrowed = borrowed
+        .map(|statement| {
+            let (position, statement) = statement.unwrap();
+            (position, statement.try_into().unwrap())
+        })
+        .collect::<Vec<(usize, Statement)>>();
+    let owned = owned_statements(input);
+    assert!(!owned.is_empty());
+    assert_eq!(borrowed, owned);
+}
+
+#[test]
+fn sample_files_are_parsed_the_same() {
+    for path in &[
+        "parts/examples/ap203_example.stp",
+        "parts/examples/ap214_example.stp",
+        "examples/family/family.stp",
+    ] {
+        // the sample files are not modified while the tests run
+        let file = unsafe { MappedFile::open(path) }.unwrap();
+        assert_same_statements(file.bytes(), file.statements());
+    }
+}
+
+#[test]
+fn all_kinds_of_statements_are_parsed_the_same() {
+    let input = "ISO-10303-21;
+HEADER;
+FILE_DESCRIPTION(('a /* not a comment */'), '3;1');
+/* a comment; with a semicolon */
+FILE_NAME('', '', (''), (''), '', '', '');
+FILE_SCHEMA(('TEST { 1 2 }'));
+ENDSEC;
+ANCHOR;
+<point>=#1{colour:'red'};
+ENDSEC;
+REFERENCE;
+#100=<parts.stp#bolt>;
+@2=<values.stp#weight>;
+ENDSEC;
+DATA('DS1', ('TEST'));
+#1=POINT('it''s \\X2\\00E4\\X0\\', \"3F\", .T., #TEN, @2, <a.stp#b>, (1, -2., 3.5E-3), LENGTH(1.), $, *);
+#2=(A(1) B((2, 3)) C());
+ENDSEC;
+END-ISO-10303-21;
+";
+    assert_same_statements(input.as_bytes(), Statements::new(input.as_bytes()));
+}
+
+#[test]
+fn values_are_borrowed_from_the_input() {
+    let input = b"  #7 = POINT(.T., 'it''s', <a.stp#b>);";
+    let statement = parser::borrowed_statement().parse(input).unwrap();
+    let instance = match statement {
+        borrowed::Statement::Instance(instance) => instance,
+        statement => panic!("instance expected, found {:?}", statement),
+    };
+    assert_eq!(instance.id, 7);
+    assert_eq!(instance.value[0].type_name, "POINT");
+    let parameters = &instance.value[0].parameters;
+    assert_eq!(
+        parameters[0],
+        borrowed::Parameter::UnTypedParameter(UnTypedParameter::EnumValue("T"))
+    );
+    match &parameters[1] {
+        borrowed::Parameter::UnTypedParameter(UnTypedParameter::String(raw)) => {
+            assert_eq!(raw.0, b"it''s");
+            assert_eq!(raw.decode().unwrap(), "it's");
+        }
+        parameter => panic!("string expected, found {:?}", parameter),
+    }
+    assert_eq!(
+        parameters[2],
+        borrowed::Parameter::UnTypedParameter(UnTypedParameter::Resource("a.stp#b"))
+    );
+}
@@ -0,0 +1,78 @@
+use iso_10303::step::{StepError, StepReader};
+
+#[path = "derived/reader.rs"]
+mod reader;
+use reader::*;
+
+const DATA: &str = "ISO-10303-21;
+HEADER;
+FILE_DESCRIPTION((''), '2;1');
+FILE_NAME('', '', (''), (''), '', '', '');
+FILE_SCHEMA(('DERIVED'));
+ENDSEC;
+DATA;
+#1 = PERSON('Tom', (1963, 4, 9));
+#2 = PERSON('Teresa', (1963, 7, 3));
+#3 = PERSON('Dick', (1993, 9, 3));
+#4 = COUNTER(41);
+#5 = COUNTER(9223372036854775807);
+#6 = COUNTER(-9223372036854775808);
+ENDSEC;
+END-ISO-10303-21;
+";
+
+fn read() -> DerivedReader {
+    let mut reader = DerivedReader::new();
+    reader.read_from(DATA.as_bytes()).unwrap();
+    reader
+}
+
+#[test]
+fn derived_attribute_calls_schema_function() {
+    let reader = read();
+    // the function counts the years up to 2008-06-25
+    assert_eq!(reader.person_age(1), Some(45));
+    assert_eq!(reader.person_age(2), Some(44));
+    assert_eq!(reader.person_age(3), Some(14));
+    assert_eq!(reader.person_age(99), None);
+}
+
+#[test]
+fn integer_overflow_is_none_as_in_the_evaluator() {
+    let reader = read();
+    assert_eq!(reader.counter_next(4), Some(42));
+    assert_eq!(reader.counter_previous(4), Some(40));
+    assert_eq!(reader.counter_negated(4), Some(-41));
+    assert_eq!(reader.counter_next(5), None);
+    assert_eq!(reader.counter_previous(6), None);
+    assert_eq!(reader.counter_negated(6), None);
+
+    // arithmetic on literals alone is computed by the generator and stays a trait method
+    let (_, counter) = reader.get_entities::<Counter>().next().unwrap();
+    assert_eq!(counter.step(), 5);
+}
+
+#[test]
+fn parameter_count_must_match_the_attributes() {
+    let data = DATA
+        .replace("COUNTER(41)", "COUNTER()")
+        .replace("COUNTER(9223372036854775807)", "COUNTER(1, 2)");
+    let mut reader = DerivedReader::new();
+    let report = reader.read_from(data.as_bytes()).unwrap();
+    let errors = report
+        .diagnostics
+        .iter()
+        .map(|diagnostic| match diagnostic {
+            StepError::Conversion { id, index, error } => (*id, *index, error.to_string()),
+            diagnostic => panic!("conversion error expected, found {}", diagnostic),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        errors,
+        [
+            (4, 0, "expected 1 parameters, found 0 parameters".to_string()),
+            (5, 1, "expected 1 parameters, found 2 parameters".to_string()),
+        ]
+    );
+    assert!(reader.get_entities::<Counter>().map(|(id, _)| id).eq(vec![6]));
+}
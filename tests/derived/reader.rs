@@ -0,0 +1,278 @@
+#![doc = r" This file is generated. Do not edit."]
+#![allow(dead_code, unused_imports, clippy::single_match)]
+use iso_10303::step::*;
+use std::collections::HashSet;
+use std::convert::{TryFrom, TryInto};
+#[derive(Default, Debug)]
+pub struct Unimplemented {}
+impl TryFrom<Parameter> for Unimplemented {
+    type Error = ConversionError;
+    fn try_from(_parameter: Parameter) -> Result<Self, Self::Error> {
+        Ok(Unimplemented {})
+    }
+}
+impl ToParameter for Unimplemented {
+    fn to_parameter(&self) -> Parameter {
+        Parameter::UnTypedParameter(UnTypedParameter::Null)
+    }
+}
+type Date = Vec<i64>;
+pub trait IPerson {
+    fn name(&self) -> &String;
+    fn birth_date(&self) -> &Date;
+}
+#[derive(Default, Debug)]
+pub struct Person {
+    name: String,
+    birth_date: Date,
+}
+impl IPerson for Person {
+    fn name(&self) -> &String {
+        &self.name
+    }
+    fn birth_date(&self) -> &Date {
+        &self.birth_date
+    }
+}
+impl Person {
+    pub fn from_parameters(parameters: Vec<Parameter>) -> Result<Self, (usize, ConversionError)> {
+        if parameters.len() != 2usize {
+            let error = ConversionError::new("2 parameters", format!("{} parameters", parameters.len()));
+            return Err((parameters.len().min(2usize), error));
+        }
+        let mut entity = Self::default();
+        for (index, parameter) in parameters.into_iter().enumerate() {
+            match index {
+                0usize => entity.name = parameter.try_into().map_err(|error| (index, error))?,
+                1usize => entity.birth_date = parameter.try_into().map_err(|error| (index, error))?,
+                _ => {}
+            }
+        }
+        Ok(entity)
+    }
+}
+impl ToParameters for Person {
+    const TYPE_NAME: &'static str = "PERSON";
+    fn to_parameters(&self) -> Vec<Parameter> {
+        vec![self.name.to_parameter(), self.birth_date.to_parameter()]
+    }
+}
+impl AttributeReferences for Person {
+    fn attribute_references(&self, _visit: &mut dyn FnMut(&'static str, &'static str, i64, EntityCheck)) {}
+}
+pub trait ICounter {
+    fn count(&self) -> i64;
+    fn step(&self) -> i64 {
+        5
+    }
+}
+#[derive(Default, Debug)]
+pub struct Counter {
+    count: i64,
+}
+impl ICounter for Counter {
+    fn count(&self) -> i64 {
+        self.count
+    }
+}
+impl Counter {
+    pub fn from_parameters(parameters: Vec<Parameter>) -> Result<Self, (usize, ConversionError)> {
+        if parameters.len() != 1usize {
+            let error = ConversionError::new("1 parameters", format!("{} parameters", parameters.len()));
+            return Err((parameters.len().min(1usize), error));
+        }
+        let mut entity = Self::default();
+        for (index, parameter) in parameters.into_iter().enumerate() {
+            match index {
+                0usize => entity.count = parameter.try_into().map_err(|error| (index, error))?,
+                _ => {}
+            }
+        }
+        Ok(entity)
+    }
+}
+impl ToParameters for Counter {
+    const TYPE_NAME: &'static str = "COUNTER";
+    fn to_parameters(&self) -> Vec<Parameter> {
+        vec![self.count.to_parameter()]
+    }
+}
+impl AttributeReferences for Counter {
+    fn attribute_references(&self, _visit: &mut dyn FnMut(&'static str, &'static str, i64, EntityCheck)) {}
+}
+use std::any::{Any, TypeId};
+use std::collections::{BTreeMap, HashMap};
+pub struct DerivedReader {
+    pub entities: BTreeMap<i64, Box<dyn Any>>,
+    pub type_ids: HashMap<TypeId, Vec<i64>>,
+    pub type_names: HashMap<TypeId, &'static str>,
+    empty: Vec<i64>,
+    duplicate_ids: Vec<i64>,
+    visitors: HashMap<TypeId, ReferenceVisitor>,
+    used_in: UsedInIndex,
+}
+impl DerivedReader {
+    pub fn new() -> Self {
+        let mut visitors = HashMap::new();
+        visitors.insert(TypeId::of::<Person>(), reference_visitor::<Person> as ReferenceVisitor);
+        visitors.insert(
+            TypeId::of::<Counter>(),
+            reference_visitor::<Counter> as ReferenceVisitor,
+        );
+        DerivedReader {
+            entities: BTreeMap::new(),
+            type_ids: HashMap::new(),
+            type_names: HashMap::new(),
+            empty: Vec::new(),
+            duplicate_ids: Vec::new(),
+            visitors,
+            used_in: UsedInIndex::default(),
+        }
+    }
+    #[doc = r" Add an entity instance, replacing the instance with the same id."]
+    pub fn add_entity<T: Any>(&mut self, id: i64, entity: T) {
+        self.remove_entity(id);
+        let type_id = entity.type_id();
+        self.insert_entity(id, type_id, std::any::type_name::<T>(), Box::new(entity));
+    }
+    fn remove_entity(&mut self, id: i64) {
+        if let Some(entity) = self.entities.remove(&id) {
+            self.used_in.remove(id, &*entity, &self.visitors);
+            if let Some(ids) = self.type_ids.get_mut(&(*entity).type_id()) {
+                ids.retain(|other| *other != id);
+            }
+        }
+    }
+    pub fn get_entity<T: Any>(&self, entity_ref: &EntityRef) -> Option<&T> {
+        self.entities
+            .get(&entity_ref.0)
+            .and_then(|entity| entity.downcast_ref::<T>())
+    }
+    pub fn resolve<T: FromEntity + ?Sized>(&self, entity_ref: &TypedRef<T>) -> Option<&T> {
+        self.entities
+            .get(&entity_ref.id())
+            .and_then(|entity| T::resolve(&**entity))
+    }
+    pub fn get_entities<T: Any>(&self) -> impl Iterator<Item = (i64, &T)> {
+        let type_id = TypeId::of::<T>();
+        self.type_ids
+            .get(&type_id)
+            .unwrap_or(&self.empty)
+            .iter()
+            .map(move |id| (*id, self.entities[id].downcast_ref::<T>().unwrap()))
+    }
+    pub fn get_type_name(&self, id: i64) -> &'static str {
+        let type_id = (*self.entities[&id]).type_id();
+        self.type_names[&type_id]
+    }
+    pub fn validate(&self) -> Vec<StepError> {
+        let mut errors = self
+            .duplicate_ids
+            .iter()
+            .map(|&id| StepError::DuplicateId { id })
+            .collect::<Vec<_>>();
+        errors.extend(validate_references(&self.entities, &self.visitors));
+        errors
+    }
+    pub fn used_in(&self, id: i64) -> &[(i64, &'static str)] {
+        self.used_in.used_in(id)
+    }
+    pub fn used_in_typed<T: FromEntity + ?Sized>(&self, id: i64, attribute: &str) -> Vec<TypedRef<T>> {
+        self.used_in.used_in_typed(&self.entities, id, attribute)
+    }
+    pub fn person_age(&self, id: i64) -> Option<i64> {
+        let entity = self.resolve(&TypedRef::<Person>::new(id))?;
+        functions::years(self, IPerson::birth_date(entity))
+    }
+    pub fn counter_next(&self, id: i64) -> Option<i64> {
+        let entity = self.resolve(&TypedRef::<Counter>::new(id))?;
+        i64::checked_add(ICounter::count(entity), 1)
+    }
+    pub fn counter_previous(&self, id: i64) -> Option<i64> {
+        let entity = self.resolve(&TypedRef::<Counter>::new(id))?;
+        i64::checked_sub(ICounter::count(entity), 1)
+    }
+    pub fn counter_negated(&self, id: i64) -> Option<i64> {
+        let entity = self.resolve(&TypedRef::<Counter>::new(id))?;
+        i64::checked_neg(ICounter::count(entity))
+    }
+}
+impl Default for DerivedReader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl StepReader for DerivedReader {
+    fn insert_entity(&mut self, id: i64, type_id: TypeId, type_name: &'static str, entity: Box<dyn Any>) {
+        if self.entities.contains_key(&id) {
+            self.duplicate_ids.push(id);
+            return;
+        }
+        self.used_in.insert(id, &*entity, &self.visitors);
+        self.entities.insert(id, entity);
+        self.type_ids.entry(type_id).or_insert(vec![]).push(id);
+        self.type_names.entry(type_id).or_insert(type_name);
+    }
+    fn create_simple_entity(id: i64, typed_parameter: TypedParameter, own_parameters_only: bool) -> EntityResult {
+        match typed_parameter.type_name.as_str() {
+            "PERSON" => {
+                let entity = Person::from_parameters(typed_parameter.parameters)
+                    .map_err(|(index, error)| StepError::Conversion { id, index, error })?;
+                Ok((entity.type_id(), std::any::type_name::<Person>(), Box::new(entity)))
+            }
+            "COUNTER" => {
+                let entity = Counter::from_parameters(typed_parameter.parameters)
+                    .map_err(|(index, error)| StepError::Conversion { id, index, error })?;
+                Ok((entity.type_id(), std::any::type_name::<Counter>(), Box::new(entity)))
+            }
+            _ if own_parameters_only => Ok((
+                TypeId::of::<TypedParameter>(),
+                std::any::type_name::<TypedParameter>(),
+                Box::new(typed_parameter),
+            )),
+            _ => Err(StepError::UnknownEntity {
+                id,
+                type_name: typed_parameter.type_name,
+            }),
+        }
+    }
+}
+impl StepWriter for DerivedReader {
+    fn schema_name(&self) -> &'static str {
+        "DERIVED"
+    }
+    fn entities(&self) -> &BTreeMap<i64, Box<dyn Any>> {
+        &self.entities
+    }
+    fn typed_parameter_writers(&self) -> HashMap<TypeId, TypedParameterWriter> {
+        let mut writers = HashMap::new();
+        writers.insert(
+            TypeId::of::<Person>(),
+            typed_parameter_writer::<Person> as TypedParameterWriter,
+        );
+        writers.insert(
+            TypeId::of::<Counter>(),
+            typed_parameter_writer::<Counter> as TypedParameterWriter,
+        );
+        writers
+    }
+}
+mod functions {
+    use super::*;
+    pub fn years(_reader: &DerivedReader, past: &[i64]) -> Option<i64> {
+        Some(
+            ((i64::checked_sub(
+                20080625,
+                i64::checked_add(
+                    i64::checked_add(
+                        i64::checked_mul(*past.first()?, 10000)?,
+                        i64::checked_mul(*past.get(1)?, 100)?,
+                    )?,
+                    *past.get(2)?,
+                )?,
+            )? as f64)
+                / 10000.0)
+                .floor() as i64,
+        )
+    }
+}
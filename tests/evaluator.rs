@@ -0,0 +1,133 @@
+use iso_10303::express::{parser, Evaluator, Model, Schema, Value};
+
+const SCHEMA: &str = "
+SCHEMA bounds;
+
+TYPE triple = ARRAY [0:2] OF INTEGER;
+END_TYPE;
+
+ENTITY grid;
+    cells : ARRAY [-1:1] OF INTEGER;
+    next : OPTIONAL grid;
+DERIVE
+    low : INTEGER := LOINDEX(cells);
+    high : INTEGER := HIINDEX(cells);
+    first : INTEGER := cells[-1];
+END_ENTITY;
+
+FUNCTION int_div(a, b : INTEGER) : INTEGER;
+    RETURN (a DIV b);
+END_FUNCTION;
+
+FUNCTION int_mod(a, b : INTEGER) : INTEGER;
+    RETURN (a MOD b);
+END_FUNCTION;
+
+FUNCTION int_add(a, b : INTEGER) : INTEGER;
+    RETURN (a + b);
+END_FUNCTION;
+
+FUNCTION int_power(a, b : INTEGER) : INTEGER;
+    RETURN (a ** b);
+END_FUNCTION;
+
+FUNCTION int_neg(a : INTEGER) : INTEGER;
+    RETURN (-a);
+END_FUNCTION;
+
+FUNCTION shifted : INTEGER;
+    LOCAL
+        a : ARRAY [5:7] OF INTEGER := [1, 2, 3];
+    END_LOCAL;
+    a[5] := 10;
+    RETURN (a[5] + a[7] + HIINDEX(a) * 100 + LOINDEX(a) * 1000);
+END_FUNCTION;
+
+FUNCTION triple_low(t : triple) : INTEGER;
+    RETURN (LOINDEX(t) * 10 + t[0]);
+END_FUNCTION;
+
+END_SCHEMA;
+";
+
+const DATA: &str = "ISO-10303-21;
+HEADER;
+FILE_DESCRIPTION((''), '2;1');
+FILE_NAME('', '', (''), (''), '', '', '');
+FILE_SCHEMA(('BOUNDS'));
+ENDSEC;
+DATA;
+#1 = GRID((4, 5, 6), $);
+#2 = GRID((7, 8, 9), #1);
+#2 = GRID((7, 8, 9), $);
+ENDSEC;
+END-ISO-10303-21;
+";
+
+fn schema() -> Schema {
+    parser::schema().parse(SCHEMA.as_bytes()).unwrap()
+}
+
+fn call(model: &Model, name: &str, arguments: &[i64]) -> Value {
+    let arguments = arguments.iter().copied().map(Value::Integer).collect();
+    Evaluator::new(model).call(name, arguments).unwrap()
+}
+
+#[test]
+fn integer_overflow_is_indeterminate() {
+    let schema = schema();
+    let model = Model::new(&schema);
+    assert_eq!(call(&model, "int_div", &[i64::MIN, -1]), Value::Indeterminate);
+    assert_eq!(call(&model, "int_mod", &[i64::MIN, -1]), Value::Integer(0));
+    assert_eq!(call(&model, "int_div", &[-7, 2]), Value::Integer(-4));
+    assert_eq!(call(&model, "int_mod", &[-7, 2]), Value::Integer(1));
+    assert_eq!(call(&model, "int_mod", &[7, -2]), Value::Integer(-1));
+    assert_eq!(call(&model, "int_div", &[7, 0]), Value::Indeterminate);
+    assert_eq!(call(&model, "int_add", &[i64::MAX, 1]), Value::Indeterminate);
+    assert_eq!(call(&model, "int_neg", &[i64::MIN]), Value::Indeterminate);
+    assert_eq!(call(&model, "int_power", &[2, 10]), Value::Integer(1024));
+    assert_eq!(call(&model, "int_power", &[2, 64]), Value::Indeterminate);
+    assert_eq!(call(&model, "int_power", &[1, 1 << 32]), Value::Indeterminate);
+    assert_eq!(call(&model, "abs", &[i64::MIN]), Value::Indeterminate);
+}
+
+#[test]
+fn arrays_are_indexed_within_their_declared_bounds() {
+    let schema = schema();
+    let model = Model::read_from(&schema, DATA.as_bytes()).unwrap();
+    assert_eq!(model.attribute(1, None, "low").unwrap(), Value::Integer(-1));
+    assert_eq!(model.attribute(1, None, "high").unwrap(), Value::Integer(1));
+    assert_eq!(model.attribute(1, None, "first").unwrap(), Value::Integer(4));
+    assert_eq!(
+        call(&model, "shifted", &[]),
+        Value::Integer(5 * 1000 + 7 * 100 + 10 + 3)
+    );
+
+    let triple = Value::Typed(
+        "triple".to_string(),
+        Box::new(Value::Aggregate(vec![
+            Value::Integer(3),
+            Value::Integer(2),
+            Value::Integer(1),
+        ])),
+    );
+    assert_eq!(
+        Evaluator::new(&model).call("triple_low", vec![triple.clone()]).unwrap(),
+        Value::Integer(3)
+    );
+    assert_eq!(
+        Evaluator::new(&model).call("hiindex", vec![triple]).unwrap(),
+        Value::Integer(2)
+    );
+}
+
+#[test]
+fn used_in_forgets_replaced_instances() {
+    let schema = schema();
+    let model = Model::read_from(&schema, DATA.as_bytes()).unwrap();
+    assert!(model.used_in(1).is_empty());
+    let referrers = Evaluator::new(&model)
+        .call("usedin", vec![Value::Entity(1), Value::String(String::new())])
+        .unwrap();
+    assert_eq!(referrers, Value::Aggregate(Vec::new()));
+}
@@ -0,0 +1,107 @@
+use iso_10303::express::{parser, EvalError, GlobalRuleViolation, Model};
+
+const SCHEMA: &str = "
+SCHEMA staff;
+
+ENTITY person;
+    name : STRING;
+    age : INTEGER;
+END_ENTITY;
+
+ENTITY company;
+    name : STRING;
+    employees : SET [0:?] OF person;
+END_ENTITY;
+
+RULE adults FOR (person);
+WHERE
+    wr1 : SIZEOF(QUERY(p <* person | p.age < 18)) = 0;
+    wr2 : SIZEOF(person) > 0;
+END_RULE;
+
+RULE employed FOR (person, company);
+    LOCAL
+        count : INTEGER := 0;
+    END_LOCAL;
+    REPEAT i := 1 TO SIZEOF(company);
+        count := count + SIZEOF(company[i].employees);
+    END_REPEAT;
+WHERE
+    all_employed : count = SIZEOF(person);
+END_RULE;
+
+RULE broken FOR (company);
+    undefined_procedure(company);
+WHERE
+    wr1 : TRUE;
+END_RULE;
+
+END_SCHEMA;
+";
+
+const DATA: &str = "ISO-10303-21;
+HEADER;
+FILE_DESCRIPTION((''), '2;1');
+FILE_NAME('', '', (''), (''), '', '', '');
+FILE_SCHEMA(('STAFF'));
+ENDSEC;
+DATA;
+#1 = PERSON('Ann', 30);
+#2 = PERSON('Bob', 12);
+#3 = COMPANY('ACME', (#1, #2));
+#4 = PERSON('Cy', 40);
+ENDSEC;
+END-ISO-10303-21;
+";
+
+#[test]
+fn violated_global_rules_are_reported_with_their_instances() {
+    let schema = parser::schema().parse(SCHEMA.as_bytes()).unwrap();
+    let model = Model::read_from(&schema, DATA.as_bytes()).unwrap();
+    let report = model.validate();
+    assert_eq!(
+        report.global_rules,
+        [
+            GlobalRuleViolation {
+                rule: "adults".to_string(),
+                label: Some("wr1".to_string()),
+                ids: vec![1, 2, 4],
+                error: None,
+            },
+            GlobalRuleViolation {
+                rule: "employed".to_string(),
+                label: Some("all_employed".to_string()),
+                ids: vec![1, 2, 3, 4],
+                error: None,
+            },
+            GlobalRuleViolation {
+                rule: "broken".to_string(),
+                label: None,
+                ids: vec![3],
+                error: Some(EvalError::UnknownName("undefined_procedure".to_string())),
+            },
+        ]
+    );
+    assert_eq!(
+        report.global_rules[0].to_string(),
+        "RULE adults.wr1 is violated for #1, #2, #4"
+    );
+    assert!(report.failed.is_empty() && report.indeterminate.is_empty());
+    assert!(!report.is_valid());
+}
+
+#[test]
+fn satisfied_global_rules_are_not_reported() {
+    let data = DATA
+        .replace("'Bob', 12", "'Bob', 21")
+        .replace("(#1, #2)", "(#1, #2, #4)");
+    let schema = parser::schema().parse(SCHEMA.as_bytes()).unwrap();
+    let model = Model::read_from(&schema, data.as_bytes()).unwrap();
+    let rules = model
+        .validate()
+        .global_rules
+        .into_iter()
+        .map(|violation| violation.rule)
+        .collect::<Vec<_>>();
+    assert_eq!(rules, ["broken"]);
+}
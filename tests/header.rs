@@ -0,0 +1,125 @@
+use iso_10303::step::{parser, FilePopulation, Header, SchemaName, SectionContext, SectionLanguage, StepError};
+
+const HEADER: &str = "ISO-10303-21;
+HEADER;
+FILE_DESCRIPTION(('first', 'second'), '3;1');
+FILE_NAME('demo', '2024-01-01T00:00:00', ('author'), ('organization'), 'preprocessor', 'system', '');
+FILE_SCHEMA(('AUTOMOTIVE_DESIGN { 1 0 10303 214 2 1 1 }', 'CONFIG_CONTROL_DESIGN'));
+FILE_POPULATION('AUTOMOTIVE_DESIGN', 'include_all_compatible', ('DS1'));
+FILE_POPULATION('CONFIG_CONTROL_DESIGN', 'include_referenced', $);
+SECTION_LANGUAGE($, 'EN');
+SECTION_LANGUAGE('DS1', 'DE');
+SECTION_CONTEXT($, ('default'));
+SECTION_CONTEXT('DS1', ('geometry', 'colours'));
+USER_DEFINED('value');
+SECTION_LANGUAGE(1, 'EN');
+ENDSEC;
+";
+
+fn from_text(text: &str) -> (Header, Vec<StepError>) {
+    let entities = parser::header_section().parse(text.as_bytes()).unwrap();
+    Header::from_entities(entities)
+}
+
+#[test]
+fn optional_header_entities() {
+    let (header, diagnostics) = from_text(HEADER);
+    assert_eq!(header.description, ["first", "second"]);
+    assert_eq!(header.implementation_level, "3;1");
+    assert_eq!(header.name, "demo");
+    assert_eq!(
+        header.schema_identifiers,
+        [
+            SchemaName {
+                name: "AUTOMOTIVE_DESIGN".to_string(),
+                object_identifier: ["1", "0", "10303", "214", "2", "1", "1"]
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect(),
+            },
+            SchemaName {
+                name: "CONFIG_CONTROL_DESIGN".to_string(),
+                object_identifier: Vec::new(),
+            },
+        ]
+    );
+    assert_eq!(
+        header.file_populations,
+        [
+            FilePopulation {
+                governing_schema: "AUTOMOTIVE_DESIGN".to_string(),
+                determination_method: "include_all_compatible".to_string(),
+                governed_sections: Some(vec!["DS1".to_string()]),
+            },
+            FilePopulation {
+                governing_schema: "CONFIG_CONTROL_DESIGN".to_string(),
+                determination_method: "include_referenced".to_string(),
+                governed_sections: None,
+            },
+        ]
+    );
+    assert_eq!(
+        header.section_languages,
+        [
+            SectionLanguage {
+                section: None,
+                default_language: "EN".to_string(),
+            },
+            SectionLanguage {
+                section: Some("DS1".to_string()),
+                default_language: "DE".to_string(),
+            },
+        ]
+    );
+    assert_eq!(
+        header.section_contexts,
+        [
+            SectionContext {
+                section: None,
+                context_identifiers: vec!["default".to_string()],
+            },
+            SectionContext {
+                section: Some("DS1".to_string()),
+                context_identifiers: vec!["geometry".to_string(), "colours".to_string()],
+            },
+        ]
+    );
+    assert_eq!(header.others.len(), 1);
+    assert_eq!(header.others[0].type_name, "USER_DEFINED");
+
+    // an entity with invalid attributes is reported and skipped
+    assert_eq!(diagnostics.len(), 1);
+    assert!(
+        matches!(&diagnostics[0], StepError::Header { type_name, index: 0, .. } if type_name == "SECTION_LANGUAGE")
+    );
+}
+
+#[test]
+fn header_entities_round_trip() {
+    let (header, _) = from_text(HEADER);
+    let text = format!(
+        "ISO-10303-21;\nHEADER;\n{}ENDSEC;\n",
+        header
+            .to_entities()
+            .iter()
+            .map(|entity| format!("{};\n", entity))
+            .collect::<String>()
+    );
+    let (written, diagnostics) = from_text(&text);
+    assert!(diagnostics.is_empty());
+    assert_eq!(written, header);
+}
+
+#[test]
+fn read_header_of_a_file() {
+    let (header, diagnostics) = Header::read("parts/examples/ap214_example.stp").unwrap();
+    assert!(diagnostics.is_empty());
+    assert_eq!(header.name, "demo");
+    assert_eq!(header.author, ["Lothar Klein"]);
+    assert_eq!(header.schema_identifiers.len(), 1);
+    assert_eq!(header.schema_identifiers[0].name, "AUTOMOTIVE_DESIGN");
+    assert_eq!(
+        header.schema_identifiers[0].to_string(),
+        "AUTOMOTIVE_DESIGN { 1 0 10303 214 2 1 1 }"
+    );
+}
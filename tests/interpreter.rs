@@ -0,0 +1,137 @@
+use iso_10303::express::{parser, Evaluator, Model, Schema, Value};
+
+const SCHEMA: &str = "
+SCHEMA graph;
+
+ENTITY node;
+    weights : LIST [0:?] OF INTEGER;
+    bonus : OPTIONAL INTEGER;
+WHERE
+    heavy : total_weight(SELF) < 100;
+END_ENTITY;
+
+ENTITY edge;
+    source : node;
+    target : node;
+END_ENTITY;
+
+FUNCTION total_weight(n : node) : INTEGER;
+    LOCAL
+        total : INTEGER := NVL(n.bonus, 0);
+        i : INTEGER;
+    END_LOCAL;
+    REPEAT i := 1 TO HIINDEX(n.weights);
+        IF n.weights[i] < 0 THEN
+            ESCAPE;
+        END_IF;
+        IF n.weights[i] = 0 THEN
+            SKIP;
+        END_IF;
+        total := total + n.weights[i];
+    END_REPEAT;
+    RETURN (total);
+END_FUNCTION;
+
+FUNCTION role_count(n : node) : INTEGER;
+    LOCAL
+        roles : SET OF STRING := ROLESOF(n);
+    END_LOCAL;
+    RETURN (SIZEOF(roles));
+END_FUNCTION;
+
+FUNCTION classify(x : INTEGER) : STRING;
+    LOCAL
+        name : STRING;
+    END_LOCAL;
+    CASE x OF
+        1, 2 : name := 'small';
+        3 : BEGIN
+            name := 'medium';
+            RETURN (name + '!');
+        END;
+        OTHERWISE : name := 'large';
+    END_CASE;
+    RETURN (name);
+END_FUNCTION;
+
+FUNCTION factorial(x : INTEGER) : INTEGER;
+    IF x <= 1 THEN
+        RETURN (1);
+    ELSE
+        RETURN (x * factorial(x - 1));
+    END_IF;
+END_FUNCTION;
+
+END_SCHEMA;
+";
+
+const DATA: &str = "ISO-10303-21;
+HEADER;
+FILE_DESCRIPTION((''), '2;1');
+FILE_NAME('', '', (''), (''), '', '', '');
+FILE_SCHEMA(('GRAPH'));
+ENDSEC;
+DATA;
+#1 = NODE((1, 2, 3), $);
+#2 = NODE((5, 0, 7, -1, 100), 10);
+#3 = NODE((60, 60), $);
+#4 = EDGE(#1, #2);
+#5 = EDGE(#2, #1);
+#6 = EDGE(#2, #3);
+ENDSEC;
+END-ISO-10303-21;
+";
+
+fn schema() -> Schema {
+    parser::schema().parse(SCHEMA.as_bytes()).unwrap()
+}
+
+fn call(model: &Model, name: &str, argument: Value) -> Value {
+    Evaluator::new(model).call(name, vec![argument]).unwrap()
+}
+
+#[test]
+fn functions_run_with_their_locals() {
+    let schema = schema();
+    let model = Model::read_from(&schema, DATA.as_bytes()).unwrap();
+    assert_eq!(call(&model, "total_weight", Value::Entity(1)), Value::Integer(6));
+    // the bonus is added, 0 is skipped and the loop ends at -1
+    assert_eq!(call(&model, "total_weight", Value::Entity(2)), Value::Integer(22));
+    assert_eq!(
+        call(&model, "classify", Value::Integer(2)),
+        Value::String("small".to_string())
+    );
+    assert_eq!(
+        call(&model, "classify", Value::Integer(3)),
+        Value::String("medium!".to_string())
+    );
+    assert_eq!(
+        call(&model, "classify", Value::Integer(9)),
+        Value::String("large".to_string())
+    );
+    assert_eq!(call(&model, "factorial", Value::Integer(5)), Value::Integer(120));
+}
+
+#[test]
+fn functions_call_built_ins_on_the_population() {
+    let schema = schema();
+    let model = Model::read_from(&schema, DATA.as_bytes()).unwrap();
+    // #2 is the source and the target of edges, #3 only a target
+    assert_eq!(call(&model, "role_count", Value::Entity(2)), Value::Integer(2));
+    assert_eq!(call(&model, "role_count", Value::Entity(3)), Value::Integer(1));
+    let roles = Evaluator::new(&model).call("rolesof", vec![Value::Entity(3)]).unwrap();
+    assert_eq!(
+        roles,
+        Value::Aggregate(vec![Value::String("GRAPH.EDGE.TARGET".to_string())])
+    );
+}
+
+#[test]
+fn where_rules_call_schema_functions() {
+    let schema = schema();
+    let model = Model::read_from(&schema, DATA.as_bytes()).unwrap();
+    let report = model.validate();
+    let failed = report.failed.iter().map(|violation| violation.id).collect::<Vec<_>>();
+    assert_eq!(failed, [3]);
+    assert!(report.indeterminate.is_empty(), "{:?}", report.indeterminate);
+}
@@ -0,0 +1,113 @@
+use iso_10303::express::{link, parser, DataType, Declaration, LinkError, Schema};
+
+fn schemas(text: &str) -> Vec<Schema> {
+    parser::schemas().parse(text.as_bytes()).unwrap()
+}
+
+#[test]
+fn attribute_types_are_renamed_before_attributes_hide_them() {
+    let schemas = schemas(
+        "
+SCHEMA root;
+REFERENCE FROM units (measure AS length_measure);
+USE FROM units (bar);
+END_SCHEMA;
+
+SCHEMA units;
+TYPE measure = REAL;
+END_TYPE;
+ENTITY bar;
+    measure : measure;
+END_ENTITY;
+END_SCHEMA;
+",
+    );
+    let schema = link(schemas, "root").unwrap();
+    let bar = schema
+        .declarations
+        .iter()
+        .find_map(|declaration| match declaration {
+            Declaration::Entity(entity) if entity.name == "bar" => Some(entity),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(bar.attributes[0].name, "measure");
+    assert!(matches!(&bar.attributes[0].data_type, DataType::TypeRef { name } if name == "length_measure"));
+}
+
+#[test]
+fn different_items_under_one_name_are_rejected() {
+    let schemas = schemas(
+        "
+SCHEMA root;
+USE FROM first;
+USE FROM second;
+END_SCHEMA;
+
+SCHEMA first;
+TYPE label = STRING;
+END_TYPE;
+END_SCHEMA;
+
+SCHEMA second;
+TYPE label = STRING;
+END_TYPE;
+END_SCHEMA;
+",
+    );
+    assert_eq!(
+        link(schemas, "root").unwrap_err(),
+        LinkError::DuplicateName("label".to_string())
+    );
+}
+
+#[test]
+fn implicitly_interfaced_items_are_renamed_instead_of_clashing() {
+    let schemas = schemas(
+        "
+SCHEMA root;
+USE FROM units (bar);
+USE FROM other (baz);
+TYPE label = INTEGER;
+END_TYPE;
+END_SCHEMA;
+
+SCHEMA units;
+TYPE label = STRING;
+END_TYPE;
+ENTITY bar;
+    name : label;
+END_ENTITY;
+END_SCHEMA;
+
+SCHEMA other;
+TYPE label = REAL;
+END_TYPE;
+ENTITY baz;
+    name : label;
+END_ENTITY;
+END_SCHEMA;
+",
+    );
+    let schema = link(schemas, "root").unwrap();
+    let names = schema.declarations.iter().map(Declaration::name).collect::<Vec<_>>();
+    assert_eq!(names, ["label", "bar", "baz", "units_label", "other_label"]);
+    let type_names = schema
+        .declarations
+        .iter()
+        .filter_map(|declaration| match declaration {
+            Declaration::Entity(entity) => match &entity.attributes[0].data_type {
+                DataType::TypeRef { name } => Some(name.as_str()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(type_names, ["units_label", "other_label"]);
+    assert!(
+        matches!(&schema.declarations[0], Declaration::TypeDef(label) if matches!(label.underlying_type, DataType::Integer))
+    );
+    assert!(
+        matches!(&schema.declarations[3], Declaration::TypeDef(label) if matches!(label.underlying_type, DataType::String { .. }))
+    );
+}
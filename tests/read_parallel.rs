@@ -0,0 +1,54 @@
+use iso_10303::step::{EntityResult, StepReader, TypedParameter};
+use std::any::{Any, TypeId};
+use std::io::Cursor;
+use std::sync::Mutex;
+use std::thread::ThreadId;
+use std::time::{Duration, Instant};
+
+/// When and on which thread each entity was created.
+static CREATED: Mutex<Vec<(ThreadId, Instant, Instant)>> = Mutex::new(Vec::new());
+
+#[derive(Default)]
+struct SlowReader {
+    ids: Vec<i64>,
+}
+
+impl StepReader for SlowReader {
+    fn insert_entity(&mut self, id: i64, _type_id: TypeId, _type_name: &'static str, _entity: Box<dyn Any>) {
+        self.ids.push(id);
+    }
+
+    fn create_simple_entity(_id: i64, _typed_parameter: TypedParameter, _own_parameters_only: bool) -> EntityResult {
+        let start = Instant::now();
+        std::thread::sleep(Duration::from_millis(20));
+        CREATED
+            .lock()
+            .unwrap()
+            .push((std::thread::current().id(), start, Instant::now()));
+        Ok((TypeId::of::<()>(), "()", Box::new(())))
+    }
+}
+
+#[test]
+fn workers_create_entities_at_the_same_time() {
+    // instances of 100 kB make batches of about ten instances
+    let text = "x".repeat(100_000);
+    let mut file = String::from("ISO-10303-21;\nHEADER;\nFILE_DESCRIPTION((''),'2;1');\n");
+    file.push_str("FILE_NAME('','',(''),(''),'','','');\nFILE_SCHEMA(('TEST'));\nENDSEC;\nDATA;\n");
+    for id in 1..=60 {
+        file.push_str(&format!("#{}=ITEM('{}');\n", id, text));
+    }
+    file.push_str("ENDSEC;\nEND-ISO-10303-21;\n");
+
+    let mut reader = SlowReader::default();
+    reader.read_from_parallel(Cursor::new(file), 4).unwrap();
+    assert_eq!(reader.ids, (1..=60).collect::<Vec<_>>());
+
+    let created = CREATED.lock().unwrap();
+    let overlapping = created.iter().any(|(thread, start, end)| {
+        created
+            .iter()
+            .any(|(other, other_start, other_end)| other != thread && other_start < end && start < other_end)
+    });
+    assert!(overlapping, "no two workers created entities at the same time");
+}
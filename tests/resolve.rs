@@ -0,0 +1,75 @@
+use iso_10303::express::{load_schema, parser, Diagnostic, LinkError, LoadError, ResolvedSchema};
+use std::path::Path;
+
+const SCHEMA: &str = "
+SCHEMA broken;
+
+ENTITY a
+    SUBTYPE OF (c);
+END_ENTITY;
+
+ENTITY b
+    SUBTYPE OF (a);
+END_ENTITY;
+
+ENTITY c
+    SUBTYPE OF (b);
+END_ENTITY;
+
+ENTITY d
+    SUBTYPE OF (d, a);
+END_ENTITY;
+
+ENTITY e
+    SUBTYPE OF (missing);
+    size : unknown_type;
+END_ENTITY;
+
+TYPE a = INTEGER;
+END_TYPE;
+
+END_SCHEMA;
+";
+
+#[test]
+fn diagnostics_of_a_broken_schema() {
+    let schema = parser::schema().parse(SCHEMA.as_bytes()).unwrap();
+    let resolved = ResolvedSchema::new(&schema);
+    let names = |names: &[&str]| names.iter().map(ToString::to_string).collect();
+    assert_eq!(
+        resolved.diagnostics(),
+        &[
+            Diagnostic::DuplicateDeclaration("a".to_string()),
+            Diagnostic::UnknownName {
+                declaration: "e".to_string(),
+                name: "missing".to_string(),
+            },
+            // each cycle once, though it is reached from every entity on it and from d
+            Diagnostic::SupertypeCycle(names(&["a", "c", "b"])),
+            Diagnostic::SupertypeCycle(names(&["d"])),
+            Diagnostic::UnknownName {
+                declaration: "e".to_string(),
+                name: "unknown_type".to_string(),
+            },
+        ]
+    );
+    assert_eq!(
+        resolved.diagnostics()[2].to_string(),
+        "supertype cycle a -> c -> b -> a"
+    );
+}
+
+#[test]
+fn schemas_are_loaded_from_files() {
+    let schema = load_schema(&["schemas/example.exp"], None).unwrap();
+    assert!(!schema.declarations.is_empty());
+
+    match load_schema(&["schemas/missing.exp"], None) {
+        Err(LoadError::Io { path, .. }) => assert_eq!(path, Path::new("schemas/missing.exp")),
+        result => panic!("Io error expected, found {:?}", result.map(|schema| schema.name)),
+    }
+    match load_schema(&["schemas/example.exp"], Some("missing")) {
+        Err(LoadError::Link(LinkError::UnknownSchema(name))) => assert_eq!(name, "missing"),
+        result => panic!("link error expected, found {:?}", result.map(|schema| schema.name)),
+    }
+}
@@ -0,0 +1,74 @@
+use iso_10303::step::{parser, write_real, ExchangeFile, Parameter, UnTypedParameter};
+use std::fmt::Write;
+
+fn parse(text: &str) -> ExchangeFile {
+    parser::exchange_file().parse(text.as_bytes()).unwrap()
+}
+
+fn assert_round_trip(text: &str) {
+    let file = parse(text);
+    let written = file.to_string();
+    assert_eq!(parse(&written), file, "written as:\n{}", written);
+}
+
+#[test]
+fn sample_files_round_trip() {
+    for path in &[
+        "parts/examples/ap203_example.stp",
+        "parts/examples/ap214_example.stp",
+        "examples/family/family.stp",
+    ] {
+        let text = std::fs::read_to_string(path).unwrap();
+        assert_round_trip(&text);
+    }
+}
+
+#[test]
+fn parameters_round_trip() {
+    let text = "ISO-10303-21;
+HEADER;
+FILE_DESCRIPTION((''), '2;1');
+FILE_NAME('', '', (''), (''), '', '', '');
+FILE_SCHEMA(('TEST'));
+ENDSEC;
+REFERENCE;
+@1=<values.stp#length>;
+ENDSEC;
+DATA;
+#1=TEXT('\\X2\\00E400F6\\X0\\', '\\X4\\0001F600\\X0\\', '\\PA\\\\S\\D', 'it''s \\\\');
+#2=BITS(\"3F\", \"0\", \"1F0\", \"2A\");
+#3=MIXED(@1, <other.stp#item>, (1.5, -2., 1.E-07, 12345.678E10), .T., $, *);
+ENDSEC;
+END-ISO-10303-21;
+";
+    let file = parse(text);
+    let instances = &file.data[0].instances;
+    assert_eq!(
+        instances[0].value[0].parameters[..3],
+        [
+            Parameter::UnTypedParameter(UnTypedParameter::String("äö".to_string())),
+            Parameter::UnTypedParameter(UnTypedParameter::String("\u{1F600}".to_string())),
+            Parameter::UnTypedParameter(UnTypedParameter::String("Ä".to_string())),
+        ]
+    );
+    assert_eq!(
+        instances[2].value[0].parameters[..2],
+        [
+            Parameter::UnTypedParameter(UnTypedParameter::ValueRef(1)),
+            Parameter::UnTypedParameter(UnTypedParameter::Resource("other.stp#item".to_string())),
+        ]
+    );
+    assert_round_trip(text);
+}
+
+#[test]
+fn non_finite_reals_are_not_written() {
+    for value in &[f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+        let mut text = String::new();
+        assert!(write_real(&mut text, *value).is_err());
+    }
+
+    let mut text = String::new();
+    let parameter = Parameter::UnTypedParameter(UnTypedParameter::Real(f64::NAN));
+    assert!(write!(text, "{}", parameter).is_err());
+}
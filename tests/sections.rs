@@ -0,0 +1,124 @@
+use iso_10303::step::{parser, Anchor, InstanceStream, Parameter, Reference, StepError, UnTypedParameter};
+use std::io::Cursor;
+
+const FILE: &str = "ISO-10303-21;
+HEADER;
+FILE_DESCRIPTION((''), '3;1');
+FILE_NAME('', '', (''), (''), '', '', '');
+FILE_SCHEMA(('FIRST', 'SECOND'));
+ENDSEC;
+ANCHOR;
+<point>=#1{colour:'red'}{weight:@2};
+<length>=12.5;
+ENDSEC;
+REFERENCE;
+#100=<parts.stp#bolt>;
+@2=<values.stp#weight>;
+ENDSEC;
+DATA('DS1', ('FIRST'));
+#1=POINT(#100, @2);
+ENDSEC;
+DATA('DS2', ('SECOND'));
+#2=LINE(#1);
+ENDSEC;
+END-ISO-10303-21;
+SIGNATURE
+first signature;
+ENDSEC;
+SIGNATURE second signature;ENDSEC;
+";
+
+fn untyped(parameter: UnTypedParameter) -> Parameter {
+    Parameter::UnTypedParameter(parameter)
+}
+
+fn string(value: &str) -> UnTypedParameter {
+    UnTypedParameter::String(value.to_string())
+}
+
+fn anchors() -> Vec<Anchor> {
+    vec![
+        Anchor {
+            name: "point".to_string(),
+            item: UnTypedParameter::EntityRef(1),
+            tags: vec![
+                ("colour".to_string(), string("red")),
+                ("weight".to_string(), UnTypedParameter::ValueRef(2)),
+            ],
+        },
+        Anchor {
+            name: "length".to_string(),
+            item: UnTypedParameter::Real(12.5),
+            tags: Vec::new(),
+        },
+    ]
+}
+
+fn references() -> Vec<Reference> {
+    vec![
+        Reference {
+            name: UnTypedParameter::EntityRef(100),
+            resource: "parts.stp#bolt".to_string(),
+        },
+        Reference {
+            name: UnTypedParameter::ValueRef(2),
+            resource: "values.stp#weight".to_string(),
+        },
+    ]
+}
+
+fn data_sections() -> Vec<Vec<Parameter>> {
+    let section = |name: &str, schema: &str| {
+        vec![
+            untyped(string(name)),
+            untyped(UnTypedParameter::List(vec![untyped(string(schema))])),
+        ]
+    };
+    vec![section("DS1", "FIRST"), section("DS2", "SECOND")]
+}
+
+fn signatures() -> Vec<String> {
+    vec!["\nfirst signature;\n".to_string(), " second signature;".to_string()]
+}
+
+#[test]
+fn edition_3_sections_are_parsed() {
+    let file = parser::exchange_file().parse(FILE.as_bytes()).unwrap();
+    assert_eq!(file.anchors, anchors());
+    assert_eq!(file.references, references());
+    let sections = file
+        .data
+        .iter()
+        .map(|section| section.parameters.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(sections, data_sections());
+    assert_eq!(file.data[0].instances[0].id, 1);
+    assert_eq!(file.data[1].instances[0].id, 2);
+    assert_eq!(file.signatures, signatures());
+}
+
+#[test]
+fn edition_3_sections_are_streamed() {
+    let mut stream = InstanceStream::new(Cursor::new(FILE)).unwrap();
+    assert_eq!(stream.anchors(), &anchors()[..]);
+    assert_eq!(stream.references(), &references()[..]);
+    let ids = (&mut stream).map(|instance| instance.unwrap().id).collect::<Vec<_>>();
+    assert_eq!(ids, [1, 2]);
+    assert!(stream.signatures().is_empty());
+    stream.finish().unwrap();
+    assert_eq!(stream.data_sections(), &data_sections()[..]);
+    assert_eq!(stream.signatures(), &signatures()[..]);
+}
+
+#[test]
+fn only_signatures_follow_the_end() {
+    let file = format!("{}TRAILER;\n", FILE);
+    assert!(parser::exchange_file().parse(file.as_bytes()).is_err());
+
+    let mut stream = InstanceStream::new(Cursor::new(file)).unwrap();
+    assert_eq!((&mut stream).count(), 2);
+    match stream.finish() {
+        Err(StepError::Syntax { line, column, .. }) => assert_eq!((line, column), (26, 1)),
+        result => panic!("syntax error expected, found {:?}", result),
+    }
+}
@@ -0,0 +1,126 @@
+use iso_10303::express::{parser, Accessor, Declaration, Function, Schema, Statement};
+
+const SCHEMA: &str = "
+SCHEMA statements;
+
+FUNCTION control(x : INTEGER; p : point) : INTEGER;
+    LOCAL
+        total : INTEGER := 0;
+    END_LOCAL;
+    IF x > 0 THEN
+        total := 1;
+    ELSE
+        total := 2; total := 3;
+    END_IF;
+    CASE x OF
+        1, 2 : total := 4;
+        3 : BEGIN total := 5; END;
+        OTHERWISE : ;
+    END_CASE;
+    REPEAT i := 1 TO x BY 2 WHILE total < 10 UNTIL total > 20;
+        IF i = 3 THEN SKIP; END_IF;
+        IF i = 5 THEN ESCAPE; END_IF;
+    END_REPEAT;
+    ALIAS c FOR p.coordinates[1];
+        total := total + c;
+    END_ALIAS;
+    RETURN (total);
+END_FUNCTION;
+
+END_SCHEMA;
+";
+
+fn function(schema: &Schema) -> &Function {
+    match &schema.declarations[0] {
+        Declaration::Function(function) => function,
+        declaration => panic!("function expected, found {:?}", declaration),
+    }
+}
+
+#[test]
+fn statements_of_a_function_body_are_parsed() {
+    let schema = parser::schema().parse(SCHEMA.as_bytes()).unwrap();
+    let function = function(&schema);
+    assert_eq!(function.locals.len(), 1);
+    let statements = &function.statements;
+    assert_eq!(statements.len(), 5);
+
+    match &statements[0] {
+        Statement::If {
+            statements,
+            else_statements,
+            ..
+        } => {
+            assert_eq!(statements.len(), 1);
+            assert_eq!(else_statements.len(), 2);
+        }
+        statement => panic!("IF expected, found {:?}", statement),
+    }
+
+    match &statements[1] {
+        Statement::Case { actions, otherwise, .. } => {
+            assert_eq!(actions.len(), 2);
+            assert_eq!(actions[0].labels.len(), 2);
+            assert!(matches!(&actions[0].statement, Statement::Assignment { target, .. } if target == "total"));
+            assert!(matches!(&actions[1].statement, Statement::Compound(statements) if statements.len() == 1));
+            assert!(matches!(otherwise.as_deref(), Some(Statement::Null)));
+        }
+        statement => panic!("CASE expected, found {:?}", statement),
+    }
+
+    match &statements[2] {
+        Statement::Repeat {
+            increment,
+            while_condition,
+            until_condition,
+            statements,
+        } => {
+            let increment = increment.as_ref().unwrap();
+            assert_eq!(increment.variable, "i");
+            assert!(increment.step.is_some());
+            assert!(while_condition.is_some());
+            assert!(until_condition.is_some());
+            assert!(
+                matches!(&statements[0], Statement::If { statements, .. } if matches!(statements[..], [Statement::Skip]))
+            );
+            assert!(
+                matches!(&statements[1], Statement::If { statements, .. } if matches!(statements[..], [Statement::Escape]))
+            );
+        }
+        statement => panic!("REPEAT expected, found {:?}", statement),
+    }
+
+    match &statements[3] {
+        Statement::Alias {
+            variable,
+            target,
+            accessors,
+            statements,
+        } => {
+            assert_eq!((variable.as_str(), target.as_str()), ("c", "p"));
+            assert!(
+                matches!(&accessors[..], [Accessor::Attribute { name }, Accessor::Indexer { end: None, .. }] if name == "coordinates")
+            );
+            assert_eq!(statements.len(), 1);
+        }
+        statement => panic!("ALIAS expected, found {:?}", statement),
+    }
+
+    assert!(matches!(&statements[4], Statement::Return(Some(_))));
+}
+
+#[test]
+fn unterminated_statements_are_rejected() {
+    for body in &[
+        "IF x THEN RETURN (1);",
+        "CASE x OF 1 : RETURN (1);",
+        "REPEAT i := 1 TO 2; SKIP;",
+        "ALIAS a FOR x; ESCAPE;",
+    ] {
+        let schema = format!(
+            "SCHEMA s; FUNCTION f(x : INTEGER) : INTEGER; {} END_FUNCTION; END_SCHEMA;",
+            body
+        );
+        assert!(parser::schema().parse(schema.as_bytes()).is_err(), "{}", body);
+    }
+}
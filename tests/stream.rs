@@ -0,0 +1,100 @@
+use iso_10303::step::{EntityResult, InstanceStream, StepError, StepReader, TypedParameter};
+use std::any::{Any, TypeId};
+use std::io::Cursor;
+
+const HEADER: &str = "ISO-10303-21;
+HEADER;
+FILE_DESCRIPTION((''), '2;1');
+FILE_NAME('', '', (''), (''), '', '', '');
+FILE_SCHEMA(('TEST'));
+ENDSEC;
+DATA;
+";
+
+/// Read the instances of `data` up to the first error, returning their ids and the error.
+fn read(data: &str) -> (Vec<i64>, Option<StepError>) {
+    let file = format!("{}{}", HEADER, data);
+    let mut ids = Vec::new();
+    for instance in InstanceStream::new(Cursor::new(file)).unwrap() {
+        match instance {
+            Ok(instance) => ids.push(instance.id),
+            Err(error) => return (ids, Some(error)),
+        }
+    }
+    (ids, None)
+}
+
+fn location(error: Option<StepError>) -> (usize, usize, String) {
+    match error {
+        Some(StepError::Syntax {
+            line, column, message, ..
+        }) => (line, column, message),
+        error => panic!("syntax error expected, found {:?}", error),
+    }
+}
+
+#[derive(Default)]
+struct IdReader {
+    ids: Vec<i64>,
+}
+
+impl StepReader for IdReader {
+    fn insert_entity(&mut self, id: i64, _type_id: TypeId, _type_name: &'static str, _entity: Box<dyn Any>) {
+        self.ids.push(id);
+    }
+
+    fn create_simple_entity(_id: i64, _typed_parameter: TypedParameter, _own_parameters_only: bool) -> EntityResult {
+        Ok((TypeId::of::<()>(), "()", Box::new(())))
+    }
+}
+
+#[test]
+fn statements_before_a_malformed_one_are_read() {
+    let (ids, error) = read("#1=X(1); #2=Y(;\nENDSEC;\nEND-ISO-10303-21;\n");
+    assert_eq!(ids, [1]);
+    let (line, column, message) = location(error);
+    // the mismatch is reported where the parameter list of #2 is malformed, not at its start
+    assert_eq!((line, column), (8, 15));
+    assert!(message.contains("41"), "{}", message);
+}
+
+/// More instances of 1 kB than fit into one batch of 1 MB.
+const COUNT: i64 = 1_100;
+
+/// An instance missing its semicolon, found at its 26th character.
+const MALFORMED: &str = "#90001=POINT((0.,1.,2.)) #90002=POINT(());\nENDSEC;\nEND-ISO-10303-21;\n";
+
+#[test]
+fn syntax_errors_are_located_across_batches() {
+    // on separate lines
+    let mut data = String::new();
+    for id in 1..=COUNT {
+        data.push_str(&format!("#{}=TEXT('{}');\n", id, "x".repeat(1000)));
+    }
+    data.push_str(MALFORMED);
+    assert!(data.len() > 0x100000);
+    let (ids, error) = read(&data);
+    assert_eq!(ids.len(), COUNT as usize);
+    let (line, column, _) = location(error);
+    assert_eq!((line, column), (8 + COUNT as usize, 26));
+
+    // on a single line, so a later batch starts in the middle of it
+    let mut data = String::new();
+    for id in 1..=COUNT {
+        data.push_str(&format!("#{}=TEXT('{}');", id, "x".repeat(1000)));
+    }
+    let column = data.len() + 1;
+    data.push_str(MALFORMED);
+    let (ids, error) = read(&data);
+    assert_eq!(ids.len(), COUNT as usize);
+    let (line, error_column, _) = location(error);
+    assert_eq!((line, error_column), (8, column + 25));
+
+    // the parallel reader reports the same location
+    let file = format!("{}{}", HEADER, data);
+    let mut reader = IdReader::default();
+    let error = reader.read_from_parallel(Cursor::new(file), 4).unwrap_err();
+    let (line, error_column, _) = location(Some(error));
+    assert_eq!((line, error_column), (8, column + 25));
+    assert_eq!(reader.ids.len(), COUNT as usize);
+}
@@ -0,0 +1,90 @@
+use iso_10303::express::{parser, Model};
+
+const SCHEMA: &str = "
+SCHEMA unique;
+
+TYPE label = STRING;
+END_TYPE;
+
+ENTITY measure;
+    amount : NUMBER;
+UNIQUE
+    ur1 : amount;
+END_ENTITY;
+
+ENTITY offset;
+    distance : REAL;
+UNIQUE
+    ur1 : distance;
+END_ENTITY;
+
+ENTITY group;
+    members : SET [1:?] OF INTEGER;
+    counts : BAG [1:?] OF INTEGER;
+    order : LIST [1:?] OF INTEGER;
+UNIQUE
+    ur1 : members;
+    ur2 : counts;
+    ur3 : order;
+END_ENTITY;
+
+ENTITY item;
+    name : OPTIONAL label;
+    alias : OPTIONAL STRING;
+DERIVE
+    key : STRING := NVL(name, alias);
+UNIQUE
+    ur1 : key;
+END_ENTITY;
+
+END_SCHEMA;
+";
+
+const DATA: &str = "ISO-10303-21;
+HEADER;
+FILE_DESCRIPTION((''), '2;1');
+FILE_NAME('', '', (''), (''), '', '', '');
+FILE_SCHEMA(('UNIQUE'));
+ENDSEC;
+DATA;
+#1 = MEASURE(1);
+#2 = MEASURE(1.0);
+#3 = MEASURE(1.5);
+#4 = OFFSET(0.0);
+#5 = OFFSET(-0.0);
+#6 = GROUP((1, 2, 3), (1, 1, 2), (1, 2));
+#7 = GROUP((3, 1, 2), (2, 1, 1), (2, 1));
+#8 = ITEM('x', $);
+#9 = ITEM($, 'x');
+#10 = ITEM($, 'y');
+ENDSEC;
+END-ISO-10303-21;
+";
+
+#[test]
+fn unique_compares_with_express_equality() {
+    let schema = parser::schema().parse(SCHEMA.as_bytes()).unwrap();
+    let model = Model::read_from(&schema, DATA.as_bytes()).unwrap();
+    let report = model.validate();
+    let duplicates = report
+        .duplicates
+        .iter()
+        .map(|duplicate| {
+            (
+                duplicate.entity.as_str(),
+                duplicate.label.as_deref(),
+                duplicate.ids.clone(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        duplicates,
+        vec![
+            ("measure", Some("ur1"), vec![1, 2]),
+            ("offset", Some("ur1"), vec![4, 5]),
+            ("group", Some("ur1"), vec![6, 7]),
+            ("group", Some("ur2"), vec![6, 7]),
+            ("item", Some("ur1"), vec![8, 9]),
+        ]
+    );
+}
@@ -0,0 +1,94 @@
+use iso_10303::express::{parser, EvalError, Model, RuleViolation};
+
+const SCHEMA: &str = "
+SCHEMA rules;
+
+TYPE positive = INTEGER;
+WHERE
+    wr1 : SELF > 0;
+END_TYPE;
+
+ENTITY range;
+    low : INTEGER;
+    high : INTEGER;
+    step : OPTIONAL positive;
+WHERE
+    ordered : low <= high;
+    small : high - low < 100;
+    stepped : (high - low) MOD step = 0;
+END_ENTITY;
+
+ENTITY probe;
+    value : INTEGER;
+WHERE
+    undefined : value > limit;
+END_ENTITY;
+
+END_SCHEMA;
+";
+
+const DATA: &str = "ISO-10303-21;
+HEADER;
+FILE_DESCRIPTION((''), '2;1');
+FILE_NAME('', '', (''), (''), '', '', '');
+FILE_SCHEMA(('RULES'));
+ENDSEC;
+DATA;
+#1 = RANGE(0, 10, 5);
+#2 = RANGE(10, 0, $);
+#3 = RANGE(0, 200, -4);
+#4 = PROBE(1);
+ENDSEC;
+END-ISO-10303-21;
+";
+
+fn violation(id: i64, declaration: &str, attribute: Option<&str>, label: &str) -> RuleViolation {
+    RuleViolation {
+        id,
+        declaration: declaration.to_string(),
+        attribute: attribute.map(ToString::to_string),
+        label: Some(label.to_string()),
+        error: None,
+    }
+}
+
+#[test]
+fn false_rules_fail_and_unknown_rules_hold() {
+    let schema = parser::schema().parse(SCHEMA.as_bytes()).unwrap();
+    let model = Model::read_from(&schema, DATA.as_bytes()).unwrap();
+    let report = model.validate();
+    // the rule of #2 over its unset step is UNKNOWN, which does not violate it
+    assert_eq!(
+        report.failed,
+        [
+            violation(2, "range", None, "ordered"),
+            violation(3, "range", None, "small"),
+            violation(3, "positive", Some("step"), "wr1"),
+        ]
+    );
+    assert_eq!(
+        report.failed[2].to_string(),
+        "#3: positive.wr1 of attribute step is violated"
+    );
+    assert!(!report.is_valid());
+}
+
+#[test]
+fn rules_which_can_not_be_evaluated_are_indeterminate() {
+    let schema = parser::schema().parse(SCHEMA.as_bytes()).unwrap();
+    let model = Model::read_from(&schema, DATA.as_bytes()).unwrap();
+    let report = model.validate();
+    assert_eq!(
+        report.indeterminate,
+        [RuleViolation {
+            error: Some(EvalError::UnknownName("limit".to_string())),
+            ..violation(4, "probe", None, "undefined")
+        }]
+    );
+    assert_eq!(
+        report.indeterminate[0].to_string(),
+        "#4: probe.undefined can not be evaluated: limit is not defined"
+    );
+    // each rule is checked once per instance, and the type rule once per value
+    assert_eq!(report.checked, 3 * 3 + 1 + 2);
+}